@@ -1,20 +1,91 @@
+use serde::Serialize;
 use std::collections::HashMap;
 use std::path::Path;
 
 use crate::app::{Thought, ThoughtType, Suggestion, ActionType, Priority};
 
+/// Share of comment lines below which a file gets a "low comment density" suggestion,
+/// for languages without their own threshold
+const DEFAULT_MIN_COMMENT_RATIO: f32 = 0.1;
+
+/// Function bodies are found by brace matching unless a language says otherwise
+const DEFAULT_BLOCK_STYLE: BlockStyle = BlockStyle::Braces(&['"', '\'']);
+
+/// Functions longer than this many lines get a "split long functions" suggestion
+const LONG_FUNCTION_LINES: usize = 50;
+
+/// Lines longer than this many characters get a "break long lines" suggestion, for
+/// languages without their own limit
+const LONG_LINE_CHARS: usize = 120;
+
 pub struct CodeAnalyzer {
     language_patterns: HashMap<String, LanguageConfig>,
+    suppressed_thought_types: Vec<ThoughtType>,
+    /// Replaces every language's line length limit when set
+    max_line_length: Option<usize>,
 }
 
 #[derive(Clone)]
 struct LanguageConfig {
     file_extensions: Vec<String>,
     comment_patterns: Vec<String>,
+    /// Idiomatic code in some languages is sparsely commented, so each sets its own bar
+    min_comment_ratio: f32,
+    /// Conventions differ, e.g. Java and Kotlin style guides allow 140 characters
+    max_line_length: usize,
+    block_style: BlockStyle,
     keywords: Vec<String>,
     common_patterns: Vec<Pattern>,
 }
 
+/// How a language delimits function bodies
+#[derive(Clone, Copy, Debug)]
+enum BlockStyle {
+    /// Bodies between braces; the listed characters open string literals
+    Braces(&'static [char]),
+    /// Bodies indented below their `def`, as in Python
+    Indentation,
+}
+
+/// Inclusive, 1-based lines of a function, from its header to its last line
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct FunctionSpan {
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// What the local checks measure in a file before deciding what to say about it
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct CodeMetrics {
+    pub line_count: usize,
+    /// The line length limit applied, from the language or the global override
+    pub max_line_length: usize,
+    /// 1-based numbers of lines over `max_line_length` characters
+    pub long_lines: Vec<usize>,
+    pub comment_lines: usize,
+    /// Comment lines over all lines, 0 for an empty file
+    pub comment_ratio: f32,
+    /// Deepest bracket nesting, a rough stand-in for complexity
+    pub max_nesting: usize,
+    /// Functions over 50 lines, in file order
+    pub long_functions: Vec<FunctionSpan>,
+}
+
+/// The result of the local checks on one file: its metrics and the thoughts they raised
+#[derive(Clone, Debug, Serialize)]
+pub struct AnalysisReport {
+    /// Language detected from the file extension, if it has its own checks
+    pub language: Option<String>,
+    pub metrics: CodeMetrics,
+    pub thoughts: Vec<Thought>,
+}
+
+impl FunctionSpan {
+    fn line_count(&self) -> usize {
+        self.end_line - self.start_line + 1
+    }
+}
+
 #[derive(Clone)]
 struct Pattern {
     name: String,
@@ -31,10 +102,18 @@ enum PatternSeverity {
     Critical,
 }
 
+impl Default for CodeAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CodeAnalyzer {
     pub fn new() -> Self {
         let mut analyzer = Self {
             language_patterns: HashMap::new(),
+            suppressed_thought_types: Vec::new(),
+            max_line_length: None,
         };
 
         analyzer.init_language_configs();
@@ -46,6 +125,10 @@ impl CodeAnalyzer {
         self.language_patterns.insert("rust".to_string(), LanguageConfig {
             file_extensions: vec!["rs".to_string()],
             comment_patterns: vec!["//".to_string(), "/*".to_string()],
+            min_comment_ratio: 0.05,
+            max_line_length: LONG_LINE_CHARS,
+            // Single quotes are char literals or lifetimes, handled separately
+            block_style: BlockStyle::Braces(&['"']),
             keywords: vec![
                 "fn", "let", "mut", "const", "static", "struct", "enum", "impl", "trait",
                 "mod", "use", "pub", "async", "await", "match", "if", "else", "for",
@@ -77,6 +160,9 @@ impl CodeAnalyzer {
         self.language_patterns.insert("python".to_string(), LanguageConfig {
             file_extensions: vec!["py".to_string()],
             comment_patterns: vec!["#".to_string()],
+            min_comment_ratio: 0.08,
+            max_line_length: LONG_LINE_CHARS,
+            block_style: BlockStyle::Indentation,
             keywords: vec![
                 "def", "class", "import", "from", "as", "if", "elif", "else", "for",
                 "while", "try", "except", "finally", "with", "lambda", "return",
@@ -102,6 +188,9 @@ impl CodeAnalyzer {
         self.language_patterns.insert("javascript".to_string(), LanguageConfig {
             file_extensions: vec!["js".to_string(), "ts".to_string(), "jsx".to_string(), "tsx".to_string()],
             comment_patterns: vec!["//".to_string(), "/*".to_string()],
+            min_comment_ratio: DEFAULT_MIN_COMMENT_RATIO,
+            max_line_length: LONG_LINE_CHARS,
+            block_style: BlockStyle::Braces(&['"', '\'', '`']),
             keywords: vec![
                 "function", "var", "let", "const", "class", "extends", "implements",
                 "interface", "if", "else", "for", "while", "do", "switch", "case",
@@ -123,6 +212,46 @@ impl CodeAnalyzer {
                 },
             ],
         });
+
+        // Java/Kotlin configuration; their style guides allow wider lines
+        self.language_patterns.insert("java".to_string(), LanguageConfig {
+            file_extensions: vec!["java".to_string(), "kt".to_string(), "kts".to_string()],
+            comment_patterns: vec!["//".to_string(), "/*".to_string()],
+            min_comment_ratio: DEFAULT_MIN_COMMENT_RATIO,
+            max_line_length: 140,
+            block_style: BlockStyle::Braces(&['"', '\'']),
+            keywords: vec![
+                "class", "interface", "enum", "fun", "val", "var", "public", "private",
+                "protected", "static", "final", "if", "else", "for", "while", "when",
+                "switch", "case", "return", "break", "continue", "try", "catch", "finally",
+                "throw", "import", "package"
+            ].into_iter().map(|s| s.to_string()).collect(),
+            common_patterns: Vec::new(),
+        });
+
+        // Go configuration; gofmt sets no line length, so only very long lines are flagged
+        self.language_patterns.insert("go".to_string(), LanguageConfig {
+            file_extensions: vec!["go".to_string()],
+            comment_patterns: vec!["//".to_string(), "/*".to_string()],
+            min_comment_ratio: DEFAULT_MIN_COMMENT_RATIO,
+            max_line_length: 160,
+            block_style: BlockStyle::Braces(&['"', '\'', '`']),
+            keywords: vec![
+                "func", "var", "const", "type", "struct", "interface", "map", "chan",
+                "go", "defer", "select", "if", "else", "for", "range", "switch", "case",
+                "return", "break", "continue", "package", "import"
+            ].into_iter().map(|s| s.to_string()).collect(),
+            common_patterns: Vec::new(),
+        });
+    }
+
+    pub fn set_suppressed_thought_types(&mut self, thought_types: Vec<ThoughtType>) {
+        self.suppressed_thought_types = thought_types;
+    }
+
+    /// Flags lines over `max_line_length` in every language instead of each language's own limit
+    pub fn set_max_line_length(&mut self, max_line_length: Option<usize>) {
+        self.max_line_length = max_line_length;
     }
 
     pub fn detect_language(&self, file_path: &str) -> Option<String> {
@@ -138,7 +267,13 @@ impl CodeAnalyzer {
         None
     }
 
+    /// Thoughts from the local checks, without the metrics behind them
     pub fn analyze_code_patterns(&self, code: &str, file_path: Option<&str>) -> Vec<Thought> {
+        self.analyze(code, file_path).thoughts
+    }
+
+    /// Runs the local checks on `code`, returning what was measured along with the thoughts
+    pub fn analyze(&self, code: &str, file_path: Option<&str>) -> AnalysisReport {
         let mut thoughts = Vec::new();
 
         // Detect language
@@ -148,40 +283,70 @@ impl CodeAnalyzer {
             None
         };
 
+        let config = language.as_ref().and_then(|lang| self.language_patterns.get(lang));
+        let block_style = config.map_or(DEFAULT_BLOCK_STYLE, |c| c.block_style);
+        let max_line_length = self
+            .max_line_length
+            .unwrap_or_else(|| config.map_or(LONG_LINE_CHARS, |c| c.max_line_length));
+        let metrics = self.measure(code, block_style, max_line_length);
+
         // Basic code metrics
-        thoughts.extend(self.analyze_basic_metrics(code, file_path));
+        let min_comment_ratio = config.map_or(DEFAULT_MIN_COMMENT_RATIO, |c| c.min_comment_ratio);
+        thoughts.extend(self.analyze_basic_metrics(&metrics, file_path, min_comment_ratio));
 
         // Language-specific analysis
-        if let Some(lang) = language {
-            if let Some(config) = self.language_patterns.get(&lang) {
-                thoughts.extend(self.analyze_language_patterns(code, config, file_path));
-            }
+        if let Some(config) = config {
+            thoughts.extend(self.analyze_language_patterns(code, config, file_path));
         }
 
         // General code quality analysis
-        thoughts.extend(self.analyze_general_quality(code, file_path));
+        thoughts.extend(self.analyze_general_quality(&metrics, file_path));
 
-        thoughts
+        thoughts.retain(|t| !self.suppressed_thought_types.contains(&t.thought_type));
+        AnalysisReport { language, metrics, thoughts }
     }
 
-    fn analyze_basic_metrics(&self, code: &str, file_path: Option<&str>) -> Vec<Thought> {
-        let mut thoughts = Vec::new();
-
+    fn measure(&self, code: &str, block_style: BlockStyle, max_line_length: usize) -> CodeMetrics {
         let lines = code.lines().collect::<Vec<_>>();
         let line_count = lines.len();
-        let _char_count = code.len();
-        let _word_count = code.split_whitespace().count();
+
+        let long_lines = lines.iter()
+            .enumerate()
+            .filter(|(_, line)| line.len() > max_line_length)
+            .map(|(i, _)| i + 1)
+            .collect();
+
+        let comment_lines = lines.iter()
+            .filter(|line| {
+                let trimmed = line.trim();
+                trimmed.starts_with("//") || trimmed.starts_with('#') || trimmed.starts_with("/*")
+            })
+            .count();
+
+        let comment_ratio = if line_count > 0 {
+            comment_lines as f32 / line_count as f32
+        } else {
+            0.0
+        };
+
+        CodeMetrics {
+            line_count,
+            max_line_length,
+            long_lines,
+            comment_lines,
+            comment_ratio,
+            max_nesting: self.calculate_max_nesting_level(code),
+            long_functions: self.find_long_functions(code, block_style),
+        }
+    }
+
+    fn analyze_basic_metrics(&self, metrics: &CodeMetrics, file_path: Option<&str>, min_comment_ratio: f32) -> Vec<Thought> {
+        let mut thoughts = Vec::new();
+        let line_count = metrics.line_count;
 
         // File size analysis
         if line_count > 500 {
             thoughts.push(Thought {
-                id: uuid::Uuid::new_v4().to_string(),
-                timestamp: chrono::Utc::now(),
-                thought_type: ThoughtType::Warning,
-                content: format!("Large file detected ({} lines). Consider breaking it into smaller modules.", line_count),
-                file_path: file_path.map(|s| s.to_string()),
-                line_number: None,
-                confidence: 0.8,
                 suggestions: vec![
                     Suggestion {
                         id: uuid::Uuid::new_v4().to_string(),
@@ -190,25 +355,24 @@ impl CodeAnalyzer {
                         code_snippet: None,
                         action_type: ActionType::Refactor,
                         priority: Priority::Medium,
+                        target_range: None,
                     }
                 ],
+                rule_id: Some("large_file".to_string()),
+                ..Thought::new(
+                    ThoughtType::Warning,
+                    format!("Large file detected ({} lines). Consider breaking it into smaller modules.", line_count),
+                    file_path.map(|s| s.to_string()),
+                    None,
+                    0.8,
+                )
             });
         }
 
         // Long lines analysis
-        let long_lines: Vec<_> = lines.iter().enumerate()
-            .filter(|(_, line)| line.len() > 120)
-            .collect();
-
-        if !long_lines.is_empty() {
+        let long_lines = &metrics.long_lines;
+        if let Some(&first_long_line) = long_lines.first() {
             thoughts.push(Thought {
-                id: uuid::Uuid::new_v4().to_string(),
-                timestamp: chrono::Utc::now(),
-                thought_type: ThoughtType::Style,
-                content: format!("Found {} lines longer than 120 characters. Consider breaking them up for better readability.", long_lines.len()),
-                file_path: file_path.map(|s| s.to_string()),
-                line_number: long_lines.first().map(|(i, _)| i + 1),
-                confidence: 0.7,
                 suggestions: vec![
                     Suggestion {
                         id: uuid::Uuid::new_v4().to_string(),
@@ -217,34 +381,24 @@ impl CodeAnalyzer {
                         code_snippet: None,
                         action_type: ActionType::Refactor,
                         priority: Priority::Low,
+                        target_range: Some((first_long_line, first_long_line)),
                     }
                 ],
+                rule_id: Some("long_lines".to_string()),
+                ..Thought::new(
+                    ThoughtType::Style,
+                    format!("Found {} lines longer than {} characters. Consider breaking them up for better readability.", long_lines.len(), metrics.max_line_length),
+                    file_path.map(|s| s.to_string()),
+                    Some(first_long_line),
+                    0.7,
+                )
             });
         }
 
         // Comment density analysis
-        let comment_lines = lines.iter()
-            .filter(|line| {
-                let trimmed = line.trim();
-                trimmed.starts_with("//") || trimmed.starts_with('#') || trimmed.starts_with("/*")
-            })
-            .count();
-
-        let comment_ratio = if line_count > 0 {
-            comment_lines as f32 / line_count as f32
-        } else {
-            0.0
-        };
-
-        if comment_ratio < 0.1 && line_count > 50 {
+        let comment_ratio = metrics.comment_ratio;
+        if comment_ratio < min_comment_ratio && line_count > 50 {
             thoughts.push(Thought {
-                id: uuid::Uuid::new_v4().to_string(),
-                timestamp: chrono::Utc::now(),
-                thought_type: ThoughtType::Suggesting,
-                content: format!("Low comment density ({:.1}%). Consider adding more documentation for complex logic.", comment_ratio * 100.0),
-                file_path: file_path.map(|s| s.to_string()),
-                line_number: None,
-                confidence: 0.6,
                 suggestions: vec![
                     Suggestion {
                         id: uuid::Uuid::new_v4().to_string(),
@@ -253,8 +407,17 @@ impl CodeAnalyzer {
                         code_snippet: None,
                         action_type: ActionType::Insert,
                         priority: Priority::Medium,
+                        target_range: None,
                     }
                 ],
+                rule_id: Some("low_comment_density".to_string()),
+                ..Thought::new(
+                    ThoughtType::Suggesting,
+                    format!("Low comment density ({:.1}%). Consider adding more documentation for complex logic.", comment_ratio * 100.0),
+                    file_path.map(|s| s.to_string()),
+                    None,
+                    0.6,
+                )
             });
         }
 
@@ -291,13 +454,6 @@ impl CodeAnalyzer {
                     };
 
                     thoughts.push(Thought {
-                        id: uuid::Uuid::new_v4().to_string(),
-                        timestamp: chrono::Utc::now(),
-                        thought_type,
-                        content: format!("{} (found {} occurrences)", pattern.suggestion, matches.len()),
-                        file_path: file_path.map(|s| s.to_string()),
-                        line_number: None, // TODO: Calculate line number from match position
-                        confidence,
                         suggestions: vec![
                             Suggestion {
                                 id: uuid::Uuid::new_v4().to_string(),
@@ -306,8 +462,17 @@ impl CodeAnalyzer {
                                 code_snippet: None,
                                 action_type: ActionType::Fix,
                                 priority,
+                                target_range: None,
                             }
                         ],
+                        rule_id: Some(pattern.name.clone()),
+                        ..Thought::new(
+                            thought_type,
+                            format!("{} (found {} occurrences)", pattern.suggestion, matches.len()),
+                            file_path.map(|s| s.to_string()),
+                            Some(code[..matches[0].start()].matches('\n').count() + 1),
+                            confidence,
+                        )
                     });
                 }
             }
@@ -316,20 +481,13 @@ impl CodeAnalyzer {
         thoughts
     }
 
-    fn analyze_general_quality(&self, code: &str, file_path: Option<&str>) -> Vec<Thought> {
+    fn analyze_general_quality(&self, metrics: &CodeMetrics, file_path: Option<&str>) -> Vec<Thought> {
         let mut thoughts = Vec::new();
 
         // Complexity analysis (simplified)
-        let nesting_level = self.calculate_max_nesting_level(code);
+        let nesting_level = metrics.max_nesting;
         if nesting_level > 4 {
             thoughts.push(Thought {
-                id: uuid::Uuid::new_v4().to_string(),
-                timestamp: chrono::Utc::now(),
-                thought_type: ThoughtType::Warning,
-                content: format!("High nesting level detected ({}). Consider refactoring to reduce complexity.", nesting_level),
-                file_path: file_path.map(|s| s.to_string()),
-                line_number: None,
-                confidence: 0.8,
                 suggestions: vec![
                     Suggestion {
                         id: uuid::Uuid::new_v4().to_string(),
@@ -338,22 +496,31 @@ impl CodeAnalyzer {
                         code_snippet: None,
                         action_type: ActionType::Refactor,
                         priority: Priority::Medium,
+                        target_range: None,
                     }
                 ],
+                rule_id: Some("deep_nesting".to_string()),
+                ..Thought::new(
+                    ThoughtType::Warning,
+                    format!("High nesting level detected ({}). Consider refactoring to reduce complexity.", nesting_level),
+                    file_path.map(|s| s.to_string()),
+                    None,
+                    0.8,
+                )
             });
         }
 
-        // Function length analysis (simplified)
-        let long_functions = self.find_long_functions(code);
-        if !long_functions.is_empty() {
+        // Function length analysis
+        let long_functions = &metrics.long_functions;
+        if let Some(first) = long_functions.first() {
+            let locations = long_functions
+                .iter()
+                .take(3)
+                .map(|f| format!("lines {}-{}", f.start_line, f.end_line))
+                .collect::<Vec<_>>()
+                .join(", ");
+
             thoughts.push(Thought {
-                id: uuid::Uuid::new_v4().to_string(),
-                timestamp: chrono::Utc::now(),
-                thought_type: ThoughtType::Suggesting,
-                content: format!("Found {} potentially long functions. Consider breaking them into smaller, focused functions.", long_functions.len()),
-                file_path: file_path.map(|s| s.to_string()),
-                line_number: None,
-                confidence: 0.7,
                 suggestions: vec![
                     Suggestion {
                         id: uuid::Uuid::new_v4().to_string(),
@@ -362,8 +529,21 @@ impl CodeAnalyzer {
                         code_snippet: None,
                         action_type: ActionType::Refactor,
                         priority: Priority::Low,
+                        target_range: Some((first.start_line, first.end_line)),
                     }
                 ],
+                rule_id: Some("long_functions".to_string()),
+                ..Thought::new(
+                    ThoughtType::Suggesting,
+                    format!(
+                        "Found {} potentially long functions ({}). Consider breaking them into smaller, focused functions.",
+                        long_functions.len(),
+                        locations
+                    ),
+                    file_path.map(|s| s.to_string()),
+                    Some(first.start_line),
+                    0.7,
+                )
             });
         }
 
@@ -398,40 +578,13 @@ impl CodeAnalyzer {
         max_level
     }
 
-    fn find_long_functions(&self, code: &str) -> Vec<String> {
-        // Simplified function detection - this would need to be more sophisticated for real use
-        let mut functions = Vec::new();
-        let lines: Vec<&str> = code.lines().collect();
-
-        let mut in_function = false;
-        let mut function_start = 0;
-        let mut brace_count = 0;
-
-        for (i, line) in lines.iter().enumerate() {
-            let trimmed = line.trim();
-
-            // Simple function detection (very basic)
-            if (trimmed.contains("fn ") || trimmed.contains("function ") || trimmed.contains("def "))
-                && trimmed.contains("(") {
-                in_function = true;
-                function_start = i;
-                brace_count = 0;
-            }
-
-            if in_function {
-                brace_count += line.matches('{').count() as i32;
-                brace_count -= line.matches('}').count() as i32;
-
-                if brace_count <= 0 && i > function_start {
-                    let function_length = i - function_start + 1;
-                    if function_length > 50 {  // Consider functions longer than 50 lines as long
-                        functions.push(format!("Function at line {} ({} lines)", function_start + 1, function_length));
-                    }
-                    in_function = false;
-                }
-            }
-        }
-
+    fn find_long_functions(&self, code: &str, block_style: BlockStyle) -> Vec<FunctionSpan> {
+        let mut functions = match block_style {
+            BlockStyle::Braces(quotes) => find_brace_functions(code, quotes),
+            BlockStyle::Indentation => find_indented_functions(code),
+        };
+        functions.retain(|f| f.line_count() > LONG_FUNCTION_LINES);
+        functions.sort_by_key(|f| f.start_line);
         functions
     }
 
@@ -471,4 +624,299 @@ impl CodeAnalyzer {
 
         summary
     }
-}
\ No newline at end of file
+}
+
+fn is_function_header(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    !trimmed.starts_with("//")
+        && !trimmed.starts_with('*')
+        && (trimmed.contains("fn ") || trimmed.contains("function ") || trimmed.contains("func "))
+        && trimmed.contains('(')
+}
+
+/// Finds functions whose bodies are brace-delimited, ignoring braces inside comments
+/// and string or char literals. A body opens at the first `{` after a function header
+/// and closes at its matching `}`; a `;` first means the header was only a declaration.
+fn find_brace_functions(code: &str, quotes: &[char]) -> Vec<FunctionSpan> {
+    let headers: std::collections::HashSet<usize> = code
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| is_function_header(line))
+        .map(|(i, _)| i + 1)
+        .collect();
+
+    let chars: Vec<char> = code.chars().collect();
+    let mut functions = Vec::new();
+    // Header line of each open function body and the brace depth it opened at
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut pending = headers.contains(&1).then_some(1);
+    let (mut line, mut depth, mut parens) = (1, 0, 0usize);
+    let mut i = 0;
+
+    // Moves past `len` characters of a comment or literal, counting the lines it spans
+    let skip = |i: &mut usize, len: usize, line: &mut usize| {
+        let end = (*i + len).min(chars.len());
+        *line += chars[*i..end].iter().filter(|c| **c == '\n').count();
+        *i = end;
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let rest = &chars[i..];
+
+        match c {
+            '\n' => {
+                line += 1;
+                if headers.contains(&line) {
+                    pending = Some(line);
+                }
+            }
+            '/' if rest.get(1) == Some(&'/') => {
+                let len = rest.iter().position(|c| *c == '\n').unwrap_or(rest.len());
+                skip(&mut i, len, &mut line);
+                continue;
+            }
+            '/' if rest.get(1) == Some(&'*') => {
+                let len = rest[2..].windows(2).position(|w| w == ['*', '/']).map_or(rest.len(), |p| p + 4);
+                skip(&mut i, len, &mut line);
+                continue;
+            }
+            // Raw strings such as r#"{"#, which can't contain escapes
+            'r' if (i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '_'))
+                && matches!(rest.get(1), Some('#') | Some('"')) =>
+            {
+                let hashes = rest[1..].iter().take_while(|c| **c == '#').count();
+                if rest.get(1 + hashes) == Some(&'"') {
+                    let closing: Vec<char> = std::iter::once('"').chain(std::iter::repeat_n('#', hashes)).collect();
+                    let body = 2 + hashes;
+                    let len = rest[body..]
+                        .windows(closing.len())
+                        .position(|w| w == closing.as_slice())
+                        .map_or(rest.len(), |p| body + p + closing.len());
+                    skip(&mut i, len, &mut line);
+                    continue;
+                }
+            }
+            c if quotes.contains(&c) => {
+                let mut end = 1;
+                while end < rest.len() && rest[end] != c {
+                    end += if rest[end] == '\\' { 2 } else { 1 };
+                }
+                skip(&mut i, end + 1, &mut line);
+                continue;
+            }
+            // Char literals like '{' or '\n'; anything else is a lifetime
+            '\'' => {
+                let len = match rest.get(1) {
+                    Some('\\') => rest.iter().skip(2).position(|c| *c == '\'').map(|p| p + 3),
+                    Some(_) if rest.get(2) == Some(&'\'') => Some(3),
+                    _ => None,
+                };
+                if let Some(len) = len {
+                    skip(&mut i, len, &mut line);
+                    continue;
+                }
+            }
+            '(' | '[' => parens += 1,
+            ')' | ']' => parens = parens.saturating_sub(1),
+            ';' if parens == 0 => pending = None,
+            '{' => {
+                depth += 1;
+                if let Some(start_line) = pending.take() {
+                    open.push((start_line, depth));
+                }
+            }
+            '}' => {
+                if open.last().is_some_and(|(_, opened_at)| *opened_at == depth) {
+                    let (start_line, _) = open.pop().unwrap_or_default();
+                    functions.push(FunctionSpan { start_line, end_line: line });
+                }
+                depth = depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    functions
+}
+
+/// Finds Python functions: each `def` runs until the next code line indented no
+/// deeper than it. Blank lines, comments and the inside of triple-quoted strings
+/// don't end a function.
+fn find_indented_functions(code: &str) -> Vec<FunctionSpan> {
+    let mut functions = Vec::new();
+    // Header line and indentation of each function still open
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut last_code_line = 0;
+    let mut docstring: Option<&str> = None;
+
+    for (i, line) in code.lines().enumerate() {
+        let line_number = i + 1;
+        let in_string = docstring.is_some();
+        docstring = triple_quote_state(line, docstring);
+
+        let trimmed = line.trim_start();
+        if in_string {
+            last_code_line = line_number;
+            continue;
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let indent = line.len() - trimmed.len();
+        while let Some(&(start_line, def_indent)) = open.last() {
+            if indent > def_indent {
+                break;
+            }
+            open.pop();
+            functions.push(FunctionSpan { start_line, end_line: last_code_line });
+        }
+
+        if trimmed.starts_with("def ") || trimmed.starts_with("async def ") {
+            open.push((line_number, indent));
+        }
+        last_code_line = line_number;
+    }
+
+    while let Some((start_line, _)) = open.pop() {
+        functions.push(FunctionSpan { start_line, end_line: last_code_line });
+    }
+
+    functions
+}
+
+/// Which triple-quoted string, if any, is still open after `line`
+fn triple_quote_state(line: &str, mut open: Option<&'static str>) -> Option<&'static str> {
+    let mut rest = line;
+    loop {
+        match open {
+            Some(delimiter) => match rest.find(delimiter) {
+                Some(pos) => {
+                    rest = &rest[pos + 3..];
+                    open = None;
+                }
+                None => return open,
+            },
+            None => {
+                let next = ["\"\"\"", "'''"]
+                    .into_iter()
+                    .filter_map(|d| rest.find(d).map(|pos| (pos, d)))
+                    .min();
+                match next {
+                    Some((pos, delimiter)) => {
+                        rest = &rest[pos + 3..];
+                        open = Some(delimiter);
+                    }
+                    None => return None,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 100 lines of which `comments` are comments
+    fn code_with_comments(comment: &str, comments: usize) -> String {
+        (0..100)
+            .map(|i| if i < comments { format!("{} note", comment) } else { "x = 1".to_string() })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn flags_low_comment_density(code: &str, file_path: &str) -> bool {
+        CodeAnalyzer::new()
+            .analyze_code_patterns(code, Some(file_path))
+            .iter()
+            .any(|t| t.rule_id.as_deref() == Some("low_comment_density"))
+    }
+
+    #[test]
+    fn test_comment_density_threshold_per_language() {
+        // 6% comments is enough for Rust (5%) but not for Python (8%)
+        assert!(!flags_low_comment_density(&code_with_comments("//", 6), "lib.rs"));
+        assert!(flags_low_comment_density(&code_with_comments("#", 6), "app.py"));
+        assert!(!flags_low_comment_density(&code_with_comments("#", 9), "app.py"));
+
+        // Unknown languages use the default of 10%
+        assert!(flags_low_comment_density(&code_with_comments("//", 9), "main.go"));
+    }
+
+    #[test]
+    fn test_find_functions_rust_string_braces() {
+        let body = "    x += 1;\n".repeat(55);
+        let code = format!(
+            "fn short() {{\n    let s = \"}}}}\";\n    let c = '{{';\n}}\n\n/// Doc with {{\nfn long<'a>(x: &'a str) {{\n    let r = r#\"}}\"#; // }}\n{}}}\n\nfn declared();\n",
+            body
+        );
+
+        let functions = find_brace_functions(&code, &['"']);
+        assert_eq!(functions, vec![
+            FunctionSpan { start_line: 1, end_line: 4 },
+            FunctionSpan { start_line: 7, end_line: 64 },
+        ]);
+
+        let long = CodeAnalyzer::new().find_long_functions(&code, BlockStyle::Braces(&['"']));
+        assert_eq!(long, vec![FunctionSpan { start_line: 7, end_line: 64 }]);
+    }
+
+    #[test]
+    fn test_find_functions_python_indentation() {
+        let body = "    x += 1\n".repeat(55);
+        let code = format!(
+            "def short():\n    return {{'a': 1}}\n\nclass A:\n    def long(self):\n        \"\"\"Docs\n\nstill docs\"\"\"\n{}\n    # trailing comment\n    def other(self):\n        pass\n",
+            body.replace("    ", "        ")
+        );
+
+        let functions = find_indented_functions(&code);
+        assert_eq!(functions, vec![
+            FunctionSpan { start_line: 1, end_line: 2 },
+            FunctionSpan { start_line: 5, end_line: 63 },
+            FunctionSpan { start_line: 66, end_line: 67 },
+        ]);
+
+        let thoughts = CodeAnalyzer::new().analyze_code_patterns(&code, Some("a.py"));
+        let long = thoughts.iter().find(|t| t.rule_id.as_deref() == Some("long_functions")).unwrap();
+        assert_eq!(long.line_number, Some(5));
+    }
+
+    #[test]
+    fn test_analysis_report_metrics() {
+        let code = format!("// header\nfn main() {{\n    let v = vec![(1, [2])];\n}}\n{}\n", "x".repeat(130));
+        let report = CodeAnalyzer::new().analyze(&code, Some("main.rs"));
+
+        assert_eq!(report.language.as_deref(), Some("rust"));
+        assert_eq!(report.metrics.line_count, 5);
+        assert_eq!(report.metrics.long_lines, vec![5]);
+        assert_eq!(report.metrics.comment_lines, 1);
+        assert!((report.metrics.comment_ratio - 0.2).abs() < f32::EPSILON);
+        assert_eq!(report.metrics.max_nesting, 4);
+        assert!(report.metrics.long_functions.is_empty());
+        assert!(report.thoughts.iter().any(|t| t.rule_id.as_deref() == Some("long_lines")));
+    }
+
+    #[test]
+    fn test_max_line_length_by_language() {
+        let code = format!("{}\n{}\n", "x".repeat(130), "y".repeat(150));
+
+        let rust = CodeAnalyzer::new().analyze(&code, Some("main.rs"));
+        assert_eq!(rust.metrics.max_line_length, 120);
+        assert_eq!(rust.metrics.long_lines, vec![1, 2]);
+
+        let java = CodeAnalyzer::new().analyze(&code, Some("Main.java"));
+        assert_eq!(java.metrics.max_line_length, 140);
+        assert_eq!(java.metrics.long_lines, vec![2]);
+        assert!(java.thoughts.iter().any(|t| t.content.contains("longer than 140 characters")));
+
+        // The global override wins over both
+        let mut analyzer = CodeAnalyzer::new();
+        analyzer.set_max_line_length(Some(200));
+        assert!(analyzer.analyze(&code, Some("main.rs")).metrics.long_lines.is_empty());
+        assert!(analyzer.analyze(&code, Some("Main.java")).metrics.long_lines.is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+use crate::error::{CocoError, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::app::{AiRequest, AiRequestType, Thought, ThoughtSource};
+
+/// Thoughts from earlier analyses, kept on disk as one JSON file per analyzed
+/// content so identical code isn't sent to the provider again in a later run
+pub struct ThoughtCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_age: Duration,
+    /// Size and write time of every entry on disk, by key
+    entries: HashMap<String, (u64, SystemTime)>,
+}
+
+pub fn get_cache_directory() -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .ok_or(CocoError::NoHomeDirectory)?;
+
+    Ok(home.join(".coco").join("cache"))
+}
+
+impl ThoughtCache {
+    /// Indexes the entries already in `dir`, dropping expired ones and the oldest
+    /// beyond `max_bytes`
+    pub fn open(dir: &Path, max_bytes: u64, max_age: Duration) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+
+        let mut entries = HashMap::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let (Some(key), Ok(metadata)) = (path.file_stem().and_then(|s| s.to_str()), entry.metadata()) else {
+                continue;
+            };
+            let written = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            entries.insert(key.to_string(), (metadata.len(), written));
+        }
+
+        let mut cache = Self { dir: dir.to_path_buf(), max_bytes, max_age, entries };
+        cache.evict(SystemTime::now());
+        tracing::debug!("Opened thought cache at {} with {} entries", dir.display(), cache.entries.len());
+        Ok(cache)
+    }
+
+    /// The cache key for `request` under `model` and the provider's `include_suggestions`,
+    /// or `None` for requests whose answer depends on more than what's sent
+    pub fn key(model: &str, include_suggestions: bool, request: &AiRequest) -> Option<String> {
+        if !matches!(request.request_type, AiRequestType::Analyze | AiRequestType::Optimize) || request.selection.is_some() {
+            return None;
+        }
+
+        let request_type = format!("{:?}", request.request_type);
+        let file_path = request.file_path.as_deref().unwrap_or_default();
+        let suggestions = include_suggestions.to_string();
+        let mut context: Vec<(&String, &String)> = request.context.iter().collect();
+        context.sort();
+        let hash = [model, &suggestions, &request_type, file_path, &request.content]
+            .into_iter()
+            .chain(context.into_iter().flat_map(|(name, value)| [name.as_str(), value.as_str()]))
+            .fold(FNV_OFFSET, |hash, part| fnv1a(fnv1a(hash, part.as_bytes()), &[0]));
+        Some(format!("{:016x}", hash))
+    }
+
+    /// The thoughts cached under `key`, marked as cached and given new ids
+    pub fn get(&mut self, key: &str) -> Option<Vec<Thought>> {
+        let (_, written) = *self.entries.get(key)?;
+        if is_expired(written, SystemTime::now(), self.max_age) {
+            self.remove(key);
+            return None;
+        }
+
+        let thoughts = std::fs::read_to_string(self.entry_path(key))
+            .ok()
+            .and_then(|json| serde_json::from_str::<Vec<Thought>>(&json).ok());
+        let Some(mut thoughts) = thoughts else {
+            tracing::warn!("Dropping unreadable thought cache entry {}", key);
+            self.remove(key);
+            return None;
+        };
+
+        for thought in &mut thoughts {
+            thought.id = uuid::Uuid::new_v4().to_string();
+            thought.timestamp = chrono::Utc::now();
+            thought.source = ThoughtSource::Cached;
+        }
+        Some(thoughts)
+    }
+
+    pub fn put(&mut self, key: &str, thoughts: &[Thought]) -> Result<()> {
+        let json = serde_json::to_string(thoughts)?;
+        std::fs::write(self.entry_path(key), &json)?;
+        self.entries.insert(key.to_string(), (json.len() as u64, SystemTime::now()));
+        self.evict(SystemTime::now());
+        Ok(())
+    }
+
+    /// Drops entries older than `max_age`, then the oldest until the rest fit in `max_bytes`
+    fn evict(&mut self, now: SystemTime) {
+        let expired: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, (_, written))| is_expired(*written, now, self.max_age))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            self.remove(&key);
+        }
+
+        let mut by_age: Vec<(SystemTime, String, u64)> = self
+            .entries
+            .iter()
+            .map(|(key, (size, written))| (*written, key.clone(), *size))
+            .collect();
+        by_age.sort();
+
+        let mut total: u64 = by_age.iter().map(|(_, _, size)| size).sum();
+        for (_, key, size) in by_age {
+            if total <= self.max_bytes {
+                break;
+            }
+            self.remove(&key);
+            total -= size;
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.entries.remove(key);
+        if let Err(e) = std::fs::remove_file(self.entry_path(key)) {
+            tracing::debug!("Failed to remove thought cache entry {}: {}", key, e);
+        }
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+fn is_expired(written: SystemTime, now: SystemTime, max_age: Duration) -> bool {
+    now.duration_since(written).is_ok_and(|age| age > max_age)
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a, chosen over `DefaultHasher` because its output has to stay the same across builds
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{Priority, ThoughtType};
+
+    fn request(request_type: AiRequestType, content: &str) -> AiRequest {
+        AiRequest {
+            id: "1".to_string(),
+            request_type,
+            content: content.to_string(),
+            file_path: Some("src/lib.rs".to_string()),
+            context: HashMap::new(),
+            priority: Priority::Medium,
+            selection: None,
+        }
+    }
+
+    fn thought(content: &str) -> Thought {
+        Thought {
+            id: "t".to_string(),
+            ..Thought::new(
+                ThoughtType::Warning,
+                content.to_string(),
+                Some("src/lib.rs".to_string()),
+                Some(3),
+                0.8,
+            )
+        }
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let analyze = request(AiRequestType::Analyze, "fn main() {}");
+        let key = ThoughtCache::key("model-a", true, &analyze).unwrap();
+
+        assert_eq!(ThoughtCache::key("model-a", true, &analyze), Some(key.clone()));
+        assert_ne!(ThoughtCache::key("model-b", true, &analyze), Some(key.clone()));
+        assert_ne!(ThoughtCache::key("model-a", false, &analyze), Some(key.clone()));
+        assert_ne!(ThoughtCache::key("model-a", true, &request(AiRequestType::Analyze, "fn main() { }")), Some(key.clone()));
+        assert_eq!(ThoughtCache::key("model-a", true, &request(AiRequestType::Explain, "fn main() {}")), None);
+
+        let mut with_context = analyze.clone();
+        with_context.context.insert("recent_change".to_string(), "lines 1-2".to_string());
+        assert_ne!(ThoughtCache::key("model-a", true, &with_context), Some(key));
+    }
+
+    #[test]
+    fn test_cache_round_trip_and_eviction() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = ThoughtCache::open(dir.path(), 10_000, Duration::from_secs(3600)).unwrap();
+
+        cache.put("a", &[thought("first")]).unwrap();
+        let cached = cache.get("a").unwrap();
+        assert_eq!(cached[0].content, "first");
+        assert_eq!(cached[0].source, ThoughtSource::Cached);
+        assert_ne!(cached[0].id, "t");
+
+        // Entries survive a restart
+        let mut cache = ThoughtCache::open(dir.path(), 10_000, Duration::from_secs(3600)).unwrap();
+        assert!(cache.get("a").is_some());
+
+        // Past the size bound the oldest entry goes first
+        cache.entries.get_mut("a").unwrap().1 = SystemTime::now() - Duration::from_secs(60);
+        cache.max_bytes = std::fs::metadata(cache.entry_path("a")).unwrap().len() + 10;
+        cache.put("b", &[thought("second")]).unwrap();
+        assert!(cache.get("a").is_none());
+        assert!(!cache.entry_path("a").exists());
+        assert!(cache.get("b").is_some());
+
+        // Expired entries are dropped when read
+        cache.entries.get_mut("b").unwrap().1 = SystemTime::now() - Duration::from_secs(7200);
+        assert!(cache.get("b").is_none());
+    }
+}
@@ -0,0 +1,262 @@
+use similar::{DiffTag, TextDiff};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Upper bound on all context values combined, to keep prompts small
+const MAX_CONTEXT_BYTES: usize = 2_000;
+const MAX_IMPORTS: usize = 15;
+const MAX_OUTLINE_ITEMS: usize = 8;
+/// Enclosing declarations named in a change summary before it says "and N more"
+const MAX_CHANGED_SCOPES: usize = 3;
+
+const IMPORT_PREFIXES: &[&str] = &[
+    "use ", "pub use ", "extern crate ", "import ", "from ", "#include", "require ", "using ",
+];
+
+const DECLARATION_PREFIXES: &[&str] = &[
+    "pub fn ", "fn ", "pub struct ", "struct ", "pub enum ", "enum ", "pub trait ", "trait ",
+    "impl ", "def ", "class ", "function ", "export function ", "export class ",
+    "export default ", "interface ", "func ", "type ",
+];
+
+/// Builds the context map sent alongside a file: its language, path relative to
+/// the project `root`, its imports, and short outlines of other recently analyzed files
+pub fn build_context(
+    path: &Path,
+    content: &str,
+    root: &Path,
+    related_files: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    let mut context = HashMap::new();
+    let mut budget = MAX_CONTEXT_BYTES;
+
+    if let Some(language) = language_for(path) {
+        insert_bounded(&mut context, &mut budget, "language", language.to_string());
+    }
+
+    insert_bounded(&mut context, &mut budget, "path", relative_to(path, root).display().to_string());
+
+    let imports = extract_imports(content);
+    if !imports.is_empty() {
+        insert_bounded(&mut context, &mut budget, "imports", imports.join("; "));
+    }
+
+    let mut related: Vec<(&String, &String)> = related_files
+        .iter()
+        .filter(|(other, _)| Path::new(other) != path)
+        .collect();
+    related.sort_by(|a, b| a.0.cmp(b.0));
+
+    let outlines: Vec<String> = related
+        .into_iter()
+        .filter_map(|(other, other_content)| {
+            let outline = outline(other_content);
+            if outline.is_empty() {
+                return None;
+            }
+            Some(format!("{} [{}]", relative_to(Path::new(other), root).display(), outline.join("; ")))
+        })
+        .collect();
+    if !outlines.is_empty() {
+        insert_bounded(&mut context, &mut budget, "related_files", outlines.join(" | "));
+    }
+
+    context
+}
+
+/// `path` relative to `root`; watcher paths like `./src/main.rs` are made absolute first
+fn relative_to(path: &Path, root: &Path) -> PathBuf {
+    std::path::absolute(path)
+        .ok()
+        .and_then(|absolute| absolute.strip_prefix(root).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+pub fn language_for(path: &Path) -> Option<&'static str> {
+    let language = match path.extension()?.to_str()?.to_lowercase().as_str() {
+        "rs" => "Rust",
+        "py" => "Python",
+        "js" | "jsx" => "JavaScript",
+        "ts" | "tsx" => "TypeScript",
+        "go" => "Go",
+        "java" => "Java",
+        "c" | "h" => "C",
+        "cpp" | "cc" | "cxx" | "hpp" => "C++",
+        "cs" => "C#",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "swift" => "Swift",
+        "kt" => "Kotlin",
+        "scala" => "Scala",
+        "ex" | "exs" => "Elixir",
+        "hs" => "Haskell",
+        "lua" => "Lua",
+        "dart" => "Dart",
+        "zig" => "Zig",
+        _ => return None,
+    };
+    Some(language)
+}
+
+pub fn extract_imports(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| IMPORT_PREFIXES.iter().any(|prefix| line.starts_with(prefix)) || line.contains("require("))
+        .take(MAX_IMPORTS)
+        .map(|line| line.trim_end_matches(';').to_string())
+        .collect()
+}
+
+/// Top-level declarations of a file, e.g. `fn main()` or `class Parser`
+pub fn outline(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with(char::is_whitespace))
+        .filter(|line| DECLARATION_PREFIXES.iter().any(|prefix| line.starts_with(prefix)))
+        .take(MAX_OUTLINE_ITEMS)
+        .map(|line| line.trim_end_matches(['{', ':', ' ']).to_string())
+        .collect()
+}
+
+/// One-line description of an edit, e.g. `3 lines added, 1 removed in fn parse`,
+/// with the first changed line (1-based) of `new`. `None` if nothing changed.
+pub fn change_summary(old: &str, new: &str) -> Option<(String, usize)> {
+    let diff = TextDiff::from_lines(old, new);
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let (mut added, mut removed) = (0, 0);
+    let mut first_line = None;
+    let mut scopes: Vec<String> = Vec::new();
+
+    for op in diff.ops().iter().filter(|op| op.tag() != DiffTag::Equal) {
+        added += op.new_range().len();
+        removed += op.old_range().len();
+
+        let start = op.new_range().start;
+        first_line.get_or_insert(start + 1);
+        if let Some(scope) = enclosing_declaration(&new_lines, start) {
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+    }
+
+    let first_line = first_line?;
+
+    let mut parts = Vec::new();
+    if added > 0 {
+        parts.push(format!("{} line{} added", added, if added == 1 { "" } else { "s" }));
+    }
+    if removed > 0 {
+        parts.push(format!("{} removed", removed));
+    }
+    let mut summary = parts.join(", ");
+
+    if !scopes.is_empty() {
+        let more = scopes.len().saturating_sub(MAX_CHANGED_SCOPES);
+        scopes.truncate(MAX_CHANGED_SCOPES);
+        summary.push_str(&format!(" in {}", scopes.join(", ")));
+        if more > 0 {
+            summary.push_str(&format!(" and {} more", more));
+        }
+    }
+
+    Some((summary, first_line.min(new_lines.len().max(1))))
+}
+
+/// Inclusive, 1-based ranges of `new` that differ from `old`. A pure deletion is
+/// reported as the line now at its place, so a comment on it still counts as touching the edit.
+pub fn changed_ranges(old: &str, new: &str) -> Vec<(usize, usize)> {
+    let line_count = new.lines().count().max(1);
+    TextDiff::from_lines(old, new)
+        .ops()
+        .iter()
+        .filter(|op| op.tag() != DiffTag::Equal)
+        .map(|op| {
+            let range = op.new_range();
+            let start = (range.start + 1).min(line_count);
+            (start, range.end.max(start))
+        })
+        .collect()
+}
+
+/// The nearest declaration at or above `line` (0-based), e.g. `fn parse`
+fn enclosing_declaration(lines: &[&str], line: usize) -> Option<String> {
+    lines
+        .iter()
+        .take(line.saturating_add(1))
+        .rev()
+        .map(|line| line.trim_start())
+        .find(|line| DECLARATION_PREFIXES.iter().any(|prefix| line.starts_with(prefix)))
+        .map(|line| {
+            let signature = line.split(['(', '{', ':', '<', '=']).next().unwrap_or(line);
+            signature.trim().trim_start_matches("pub ").trim_start_matches("export ").to_string()
+        })
+}
+
+fn insert_bounded(context: &mut HashMap<String, String>, budget: &mut usize, key: &str, mut value: String) {
+    if *budget == 0 {
+        return;
+    }
+
+    if value.len() > *budget {
+        let mut end = *budget;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        value.truncate(end);
+        value.push('…');
+    }
+
+    *budget = budget.saturating_sub(value.len());
+    context.insert(key.to_string(), value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_context() {
+        let content = "use std::fs;\nuse crate::config::Config;\n\nfn main() {\n    let x = 1;\n}\n";
+        let related = HashMap::from([
+            ("/repo/src/config.rs".to_string(), "pub struct Config {\n    a: u8,\n}\n\nimpl Config {\n    pub fn load() {}\n}\n".to_string()),
+            ("/repo/src/main.rs".to_string(), content.to_string()),
+        ]);
+
+        let context = build_context(Path::new("/repo/src/main.rs"), content, Path::new("/repo"), &related);
+
+        assert_eq!(context["language"], "Rust");
+        assert_eq!(context["path"], "src/main.rs");
+        assert_eq!(context["imports"], "use std::fs; use crate::config::Config");
+        assert_eq!(context["related_files"], "src/config.rs [pub struct Config; impl Config]");
+
+        // Paths as the watcher reports them, relative to the working directory
+        let cwd = std::env::current_dir().unwrap();
+        let related = HashMap::from([("./src/config.rs".to_string(), related["/repo/src/config.rs"].clone())]);
+        let context = build_context(Path::new("./src/main.rs"), content, &cwd, &related);
+        assert_eq!(context["path"], "src/main.rs");
+        assert_eq!(context["related_files"], "src/config.rs [pub struct Config; impl Config]");
+    }
+
+    #[test]
+    fn test_change_summary() {
+        let old = "fn main() {\n    run();\n}\n\nfn parse(input: &str) {\n    let a = 1;\n}\n";
+        let new = "fn main() {\n    run();\n}\n\nfn parse(input: &str) {\n    let a = 2;\n    let b = 3;\n    let c = 4;\n}\n";
+
+        assert_eq!(
+            change_summary(old, new),
+            Some(("3 lines added, 1 removed in fn parse".to_string(), 6))
+        );
+        assert_eq!(change_summary(old, old), None);
+    }
+
+    #[test]
+    fn test_changed_ranges() {
+        let old = "a\nb\nc\nd\n";
+        assert_eq!(changed_ranges(old, "a\nB\nC\nd\n"), vec![(2, 3)]);
+        assert_eq!(changed_ranges(old, "a\nc\nd\nx\n"), vec![(2, 2), (4, 4)]);
+        assert_eq!(changed_ranges(old, old), vec![]);
+    }
+}
@@ -1,10 +1,16 @@
 pub mod claude;
+pub mod openai;
 pub mod analyzer;
+pub mod cache;
+pub mod context;
+pub mod prompt;
+pub mod queue;
+pub mod retry;
+pub mod usage;
 
-use anyhow::Result;
-use std::sync::Arc;
-
-use crate::app::{AiRequest, Thought, Suggestion};
+use crate::app::{AiRequest, AiRequestType, Thought, Suggestion};
+use crate::config::{self, Config};
+use crate::error::{CocoError, Result};
 
 #[async_trait::async_trait]
 pub trait AiProvider: Send + Sync {
@@ -12,22 +18,61 @@ pub trait AiProvider: Send + Sync {
     async fn generate_suggestions(&self, code: &str, context: &str) -> Result<Vec<Suggestion>>;
     async fn explain_code(&self, code: &str) -> Result<String>;
     async fn fix_code(&self, code: &str, error: &str) -> Result<String>;
-}
-
-pub struct ClaudeClient {
-    inner: Arc<claude::ClaudeProvider>,
-}
+    /// Tokens this provider has consumed so far
+    fn usage(&self) -> usage::TokenUsage;
 
-impl ClaudeClient {
-    pub fn new(api_key: String) -> Result<Self> {
-        let provider = claude::ClaudeProvider::new(api_key)?;
-        Ok(Self {
-            inner: Arc::new(provider),
-        })
+    /// Answers any request that produces thoughts: explanations and fixes go to the
+    /// dedicated prompts, everything else is analyzed
+    async fn respond(&self, request: &AiRequest) -> Result<Vec<Thought>> {
+        match request.request_type {
+            AiRequestType::Explain => {
+                let explanation = self.explain_code(&request.content).await?;
+                Ok(vec![prompt::explanation_thought(request, &explanation)])
+            }
+            AiRequestType::Fix => {
+                let error = request.context.get("error").map(String::as_str).unwrap_or_default();
+                let response = self.fix_code(&request.content, error).await?;
+                Ok(vec![prompt::fix_thought(request, &response)])
+            }
+            _ => self.analyze_code(request).await,
+        }
     }
 
-    pub async fn process_request(&self, request: &AiRequest) -> Result<Vec<Thought>> {
-        self.inner.analyze_code(request).await
+    /// Asks for improvement ideas for a `Suggest` request, passing its context along as `key: value` lines
+    async fn suggest(&self, request: &AiRequest) -> Result<Vec<Suggestion>> {
+        let mut context: Vec<String> = request
+            .context
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key, value))
+            .collect();
+        context.sort();
+        self.generate_suggestions(&request.content, &context.join("\n")).await
     }
 }
 
+/// Builds the provider for `config.ai_provider`. OpenAI and local backends both go
+/// through the OpenAI-compatible provider, pointed at `config.openai_base_url`.
+pub fn provider_from_config(config: &Config) -> Result<Box<dyn AiProvider>> {
+    match config.ai_provider {
+        config::AiProvider::Anthropic => {
+            let api_key = config.anthropic_api_key.clone().ok_or(CocoError::MissingApiKey {
+                provider: "Anthropic",
+                env_var: "ANTHROPIC_API_KEY",
+            })?;
+            let mut provider = claude::ClaudeProvider::new(api_key)?;
+            provider.set_model(config.anthropic_model.clone());
+            provider.set_include_suggestions(config.include_suggestions);
+            Ok(Box::new(provider))
+        }
+        config::AiProvider::OpenAI | config::AiProvider::Local => {
+            let mut provider = openai::GenericOpenAiProvider::new(
+                &config.openai_base_url,
+                config.openai_model.clone(),
+                config.openai_api_key.clone(),
+            )?;
+            provider.set_include_suggestions(config.include_suggestions);
+            tracing::info!("Using OpenAI-compatible endpoint {} with model {}", config.openai_base_url, config.openai_model);
+            Ok(Box::new(provider))
+        }
+    }
+}
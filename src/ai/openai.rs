@@ -0,0 +1,200 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::app::{AiRequest, Thought, Suggestion};
+use crate::error::{CocoError, Result};
+use super::{AiProvider, prompt};
+use super::retry::{self, ApiStatusError};
+use super::usage::{TokenUsage, UsageTracker};
+
+#[derive(Serialize)]
+struct ChatCompletionRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    max_tokens: u32,
+    temperature: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChatMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<ChatChoice>,
+    /// Some local servers leave this out
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChatUsage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatChoiceMessage {
+    content: Option<String>,
+}
+
+/// Talks to any server implementing the OpenAI chat completions API: OpenAI itself,
+/// or local and hosted backends such as LM Studio, vLLM, LiteLLM and Together
+pub struct GenericOpenAiProvider {
+    client: Client,
+    endpoint: String,
+    model: String,
+    api_key: Option<String>,
+    max_retries: u32,
+    retry_delay: Duration,
+    usage: UsageTracker,
+    include_suggestions: bool,
+}
+
+impl GenericOpenAiProvider {
+    /// `base_url` is the API root, e.g. `https://api.openai.com/v1` or `http://localhost:1234/v1`.
+    /// Local servers usually need no `api_key`.
+    pub fn new(base_url: &str, model: String, api_key: Option<String>) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(120))
+            .build()?;
+
+        Ok(Self {
+            client,
+            endpoint: chat_completions_url(base_url),
+            model,
+            api_key: api_key.filter(|key| !key.is_empty()),
+            max_retries: 3,
+            retry_delay: Duration::from_millis(1000),
+            usage: UsageTracker::default(),
+            include_suggestions: true,
+        })
+    }
+
+    /// Whether analyses attach the suggestions found in the response to their thoughts
+    pub fn set_include_suggestions(&mut self, include: bool) {
+        self.include_suggestions = include;
+    }
+
+    async fn make_request(&self, prompt: &str, system_prompt: Option<&str>) -> Result<String> {
+        let mut messages = Vec::new();
+        if let Some(system) = system_prompt {
+            messages.push(ChatMessage {
+                role: "system".to_string(),
+                content: system.to_string(),
+            });
+        }
+        messages.push(ChatMessage {
+            role: "user".to_string(),
+            content: prompt.to_string(),
+        });
+
+        let request = ChatCompletionRequest {
+            model: self.model.clone(),
+            messages,
+            max_tokens: 4096,
+            temperature: 0.7,
+        };
+
+        retry::with_retries(self.max_retries, self.retry_delay, || self.send_request(&request)).await
+    }
+
+    async fn send_request(&self, request: &ChatCompletionRequest) -> Result<String> {
+        let mut builder = self
+            .client
+            .post(&self.endpoint)
+            .header("content-type", "application/json")
+            .json(request);
+
+        if let Some(api_key) = &self.api_key {
+            builder = builder.bearer_auth(api_key);
+        }
+
+        let response = builder.send().await?;
+
+        if !response.status().is_success() {
+            return Err(ApiStatusError::from_response(response).await.into());
+        }
+
+        let completion: ChatCompletionResponse = response.json().await?;
+
+        if let Some(usage) = &completion.usage {
+            self.usage.record(usage.prompt_tokens, usage.completion_tokens);
+        }
+
+        completion
+            .choices
+            .into_iter()
+            .find_map(|choice| choice.message.content)
+            .filter(|content| !content.trim().is_empty())
+            .ok_or_else(|| CocoError::InvalidResponse("no text content".to_string()))
+    }
+}
+
+/// Accepts base URLs with or without a trailing slash or the `/chat/completions` suffix
+fn chat_completions_url(base_url: &str) -> String {
+    let base = base_url.trim().trim_end_matches('/');
+    if base.ends_with("/chat/completions") {
+        base.to_string()
+    } else {
+        format!("{}/chat/completions", base)
+    }
+}
+
+#[async_trait::async_trait]
+impl AiProvider for GenericOpenAiProvider {
+    async fn analyze_code(&self, request: &AiRequest) -> Result<Vec<Thought>> {
+        let (system_prompt, user_prompt) = prompt::analysis_prompt(request);
+
+        match self.make_request(&user_prompt, Some(&system_prompt)).await {
+            Ok(response) => {
+                let thoughts = prompt::parse_response(&response, request, self.include_suggestions);
+                tracing::debug!("Generated {} thoughts for request {}", thoughts.len(), request.id);
+                Ok(thoughts)
+            }
+            Err(e) => {
+                tracing::error!("OpenAI-compatible API request to {} failed: {}", self.endpoint, e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn generate_suggestions(&self, code: &str, context: &str) -> Result<Vec<Suggestion>> {
+        let (user_prompt, system_prompt) = prompt::suggestions_prompt(code, context);
+        let response = self.make_request(&user_prompt, Some(system_prompt)).await?;
+        Ok(prompt::extract_suggestions(&response))
+    }
+
+    async fn explain_code(&self, code: &str) -> Result<String> {
+        let (user_prompt, system_prompt) = prompt::explain_prompt(code);
+        self.make_request(&user_prompt, Some(system_prompt)).await
+    }
+
+    async fn fix_code(&self, code: &str, error: &str) -> Result<String> {
+        let (user_prompt, system_prompt) = prompt::fix_prompt(code, error);
+        self.make_request(&user_prompt, Some(system_prompt)).await
+    }
+
+    fn usage(&self) -> TokenUsage {
+        self.usage.snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_completions_url() {
+        assert_eq!(chat_completions_url("https://api.openai.com/v1"), "https://api.openai.com/v1/chat/completions");
+        assert_eq!(chat_completions_url("http://localhost:1234/v1/"), "http://localhost:1234/v1/chat/completions");
+        assert_eq!(chat_completions_url("http://host/v1/chat/completions"), "http://host/v1/chat/completions");
+    }
+}
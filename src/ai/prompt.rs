@@ -0,0 +1,684 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::app::{AiRequest, AiRequestType, Thought, ThoughtType, Suggestion, ActionType, Priority};
+use crate::error::CocoError;
+
+// Prompts and response parsing shared by every provider, so the same request
+// produces comparable thoughts whichever backend answers it
+
+pub fn analysis_prompt(request: &AiRequest) -> (String, String) {
+    let system_prompt = match request.request_type {
+        AiRequestType::Analyze => {
+            "You are an expert code reviewer and AI pair programmer. Analyze the provided code and provide thoughtful insights about:
+1. Code quality and structure
+2. Potential bugs or issues
+3. Performance considerations
+4. Security implications
+5. Best practices and improvements
+6. Architecture patterns
+
+Format your response as structured thoughts that can help the developer. Be concise but thorough."
+        }
+        AiRequestType::Suggest => {
+            "You are an expert programming assistant. Provide specific, actionable suggestions for improving the given code. Focus on:
+1. Code refactoring opportunities
+2. Performance optimizations
+3. Error handling improvements
+4. Code style and readability
+5. Modern language features that could be used
+
+Provide concrete code examples where helpful."
+        }
+        AiRequestType::Fix => {
+            "You are a debugging expert. Analyze the provided code to:
+1. Identify potential bugs and errors
+2. Suggest specific fixes
+3. Explain why the issues occur
+4. Provide corrected code examples
+5. Suggest preventive measures
+
+Be precise and provide working solutions."
+        }
+        AiRequestType::Optimize => {
+            "You are a performance optimization expert. Analyze the code for:
+1. Performance bottlenecks
+2. Memory usage optimization
+3. Algorithm improvements
+4. Concurrency opportunities
+5. Resource management
+
+Provide specific optimization strategies with examples."
+        }
+        AiRequestType::Explain => {
+            "You are a code educator. Explain the provided code clearly:
+1. What the code does (high-level purpose)
+2. How it works (step-by-step breakdown)
+3. Key concepts and patterns used
+4. Context and use cases
+5. Related concepts the developer should know
+
+Make explanations accessible but thorough."
+        }
+        AiRequestType::Meta => {
+            "You are a meta-programming expert. Analyze not just the code, but also:
+1. The development patterns and practices evident
+2. Code organization and architecture decisions
+3. Testing strategies that would be appropriate
+4. Documentation needs
+5. Maintenance considerations
+6. Team collaboration aspects
+
+Provide insights about the development process itself."
+        }
+    };
+
+    let user_prompt = match request.request_type {
+        AiRequestType::Meta => format!(
+            "Project files (each prefixed with a `// File:` header):\n\n```\n{}\n```\n\nContext: {}\n\nPlease review these files together according to your role.",
+            request.content,
+            format_context(&request.context)
+        ),
+        _ => format!(
+            "File: {}\n\nCode:\n```\n{}\n```\n\nContext: {}\n\n{}Please analyze this code according to your role. When a suggestion applies to specific code, cite it as \"line N\" or \"lines N-M\".",
+            request.file_path.as_deref().unwrap_or("unknown"),
+            request.content,
+            format_context(&request.context),
+            focus_instruction(request.selection)
+        ),
+    };
+
+    (system_prompt.to_string(), user_prompt)
+}
+
+/// Points the model at the selected lines, keeping the rest of the file as context
+fn focus_instruction(selection: Option<(usize, usize)>) -> String {
+    match selection {
+        Some((start, end)) if start == end => format!("Focus on line {}; the rest of the file is context.\n\n", start),
+        Some((start, end)) => format!("Focus on lines {}-{}; the rest of the file is context.\n\n", start, end),
+        None => String::new(),
+    }
+}
+
+fn format_context(context: &std::collections::HashMap<String, String>) -> String {
+    if context.is_empty() {
+        "No additional context provided.".to_string()
+    } else {
+        let mut entries: Vec<_> = context.iter().collect();
+        entries.sort();
+
+        entries
+            .into_iter()
+            .map(|(k, v)| format!("\n- {}: {}", k, v))
+            .collect::<String>()
+    }
+}
+
+/// Splits an analysis into thoughts, attaching the suggestions found in each section
+/// when `include_suggestions` is set
+pub fn parse_response(response: &str, request: &AiRequest, include_suggestions: bool) -> Vec<Thought> {
+    let mut thoughts = Vec::new();
+
+    // Split response into logical sections
+    let sections = split_response_into_sections(response);
+
+    for (_i, section) in sections.iter().enumerate() {
+        if section.trim().is_empty() {
+            continue;
+        }
+
+        // Project reviews stay Meta thoughts even when a section mentions bugs or style
+        let thought_type = match request.request_type {
+            AiRequestType::Meta => ThoughtType::Meta,
+            _ => infer_thought_type(section, &request.request_type),
+        };
+        let confidence = calculate_confidence(section);
+        // A project review spans several files, so its line references are ambiguous
+        let line_number = match request.request_type {
+            AiRequestType::Meta => None,
+            _ => target_range_from_text(section).map(|(start, _)| start),
+        };
+        let suggestions = if include_suggestions {
+            extract_suggestions(section).into_iter().take(MAX_SUGGESTIONS_PER_THOUGHT).collect()
+        } else {
+            Vec::new()
+        };
+
+        let thought = Thought {
+            suggestions,
+            ..Thought::new(
+                thought_type,
+                section.trim().to_string(),
+                request.file_path.clone(),
+                line_number,
+                confidence,
+            )
+        };
+
+        thoughts.push(thought);
+    }
+
+    // If no thoughts were generated, create a generic one
+    if thoughts.is_empty() {
+        thoughts.push(Thought::new(
+            ThoughtType::Analyzing,
+            response.trim().to_string(),
+            request.file_path.clone(),
+            None,
+            0.5,
+        ));
+    }
+
+    thoughts
+}
+
+fn split_response_into_sections(response: &str) -> Vec<String> {
+    // Split by numbered lists, bullet points, or clear paragraph breaks
+    let mut sections = Vec::new();
+    let mut current_section = String::new();
+
+    for line in response.lines() {
+        let trimmed = line.trim();
+
+        // Check if this line starts a new section
+        if is_section_start(trimmed) && !current_section.trim().is_empty() {
+            sections.push(current_section.trim().to_string());
+            current_section = String::new();
+        }
+
+        current_section.push_str(line);
+        current_section.push('\n');
+    }
+
+    if !current_section.trim().is_empty() {
+        sections.push(current_section.trim().to_string());
+    }
+
+    sections
+}
+
+fn is_section_start(line: &str) -> bool {
+    // Detect common section starters
+    line.starts_with("1.") ||
+    line.starts_with("2.") ||
+    line.starts_with("3.") ||
+    line.starts_with("4.") ||
+    line.starts_with("5.") ||
+    line.starts_with("- ") ||
+    line.starts_with("* ") ||
+    line.starts_with("## ") ||
+    line.starts_with("### ") ||
+    (line.len() > 20 && line.ends_with(':'))
+}
+
+fn infer_thought_type(content: &str, request_type: &AiRequestType) -> ThoughtType {
+    let content_lower = content.to_lowercase();
+
+    // Look for keywords that indicate thought type
+    if content_lower.contains("error") || content_lower.contains("bug") || content_lower.contains("issue") {
+        ThoughtType::Error
+    } else if content_lower.contains("warning") || content_lower.contains("caution") || content_lower.contains("careful") {
+        ThoughtType::Warning
+    } else if content_lower.contains("suggest") || content_lower.contains("recommend") || content_lower.contains("consider") {
+        ThoughtType::Suggesting
+    } else if content_lower.contains("performance") || content_lower.contains("optimization") || content_lower.contains("speed") {
+        ThoughtType::Performance
+    } else if content_lower.contains("security") || content_lower.contains("vulnerability") || content_lower.contains("safe") {
+        ThoughtType::Security
+    } else if content_lower.contains("style") || content_lower.contains("format") || content_lower.contains("convention") {
+        ThoughtType::Style
+    } else if content_lower.contains("architecture") || content_lower.contains("design") || content_lower.contains("pattern") {
+        ThoughtType::Architecture
+    } else {
+        match request_type {
+            AiRequestType::Analyze => ThoughtType::Analyzing,
+            AiRequestType::Suggest => ThoughtType::Suggesting,
+            AiRequestType::Fix => ThoughtType::Error,
+            AiRequestType::Optimize => ThoughtType::Performance,
+            AiRequestType::Explain => ThoughtType::Complete,
+            AiRequestType::Meta => ThoughtType::Meta,
+        }
+    }
+}
+
+fn calculate_confidence(content: &str) -> f32 {
+    let content_lower = content.to_lowercase();
+    let mut confidence: f32 = 0.5; // Base confidence
+
+    // Increase confidence for specific, actionable content
+    if content_lower.contains("should") || content_lower.contains("must") {
+        confidence += 0.2;
+    }
+
+    // Decrease confidence for uncertain language
+    if content_lower.contains("might") || content_lower.contains("maybe") || content_lower.contains("possibly") {
+        confidence -= 0.2;
+    }
+
+    // Increase confidence for code examples
+    if content.contains("```") || content.contains("```") {
+        confidence += 0.1;
+    }
+
+    // Increase confidence for detailed explanations
+    if content.len() > 200 {
+        confidence += 0.1;
+    }
+
+    confidence.clamp(0.0_f32, 1.0_f32)
+}
+
+/// Suggestions attached to one thought; the thoughts panel lists three
+const MAX_SUGGESTIONS_PER_THOUGHT: usize = 3;
+
+/// A paragraph or list item of a response, with the code block right after it
+#[derive(Debug, Default)]
+struct ResponseBlock {
+    lines: Vec<String>,
+    code: Option<String>,
+}
+
+/// One suggestion per paragraph or list item that proposes something, carrying its
+/// explanatory lines as the description and the code block that follows as the snippet
+pub fn extract_suggestions(content: &str) -> Vec<Suggestion> {
+    split_into_blocks(content)
+        .into_iter()
+        .filter_map(block_suggestion)
+        .collect()
+}
+
+fn split_into_blocks(content: &str) -> Vec<ResponseBlock> {
+    let mut blocks: Vec<ResponseBlock> = Vec::new();
+    let mut code: Option<Vec<&str>> = None;
+    let mut in_paragraph = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+
+        if let Some(code_lines) = code.as_mut() {
+            if trimmed.starts_with("```") {
+                let snippet = code_lines.join("\n");
+                code = None;
+                // A code block belongs to the paragraph it follows, if that has none yet
+                if let Some(block) = blocks.last_mut().filter(|block| block.code.is_none()) {
+                    block.code = Some(snippet);
+                }
+            } else {
+                code_lines.push(line);
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("```") {
+            code = Some(Vec::new());
+            in_paragraph = false;
+        } else if trimmed.is_empty() {
+            in_paragraph = false;
+        } else {
+            let item = strip_list_marker(trimmed);
+            if !in_paragraph || item.len() < trimmed.len() {
+                blocks.push(ResponseBlock::default());
+            }
+            if let Some(block) = blocks.last_mut() {
+                block.lines.push(item.to_string());
+            }
+            in_paragraph = true;
+        }
+    }
+
+    blocks
+}
+
+/// The text of a `- `, `* ` or `1.` list item; other lines are returned as they are
+fn strip_list_marker(line: &str) -> &str {
+    if let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return item.trim_start();
+    }
+
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    match line[digits..].strip_prefix(". ").or_else(|| line[digits..].strip_prefix(") ")) {
+        Some(item) if digits > 0 => item.trim_start(),
+        _ => line,
+    }
+}
+
+fn looks_like_suggestion(line: &str) -> bool {
+    let lower = line.to_lowercase();
+    lower.contains("consider") ||
+    lower.contains("suggest") ||
+    lower.contains("recommend") ||
+    lower.contains("should") ||
+    lower.contains("could") ||
+    lower.contains("try") ||
+    lower.starts_with("replace") ||
+    lower.starts_with("add") ||
+    lower.starts_with("remove") ||
+    lower.starts_with("refactor")
+}
+
+/// A suggestion for `block` if one of its lines proposes something. The kind and
+/// priority come from that line, the title from the block's first line.
+fn block_suggestion(block: ResponseBlock) -> Option<Suggestion> {
+    let proposal = block.lines.iter().find(|line| looks_like_suggestion(line))?.to_lowercase();
+    let description = block.lines.join(" ");
+
+    if description.len() < 10 {
+        return None; // Too short to be meaningful
+    }
+
+    let action_type = if proposal.contains("replac") {
+        ActionType::Replace
+    } else if proposal.contains("add") || proposal.contains("insert") {
+        ActionType::Insert
+    } else if proposal.contains("remove") || proposal.contains("delete") {
+        ActionType::Delete
+    } else if proposal.contains("refactor") {
+        ActionType::Refactor
+    } else if proposal.contains("optimize") {
+        ActionType::Optimize
+    } else {
+        ActionType::Fix
+    };
+
+    let priority = if proposal.contains("critical") || proposal.contains("must") {
+        Priority::Critical
+    } else if proposal.contains("important") || proposal.contains("should") {
+        Priority::High
+    } else if proposal.contains("consider") || proposal.contains("could") {
+        Priority::Medium
+    } else {
+        Priority::Low
+    };
+
+    // Extract title (first part of the suggestion)
+    let first_line = block.lines[0].replace("**", "");
+    let title = if first_line.chars().count() > 50 {
+        format!("{}...", first_line.chars().take(47).collect::<String>())
+    } else {
+        first_line
+    };
+
+    Some(Suggestion {
+        id: uuid::Uuid::new_v4().to_string(),
+        title,
+        target_range: target_range_from_text(&description),
+        description,
+        code_snippet: block.code,
+        action_type,
+        priority,
+    })
+}
+
+/// Finds a line reference such as "line 12", "lines 40-55" or "lines 3 to 7"
+fn target_range_from_text(text: &str) -> Option<(usize, usize)> {
+    static LINE_REFERENCE: OnceLock<Regex> = OnceLock::new();
+    let regex = LINE_REFERENCE.get_or_init(|| {
+        Regex::new(r"(?i)\blines?\s+(\d+)(?:\s*(?:-|–|to)\s*(\d+))?").expect("valid line reference regex")
+    });
+
+    let captures = regex.captures(text)?;
+    let start: usize = captures[1].parse().ok()?;
+    let end: usize = match captures.get(2) {
+        Some(end) => end.as_str().parse().ok()?,
+        None => start,
+    };
+
+    (start > 0 && end >= start).then_some((start, end))
+}
+
+/// The thought shown in place of an analysis when the provider could not be reached
+pub fn error_thought(request: &AiRequest, error: &CocoError) -> Thought {
+    let content = match error {
+        CocoError::RetriesExhausted(exhausted) => {
+            tracing::debug!("Last API error: {}", exhausted.last_error);
+            format!("AI analysis unavailable: {}", exhausted)
+        }
+        _ => format!("AI analysis temporarily unavailable: {}", error),
+    };
+
+    Thought::new(ThoughtType::Error, content, request.file_path.clone(), None, 0.0)
+}
+
+/// Shows the answer to an `Explain` request as a thought
+pub fn explanation_thought(request: &AiRequest, explanation: &str) -> Thought {
+    Thought::new(
+        ThoughtType::Analyzing,
+        explanation.trim().to_string(),
+        request.file_path.clone(),
+        None,
+        0.8,
+    )
+}
+
+/// Shows the answer to a `Fix` request as a thought. The first code block of the
+/// response becomes a suggestion replacing the whole file, so it can be previewed
+/// and applied like any other.
+pub fn fix_thought(request: &AiRequest, response: &str) -> Thought {
+    let code_block = first_code_block(response);
+    let explanation = match &code_block {
+        Some((_, range)) => format!("{}{}", &response[..range.start], &response[range.end..]),
+        None => response.to_string(),
+    };
+
+    let suggestions = code_block
+        .map(|(code, _)| Suggestion {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: "Apply AI fix".to_string(),
+            description: "Replace the file with the corrected version".to_string(),
+            code_snippet: Some(code),
+            action_type: ActionType::Fix,
+            priority: Priority::High,
+            target_range: Some((1, request.content.lines().count().max(1))),
+        })
+        .into_iter()
+        .collect();
+
+    Thought {
+        suggestions,
+        ..Thought::new(
+            ThoughtType::Suggesting,
+            explanation.trim().to_string(),
+            request.file_path.clone(),
+            request.context.get("line_number").and_then(|line| line.parse().ok()),
+            0.8,
+        )
+    }
+}
+
+/// The contents of the first fenced code block and the byte range of the whole block
+fn first_code_block(response: &str) -> Option<(String, std::ops::Range<usize>)> {
+    let start = response.find("```")?;
+    let body_start = start + response[start..].find('\n')? + 1;
+    let body_len = response[body_start..].find("```")?;
+    let end = body_start + body_len + 3;
+
+    let code = response[body_start..body_start + body_len].trim_end_matches('\n');
+    Some((code.to_string(), start..end))
+}
+
+/// Returns the user prompt and system prompt for `AiProvider::generate_suggestions`
+pub fn suggestions_prompt(code: &str, context: &str) -> (String, &'static str) {
+    let prompt = format!(
+        "Analyze this code and provide specific, actionable suggestions for improvement:\n\nCode:\n```\n{}\n```\n\nContext: {}\n\nProvide numbered suggestions with clear actions.",
+        code, context
+    );
+
+    (prompt, "You are a code improvement expert. Provide specific, actionable suggestions for improving code quality, performance, and maintainability. Each suggestion should be clear and implementable.")
+}
+
+/// Returns the user prompt and system prompt for `AiProvider::explain_code`
+pub fn explain_prompt(code: &str) -> (String, &'static str) {
+    let prompt = format!(
+        "Explain what this code does in clear, educational terms:\n\n```\n{}\n```\n\nProvide a comprehensive but accessible explanation.",
+        code
+    );
+
+    (prompt, "You are a code educator. Explain code clearly and comprehensively, making it accessible to developers who want to understand how it works.")
+}
+
+/// Returns the user prompt and system prompt for `AiProvider::fix_code`
+pub fn fix_prompt(code: &str, error: &str) -> (String, &'static str) {
+    let prompt = format!(
+        "Fix the following code that has this error:\n\nError: {}\n\nCode:\n```\n{}\n```\n\nProvide the complete corrected code in a single code block, followed by a short explanation.",
+        error, code
+    );
+
+    (prompt, "You are a debugging expert. Analyze code errors and provide corrected versions with clear explanations of what was wrong and how it was fixed.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_range_from_text() {
+        assert_eq!(target_range_from_text("Replace the loop on line 12 with an iterator"), Some((12, 12)));
+        assert_eq!(target_range_from_text("Consider extracting lines 40-55 into a helper"), Some((40, 55)));
+        assert_eq!(target_range_from_text("Remove Lines 3 to 7"), Some((3, 7)));
+        assert_eq!(target_range_from_text("Consider splitting this pipeline"), None);
+        assert_eq!(target_range_from_text("lines 9-2 are odd"), None);
+    }
+
+    #[test]
+    fn test_fix_thought() {
+        let request = AiRequest {
+            id: "r".to_string(),
+            request_type: AiRequestType::Fix,
+            content: "fn main() {\n    run().unwrap();\n}\n".to_string(),
+            file_path: Some("src/main.rs".to_string()),
+            context: [("line_number".to_string(), "2".to_string())].into_iter().collect(),
+            priority: Priority::Medium,
+            selection: None,
+        };
+
+        let response = "```rust\nfn main() {\n    run().expect(\"run failed\");\n}\n```\n\nUse expect to explain the failure.";
+        let thought = fix_thought(&request, response);
+        assert_eq!(thought.content, "Use expect to explain the failure.");
+        assert_eq!(thought.line_number, Some(2));
+        assert_eq!(thought.suggestions[0].target_range, Some((1, 3)));
+        assert_eq!(
+            thought.suggestions[0].code_snippet.as_deref(),
+            Some("fn main() {\n    run().expect(\"run failed\");\n}")
+        );
+
+        assert!(fix_thought(&request, "No code here").suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_parse_response_suggestions() {
+        let request = AiRequest {
+            id: "r".to_string(),
+            request_type: AiRequestType::Analyze,
+            content: "fn main() {}\n".to_string(),
+            file_path: Some("src/main.rs".to_string()),
+            context: std::collections::HashMap::new(),
+            priority: Priority::Medium,
+            selection: None,
+        };
+        let response = "The error handling is fragile.\nConsider returning a Result instead of panicking.";
+
+        assert!(!parse_response(response, &request, true)[0].suggestions.is_empty());
+        assert!(parse_response(response, &request, false).iter().all(|t| t.suggestions.is_empty()));
+    }
+
+    #[test]
+    fn test_parse_response_line_numbers() {
+        let mut request = AiRequest {
+            id: "r".to_string(),
+            request_type: AiRequestType::Analyze,
+            content: "fn main() {}\n".to_string(),
+            file_path: Some("src/main.rs".to_string()),
+            context: std::collections::HashMap::new(),
+            priority: Priority::Medium,
+            selection: None,
+        };
+        let response = "1. The unwrap on line 12 panics on empty input.\n2. The module is well organized.";
+
+        let thoughts = parse_response(response, &request, false);
+        assert_eq!(thoughts[0].line_number, Some(12));
+        assert_eq!(thoughts[1].line_number, None);
+
+        request.request_type = AiRequestType::Meta;
+        assert_eq!(parse_response(response, &request, false)[0].line_number, None);
+    }
+
+    #[test]
+    fn test_extract_suggestions_groups_blocks() {
+        let response = "Overall the module is readable, but a few things stand out.
+
+1. Consider replacing the manual loop on lines 12-18 with an iterator chain.
+   This avoids the off-by-one risk in the index arithmetic and makes the
+   intent clearer.
+
+```rust
+let total: u32 = items.iter().map(|item| item.price).sum();
+```
+
+2. The error from `parse_config` is silently ignored.
+   You should propagate it with `?` so callers can react.
+
+- Add a doc comment to `Cache::evict`.
+
+This file has no tests yet.";
+
+        let suggestions = extract_suggestions(response);
+        assert_eq!(suggestions.len(), 3);
+
+        let iterator = &suggestions[0];
+        assert_eq!(iterator.title, "Consider replacing the manual loop on lines 12-...");
+        assert!(iterator.description.ends_with("makes the intent clearer."));
+        assert_eq!(
+            iterator.code_snippet.as_deref(),
+            Some("let total: u32 = items.iter().map(|item| item.price).sum();")
+        );
+        assert_eq!(iterator.target_range, Some((12, 18)));
+        assert!(matches!(iterator.action_type, ActionType::Replace));
+
+        let error = &suggestions[1];
+        assert_eq!(error.title, "The error from `parse_config` is silently ignored.");
+        assert!(error.description.contains("You should propagate it"));
+        assert!(error.code_snippet.is_none());
+        assert!(matches!(error.priority, Priority::High));
+
+        assert!(matches!(suggestions[2].action_type, ActionType::Insert));
+        assert_eq!(suggestions[2].title, "Add a doc comment to `Cache::evict`.");
+    }
+
+    #[test]
+    fn test_parse_response_caps_suggestions() {
+        let request = AiRequest {
+            id: "r".to_string(),
+            request_type: AiRequestType::Analyze,
+            content: "fn main() {}\n".to_string(),
+            file_path: Some("src/main.rs".to_string()),
+            context: std::collections::HashMap::new(),
+            priority: Priority::Medium,
+            selection: None,
+        };
+        let response = (1..=5)
+            .map(|i| format!("Consider renaming variable number {}.\n", i))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let thoughts = parse_response(&response, &request, true);
+        assert_eq!(thoughts.len(), 1);
+        assert_eq!(thoughts[0].suggestions.len(), MAX_SUGGESTIONS_PER_THOUGHT);
+    }
+
+    #[test]
+    fn test_analysis_prompt_selection() {
+        let mut request = AiRequest {
+            id: "r".to_string(),
+            request_type: AiRequestType::Analyze,
+            content: "fn main() {}\n".to_string(),
+            file_path: Some("src/main.rs".to_string()),
+            context: std::collections::HashMap::new(),
+            priority: Priority::Medium,
+            selection: None,
+        };
+        assert!(!analysis_prompt(&request).1.contains("Focus on"));
+
+        request.selection = Some((40, 55));
+        assert!(analysis_prompt(&request).1.contains("Focus on lines 40-55; the rest of the file is context."));
+    }
+}
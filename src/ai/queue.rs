@@ -0,0 +1,205 @@
+use std::collections::{HashMap, VecDeque};
+use std::mem::Discriminant;
+use std::sync::Mutex;
+use tokio::sync::Notify;
+
+use crate::app::{AiRequest, AiRequestType};
+
+/// Bounded queue of pending AI requests that never blocks the producer.
+///
+/// A new request for a file that is already waiting replaces the queued one
+/// in place (only the latest content is worth analyzing). When the queue is
+/// full otherwise, the oldest request is dropped to make room.
+pub struct AnalysisQueue {
+    state: Mutex<QueueState>,
+    notify: Notify,
+}
+
+struct QueueState {
+    requests: VecDeque<AiRequest>,
+    capacity: usize,
+}
+
+#[derive(Debug)]
+pub enum PushOutcome {
+    Queued,
+    /// Replaced a pending request for the same file
+    Coalesced,
+    /// The queue was full; this request was dropped to make room
+    DroppedOldest(AiRequest),
+}
+
+impl AnalysisQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                requests: VecDeque::new(),
+                capacity: capacity.max(1),
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    pub fn push(&self, request: AiRequest) -> PushOutcome {
+        let outcome = {
+            let mut state = self.state.lock().unwrap();
+
+            let pending = state.requests.iter_mut().find(|queued| {
+                queued.file_path.is_some()
+                    && queued.file_path == request.file_path
+                    && std::mem::discriminant(&queued.request_type)
+                        == std::mem::discriminant(&request.request_type)
+            });
+
+            if let Some(pending) = pending {
+                *pending = request;
+                PushOutcome::Coalesced
+            } else {
+                let dropped = if state.requests.len() >= state.capacity {
+                    state.requests.pop_front()
+                } else {
+                    None
+                };
+                state.requests.push_back(request);
+
+                match dropped {
+                    Some(dropped) => PushOutcome::DroppedOldest(dropped),
+                    None => PushOutcome::Queued,
+                }
+            }
+        };
+
+        self.notify.notify_one();
+        outcome
+    }
+
+    /// Waits for the next request
+    pub async fn pop(&self) -> AiRequest {
+        loop {
+            if let Some(request) = self.state.lock().unwrap().requests.pop_front() {
+                return request;
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.lock().unwrap().requests.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.state.lock().unwrap().capacity
+    }
+
+    /// Changes the capacity, dropping the oldest requests if it shrank below the current depth
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.capacity = capacity.max(1);
+        while state.requests.len() > state.capacity {
+            state.requests.pop_front();
+        }
+    }
+}
+
+/// Tracks the newest in-flight analysis of each file, so that when requests for the
+/// same file run concurrently, an older one finishing last can't overwrite newer results
+#[derive(Default)]
+pub struct AnalysisGenerations {
+    state: Mutex<GenerationState>,
+}
+
+#[derive(Default)]
+struct GenerationState {
+    next: u64,
+    latest: HashMap<(String, Discriminant<AiRequestType>), u64>,
+}
+
+impl AnalysisGenerations {
+    /// Marks `request` as the newest analysis of its file and returns its generation
+    pub fn start(&self, request: &AiRequest) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.next += 1;
+        let generation = state.next;
+
+        if let Some(key) = Self::key(request) {
+            state.latest.insert(key, generation);
+        }
+        generation
+    }
+
+    /// Ends an analysis, returning whether it is still the newest for its file.
+    /// Requests without a file never go stale.
+    pub fn finish(&self, request: &AiRequest, generation: u64) -> bool {
+        let Some(key) = Self::key(request) else {
+            return true;
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if state.latest.get(&key) == Some(&generation) {
+            state.latest.remove(&key);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn key(request: &AiRequest) -> Option<(String, Discriminant<AiRequestType>)> {
+        let file_path = request.file_path.clone()?;
+        Some((file_path, std::mem::discriminant(&request.request_type)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Priority;
+
+    fn request(file: &str, content: &str) -> AiRequest {
+        AiRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            request_type: AiRequestType::Analyze,
+            content: content.to_string(),
+            file_path: Some(file.to_string()),
+            context: HashMap::new(),
+            priority: Priority::Medium,
+            selection: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_and_drop_oldest() {
+        let queue = AnalysisQueue::new(2);
+
+        assert!(matches!(queue.push(request("a.rs", "v1")), PushOutcome::Queued));
+        assert!(matches!(queue.push(request("a.rs", "v2")), PushOutcome::Coalesced));
+        assert!(matches!(queue.push(request("b.rs", "v1")), PushOutcome::Queued));
+        assert_eq!(queue.len(), 2);
+
+        match queue.push(request("c.rs", "v1")) {
+            PushOutcome::DroppedOldest(dropped) => assert_eq!(dropped.content, "v2"),
+            other => panic!("expected a drop, got {:?}", other),
+        }
+
+        assert_eq!(queue.pop().await.file_path.as_deref(), Some("b.rs"));
+        assert_eq!(queue.pop().await.file_path.as_deref(), Some("c.rs"));
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn test_generations_discard_stale_results() {
+        let generations = AnalysisGenerations::default();
+        let (old, new, other) = (request("a.rs", "v1"), request("a.rs", "v2"), request("b.rs", "v1"));
+
+        let old_generation = generations.start(&old);
+        let new_generation = generations.start(&new);
+        let other_generation = generations.start(&other);
+
+        assert!(!generations.finish(&old, old_generation));
+        assert!(generations.finish(&new, new_generation));
+        assert!(generations.finish(&other, other_generation));
+    }
+}
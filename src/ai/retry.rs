@@ -0,0 +1,311 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+use crate::error::{CocoError, Result};
+
+/// Longest `retry-after` wait honored, so a confused server can't stall a worker indefinitely
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+#[derive(Debug)]
+pub struct ApiStatusError {
+    pub status: reqwest::StatusCode,
+    pub body: String,
+    /// How long the server asked us to wait before retrying, from its `retry-after` header
+    pub retry_after: Option<Duration>,
+}
+
+impl ApiStatusError {
+    /// Builds the error from a failed response, reading its `retry-after` header and body
+    pub async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, chrono::Utc::now()));
+        let body = response.text().await.unwrap_or_default();
+        Self { status, body, retry_after }
+    }
+}
+
+/// Parses a `retry-after` value, either delay seconds or an HTTP date, into the time
+/// left to wait from `now`. Dates in the past mean no wait.
+fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = (date.with_timezone(&chrono::Utc) - now).to_std().unwrap_or(Duration::ZERO);
+    Some(wait.min(MAX_RETRY_AFTER))
+}
+
+impl std::fmt::Display for ApiStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "API request failed with status {}: {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for ApiStatusError {}
+
+/// Returned once every retry attempt has failed, classified from the last error
+#[derive(Debug)]
+pub struct RetriesExhausted {
+    pub attempts: u32,
+    pub kind: FailureKind,
+    pub last_error: String,
+}
+
+impl std::fmt::Display for RetriesExhausted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} after {} attempt{} ({})",
+            self.kind.describe(),
+            self.attempts,
+            if self.attempts == 1 { "" } else { "s" },
+            self.kind.remediation()
+        )
+    }
+}
+
+impl std::error::Error for RetriesExhausted {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailureKind {
+    RateLimited,
+    Overloaded,
+    Unauthorized,
+    ServerError(u16),
+    ClientError(u16),
+    Timeout,
+    Unreachable,
+    Other,
+}
+
+impl FailureKind {
+    fn classify(error: &CocoError) -> Self {
+        match error {
+            CocoError::Api(api_error) => match api_error.status.as_u16() {
+                429 => FailureKind::RateLimited,
+                529 => FailureKind::Overloaded,
+                401 | 403 => FailureKind::Unauthorized,
+                code if code >= 500 => FailureKind::ServerError(code),
+                code => FailureKind::ClientError(code),
+            },
+            CocoError::Http(http_error) if http_error.is_timeout() => FailureKind::Timeout,
+            CocoError::Http(http_error) if http_error.is_connect() => FailureKind::Unreachable,
+            _ => FailureKind::Other,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            FailureKind::RateLimited => "Rate limited by the API (HTTP 429)".to_string(),
+            FailureKind::Overloaded => "API is overloaded (HTTP 529)".to_string(),
+            FailureKind::Unauthorized => "API key was rejected".to_string(),
+            FailureKind::ServerError(code) => format!("API server error (HTTP {})", code),
+            FailureKind::ClientError(code) => format!("API rejected the request (HTTP {})", code),
+            FailureKind::Timeout => "API request timed out".to_string(),
+            FailureKind::Unreachable => "Network unreachable".to_string(),
+            FailureKind::Other => "AI request failed".to_string(),
+        }
+    }
+
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            FailureKind::RateLimited => "lower save frequency or wait a minute",
+            FailureKind::Overloaded => "the service is busy, try again shortly",
+            FailureKind::Unauthorized => "check the API key for the configured provider",
+            FailureKind::ServerError(_) => "the service is having problems, try again later",
+            FailureKind::ClientError(_) => "the file may be too large or the model name invalid",
+            FailureKind::Timeout => "check your connection or analyze a smaller file",
+            FailureKind::Unreachable => "check your network connection",
+            FailureKind::Other => "see the log for details",
+        }
+    }
+}
+
+
+/// Runs `attempt` up to `max_retries` times with exponential backoff, or as long as
+/// the server's `retry-after` asks, classifying the last error into `RetriesExhausted`
+/// if none succeed
+pub async fn with_retries<T, F, Fut>(max_retries: u32, retry_delay: Duration, mut attempt: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut last_error = None;
+
+    for attempt_number in 0..max_retries {
+        match attempt().await {
+            Ok(response) => return Ok(response),
+            Err(e) => {
+                last_error = Some(e);
+                if attempt_number < max_retries - 1 {
+                    let requested = match &last_error {
+                        Some(CocoError::Api(e)) => e.retry_after,
+                        _ => None,
+                    };
+                    let delay = requested.unwrap_or(retry_delay * (2_u32.pow(attempt_number)));
+                    tracing::warn!("API request failed, retrying in {:?}. Error: {}", delay, last_error.as_ref().unwrap());
+                    sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    let (kind, last_error) = match last_error {
+        Some(e) => (FailureKind::classify(&e), e.to_string()),
+        None => (FailureKind::Other, "All retry attempts failed".to_string()),
+    };
+    Err(RetriesExhausted { attempts: max_retries, kind, last_error }.into())
+}
+
+/// Stops sending requests to a provider that keeps failing. After `threshold`
+/// consecutive failures it opens for `cooldown`; the first request after that is a
+/// probe, and the breaker closes again on any success.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BreakerState {
+    Closed { consecutive_failures: u32 },
+    Open { until: Instant },
+    /// The cooldown is over; one probe request may go out, and until it resolves no others
+    HalfOpen { probing: bool },
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState::Closed { consecutive_failures: 0 }),
+        }
+    }
+
+    /// `None` if a request may go to the provider now, which after a cooldown makes it
+    /// the probe. Otherwise the time left in the cooldown, or zero while the probe is out.
+    pub fn acquire(&self, now: Instant) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+        if let BreakerState::Open { until } = *state {
+            if until > now {
+                return Some(until - now);
+            }
+            *state = BreakerState::HalfOpen { probing: false };
+        }
+
+        match *state {
+            BreakerState::HalfOpen { probing: true } => Some(Duration::ZERO),
+            BreakerState::HalfOpen { probing: false } => {
+                *state = BreakerState::HalfOpen { probing: true };
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::Closed { consecutive_failures: 0 };
+    }
+
+    /// Counts a failure, returning the cooldown if this failure opened the breaker
+    pub fn record_failure(&self, now: Instant) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            BreakerState::Closed { consecutive_failures } if consecutive_failures + 1 < self.threshold => {
+                *state = BreakerState::Closed { consecutive_failures: consecutive_failures + 1 };
+                None
+            }
+            // In-flight requests failing while it is open don't announce it again
+            BreakerState::Open { .. } => None,
+            // The threshold was reached, or the probe failed
+            BreakerState::Closed { .. } | BreakerState::HalfOpen { .. } => {
+                *state = BreakerState::Open { until: now + self.cooldown };
+                Some(self.cooldown)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_kind_classification() {
+        let status_error = |code: u16| -> CocoError {
+            ApiStatusError {
+                status: reqwest::StatusCode::from_u16(code).unwrap(),
+                body: String::new(),
+                retry_after: None,
+            }.into()
+        };
+
+        assert_eq!(FailureKind::classify(&status_error(429)), FailureKind::RateLimited);
+        assert_eq!(FailureKind::classify(&status_error(529)), FailureKind::Overloaded);
+        assert_eq!(FailureKind::classify(&status_error(401)), FailureKind::Unauthorized);
+        assert_eq!(FailureKind::classify(&status_error(503)), FailureKind::ServerError(503));
+        assert_eq!(FailureKind::classify(&status_error(400)), FailureKind::ClientError(400));
+        assert_eq!(FailureKind::classify(&CocoError::InvalidResponse("something else".to_string())), FailureKind::Other);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = chrono::DateTime::parse_from_rfc3339("2015-10-21T07:28:00Z").unwrap().with_timezone(&chrono::Utc);
+
+        assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 ", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("86400", now), Some(MAX_RETRY_AFTER));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+        let start = Instant::now();
+
+        assert_eq!(breaker.record_failure(start), None);
+        assert_eq!(breaker.acquire(start), None);
+        assert_eq!(breaker.record_failure(start), Some(Duration::from_secs(30)));
+        assert_eq!(breaker.acquire(start + Duration::from_secs(10)), Some(Duration::from_secs(20)));
+
+        // In-flight requests failing while it is open don't announce it again
+        assert_eq!(breaker.record_failure(start + Duration::from_secs(1)), None);
+
+        // After the cooldown a failed probe reopens it straight away
+        let probe = start + Duration::from_secs(31);
+        assert_eq!(breaker.acquire(probe), None);
+        assert_eq!(breaker.record_failure(probe), Some(Duration::from_secs(30)));
+
+        breaker.record_success();
+        assert_eq!(breaker.acquire(probe), None);
+        assert_eq!(breaker.record_failure(probe), None);
+    }
+
+    #[test]
+    fn test_circuit_breaker_sends_one_probe() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(30));
+        let start = Instant::now();
+        breaker.record_failure(start);
+
+        // Two requests arrive once the cooldown is over; only the first goes out
+        let after = start + Duration::from_secs(31);
+        assert_eq!(breaker.acquire(after), None);
+        assert_eq!(breaker.acquire(after), Some(Duration::ZERO));
+
+        // A successful probe lets everything through again
+        breaker.record_success();
+        assert_eq!(breaker.acquire(after), None);
+        assert_eq!(breaker.acquire(after), None);
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::config::AiProvider;
+
+/// Tokens consumed by a provider so far
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl TokenUsage {
+    pub fn cost_usd(&self, pricing: ModelPricing) -> f64 {
+        (self.input_tokens as f64 * pricing.input + self.output_tokens as f64 * pricing.output) / 1_000_000.0
+    }
+}
+
+/// Running token totals, updated by a provider after each response
+#[derive(Debug, Default)]
+pub struct UsageTracker {
+    input_tokens: AtomicU64,
+    output_tokens: AtomicU64,
+}
+
+impl UsageTracker {
+    pub fn record(&self, input_tokens: u64, output_tokens: u64) {
+        self.input_tokens.fetch_add(input_tokens, Ordering::Relaxed);
+        self.output_tokens.fetch_add(output_tokens, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> TokenUsage {
+        TokenUsage {
+            input_tokens: self.input_tokens.load(Ordering::Relaxed),
+            output_tokens: self.output_tokens.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// List prices for the models CoCo defaults to. Local backends are free; unknown hosted
+/// models are priced like the most expensive default so a cost limit errs on the safe side.
+pub fn pricing_for(provider: &AiProvider, model: &str) -> ModelPricing {
+    let model = model.to_lowercase();
+    match provider {
+        AiProvider::Local => ModelPricing { input: 0.0, output: 0.0 },
+        _ if model.contains("haiku") => ModelPricing { input: 0.80, output: 4.00 },
+        _ if model.contains("gpt-4o-mini") => ModelPricing { input: 0.15, output: 0.60 },
+        _ => ModelPricing { input: 3.00, output: 15.00 },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cost_usd() {
+        let tracker = UsageTracker::default();
+        tracker.record(800_000, 100_000);
+        tracker.record(200_000, 150_000);
+
+        let usage = tracker.snapshot();
+        assert_eq!(usage, TokenUsage { input_tokens: 1_000_000, output_tokens: 250_000 });
+
+        let pricing = pricing_for(&AiProvider::Anthropic, "claude-3-5-haiku-20241022");
+        assert!((usage.cost_usd(pricing) - 1.80).abs() < 1e-9);
+        assert_eq!(usage.cost_usd(pricing_for(&AiProvider::Local, "qwen2.5-coder")), 0.0);
+    }
+}
@@ -1,12 +1,49 @@
 use std::sync::Arc;
-use tokio::sync::{Mutex, mpsc};
-use anyhow::Result;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::{Mutex, Semaphore, mpsc};
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
+use crate::ai::AiProvider;
+use crate::ai::cache::ThoughtCache;
+use crate::ai::queue::{AnalysisGenerations, AnalysisQueue, PushOutcome};
+use crate::ai::retry::CircuitBreaker;
 use crate::config::Config;
+use crate::edits::{AppliedEdit, PendingEdit};
+use crate::error::{CocoError, Result};
 use crate::session::{SessionRecorder, EventType};
+use crate::ui::widgets::{FilePicker, ScrollableThoughts, ThoughtGrouping};
+
+/// Lines of context kept above a line the code panel scrolls to
+const CODE_SCROLL_CONTEXT: usize = 3;
+
+/// Upper bound on the combined source sent in a single project review request
+const META_REVIEW_MAX_BYTES: usize = 15_000;
+
+/// Upper bound on the files listed by the file picker
+const FILE_PICKER_MAX_FILES: usize = 5_000;
+
+/// Files remembered for the recent-files picker, most recent first
+const RECENT_FILES_MAX: usize = 20;
+
+/// Longest note accepted by the annotation prompt
+const ANNOTATION_MAX_CHARS: usize = 200;
+
+/// How long a status bar message stays visible
+const STATUS_MESSAGE_DURATION: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How often the idle timeout is checked
+const IDLE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How often thoughts past `thought_ttl_seconds` are swept from the panel
+const THOUGHT_EXPIRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// How often the periodic analysis task checks whether a re-analysis is due
+const PERIODIC_ANALYSIS_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Inclusive, 1-based line ranges of a file
+pub type LineRanges = Vec<(usize, usize)>;
 
 #[derive(Clone)]
 pub struct App {
@@ -15,16 +52,48 @@ pub struct App {
     pub ai_thoughts: Arc<Mutex<Vec<Thought>>>,
     pub file_tx: mpsc::Sender<FileEvent>,
     pub file_rx: Arc<Mutex<mpsc::Receiver<FileEvent>>>,
-    pub ai_tx: mpsc::Sender<AiRequest>,
-    pub ai_rx: Arc<Mutex<mpsc::Receiver<AiRequest>>>,
+    pub ai_queue: Arc<AnalysisQueue>,
     pub ui_tx: mpsc::Sender<UiEvent>,
     pub ui_rx: Arc<Mutex<mpsc::Receiver<UiEvent>>>,
-    pub config: Arc<Config>,
+    pub config: Arc<Mutex<Arc<Config>>>,
+    /// Backend for every AI request, chosen from `config.ai_provider` at startup and
+    /// rebuilt when the user switches models
+    pub ai_provider: Arc<Mutex<Arc<dyn AiProvider>>>,
     pub is_recording: Arc<Mutex<bool>>,
     pub mode: Arc<Mutex<ViewMode>>,
     pub session_recorder: Arc<Mutex<Option<SessionRecorder>>>,
     pub running: Arc<Mutex<bool>>,
+    /// When the watcher last reported a file
+    pub last_file_event: Arc<Mutex<std::time::Instant>>,
+    /// Set after `idle_pause_minutes` without file changes, cleared by the next one
+    pub idle: Arc<Mutex<bool>>,
     pub file_cache: Arc<Mutex<HashMap<String, String>>>,
+    /// Lines each file's latest save changed
+    pub recent_changes: Arc<Mutex<HashMap<String, LineRanges>>>,
+    pub code_scroll: Arc<Mutex<usize>>,
+    pub code_display: Arc<Mutex<CodeDisplay>>,
+    pub thought_grouping: Arc<Mutex<ThoughtGrouping>>,
+    pub thought_selection: Arc<Mutex<ScrollableThoughts>>,
+    pub pending_edit: Arc<Mutex<Option<PendingEdit>>>,
+    pub last_applied_edit: Arc<Mutex<Option<AppliedEdit>>>,
+    pub status_message: Arc<Mutex<Option<(String, std::time::Instant)>>>,
+    pub file_picker: Arc<Mutex<Option<FilePicker>>>,
+    /// Files shown or analyzed this session, most recent first, for the `R` picker
+    pub recent_files: Arc<Mutex<VecDeque<String>>>,
+    /// Note being typed into the annotation prompt, while it is open
+    pub annotation_draft: Arc<Mutex<Option<String>>>,
+    /// Improvement ideas from the last suggestions request, while the list is open
+    pub suggestion_list: Arc<Mutex<Option<SuggestionList>>>,
+    pub request_type_picker: Arc<Mutex<Option<RequestTypePicker>>>,
+    /// Whether accepted suggestions are only previewed and recorded, never written;
+    /// starts at `dry_run_edits`
+    pub dry_run_edits: Arc<Mutex<bool>>,
+    /// Spend at which AI analysis stops for this session; starts at `max_session_cost_usd`
+    pub session_cost_limit: Arc<Mutex<Option<f64>>>,
+    /// Bumped whenever displayed state changes, so the UI can skip redundant redraws
+    pub state_version: Arc<AtomicU64>,
+    /// Filesystem events the watcher dropped because they arrived faster than it could take them
+    pub dropped_fs_events: Arc<AtomicU64>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -35,6 +104,23 @@ pub enum ViewMode {
     ThoughtsOnly,
 }
 
+/// Decorations of the code panel that can be switched off at runtime,
+/// e.g. to copy code without line numbers
+#[derive(Clone, Copy, Debug)]
+pub struct CodeDisplay {
+    pub line_numbers: bool,
+    pub syntax_highlighting: bool,
+}
+
+impl Default for CodeDisplay {
+    fn default() -> Self {
+        Self {
+            line_numbers: true,
+            syntax_highlighting: true,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Thought {
     pub id: String,
@@ -45,9 +131,50 @@ pub struct Thought {
     pub line_number: Option<usize>,
     pub confidence: f32,
     pub suggestions: Vec<Suggestion>,
+    /// Stable id of the local check that produced this thought, e.g. `unwrap_usage`
+    #[serde(default)]
+    pub rule_id: Option<String>,
+    /// Whether the thought's line was part of the latest save of its file
+    #[serde(default)]
+    pub in_recent_change: bool,
+    #[serde(default)]
+    pub source: ThoughtSource,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl Thought {
+    /// A fresh thought without suggestions, stamped now with a new id
+    pub fn new(
+        thought_type: ThoughtType,
+        content: String,
+        file_path: Option<String>,
+        line_number: Option<usize>,
+        confidence: f32,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            thought_type,
+            content,
+            file_path,
+            line_number,
+            confidence,
+            suggestions: Vec::new(),
+            rule_id: None,
+            in_recent_change: false,
+            source: ThoughtSource::Fresh,
+        }
+    }
+}
+
+/// Where a thought came from: this run's analysis, or a cached one of identical code
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThoughtSource {
+    #[default]
+    Fresh,
+    Cached,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ThoughtType {
     Analyzing,
     Suggesting,
@@ -61,6 +188,44 @@ pub enum ThoughtType {
     Architecture,
 }
 
+impl ThoughtType {
+    pub const ALL: [ThoughtType; 10] = [
+        ThoughtType::Analyzing,
+        ThoughtType::Suggesting,
+        ThoughtType::Warning,
+        ThoughtType::Error,
+        ThoughtType::Complete,
+        ThoughtType::Meta,
+        ThoughtType::Performance,
+        ThoughtType::Security,
+        ThoughtType::Style,
+        ThoughtType::Architecture,
+    ];
+
+    /// Parses a thought type name case-insensitively, e.g. `"style"` or `"Style"`
+    pub fn from_name(name: &str) -> Option<ThoughtType> {
+        Self::ALL
+            .into_iter()
+            .find(|t| format!("{:?}", t).eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Relative severity used to pick one thought type when several apply, higher is worse
+    pub fn severity(&self) -> u8 {
+        match self {
+            ThoughtType::Error => 9,
+            ThoughtType::Security => 8,
+            ThoughtType::Warning => 7,
+            ThoughtType::Performance => 6,
+            ThoughtType::Architecture => 5,
+            ThoughtType::Style => 4,
+            ThoughtType::Suggesting => 3,
+            ThoughtType::Meta => 2,
+            ThoughtType::Analyzing => 1,
+            ThoughtType::Complete => 0,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Suggestion {
     pub id: String,
@@ -69,6 +234,34 @@ pub struct Suggestion {
     pub code_snippet: Option<String>,
     pub action_type: ActionType,
     pub priority: Priority,
+    /// Inclusive, 1-based line range the suggestion applies to
+    #[serde(default)]
+    pub target_range: Option<(usize, usize)>,
+}
+
+/// Request types offered by the `t` picker, with the label shown for each
+pub const REQUEST_TYPE_CHOICES: [(AiRequestType, &str); 6] = [
+    (AiRequestType::Analyze, "Analyze - issues and improvements"),
+    (AiRequestType::Optimize, "Optimize - performance"),
+    (AiRequestType::Explain, "Explain - what the code does"),
+    (AiRequestType::Suggest, "Suggest - improvement ideas"),
+    (AiRequestType::Fix, "Fix - the selected thought's problem"),
+    (AiRequestType::Meta, "Meta - review with recently analyzed files"),
+];
+
+/// Popup for re-running a file through a different request type
+#[derive(Clone, Debug)]
+pub struct RequestTypePicker {
+    pub file_path: String,
+    /// Index into `REQUEST_TYPE_CHOICES`
+    pub selected: usize,
+}
+
+/// Suggestions returned for one file by a `Suggest` request
+#[derive(Clone, Debug)]
+pub struct SuggestionList {
+    pub file_path: String,
+    pub suggestions: Vec<Suggestion>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -105,6 +298,9 @@ pub struct AiRequest {
     pub file_path: Option<String>,
     pub context: HashMap<String, String>,
     pub priority: Priority,
+    /// Inclusive, 1-based lines to focus on, e.g. the function an editor has selected;
+    /// `None` reviews the whole file
+    pub selection: Option<(usize, usize)>,
 }
 
 #[derive(Clone, Debug)]
@@ -127,13 +323,47 @@ pub struct UiEvent {
 #[derive(Clone, Debug)]
 pub enum UiEventType {
     KeyPressed(crossterm::event::KeyCode),
+    ScrollCode(isize),
+    SelectNextThought,
+    SelectPreviousThought,
     Refresh,
     Resize,
     SelectFile,
+    OpenRecentFiles,
+    MoveFileSelection(isize),
+    OpenSelectedFile,
+    CloseFilePicker,
     ToggleMode,
+    ToggleLineNumbers,
+    ToggleSyntaxHighlighting,
+    ToggleThoughtGrouping,
+    ToggleCompactThoughts,
+    ToggleGroupCollapsed,
     AcceptSuggestion,
     RejectSuggestion,
+    ConfirmEdit,
+    CancelEdit,
+    UndoEdit,
+    DismissThought,
+    Annotate,
+    AnnotationInput(char),
+    AnnotationBackspace,
+    SubmitAnnotation,
+    CancelAnnotation,
+    RaiseCostLimit,
+    RequestSuggestions,
+    CloseSuggestions,
+    ExplainFile,
+    FixSelectedThought,
     ClearThoughts,
+    MetaReview,
+    OpenRequestTypePicker,
+    MoveRequestTypeSelection(isize),
+    RunSelectedRequestType,
+    CloseRequestTypePicker,
+    ExportThoughts,
+    CycleModel,
+    ToggleDryRun,
     Help,
     Quit,
 }
@@ -141,9 +371,9 @@ pub enum UiEventType {
 impl App {
     pub async fn new() -> Result<Self> {
         let config = Arc::new(Config::load().await?);
+        let ai_provider = crate::ai::provider_from_config(&config)?;
 
         let (file_tx, file_rx) = mpsc::channel(5);
-        let (ai_tx, ai_rx) = mpsc::channel(5);
         let (ui_tx, ui_rx) = mpsc::channel(10);
 
         Ok(Self {
@@ -152,30 +382,57 @@ impl App {
             ai_thoughts: Arc::new(Mutex::new(Vec::new())),
             file_tx,
             file_rx: Arc::new(Mutex::new(file_rx)),
-            ai_tx,
-            ai_rx: Arc::new(Mutex::new(ai_rx)),
+            ai_queue: Arc::new(AnalysisQueue::new(config.ai_queue_capacity)),
             ui_tx,
             ui_rx: Arc::new(Mutex::new(ui_rx)),
-            config,
+            config: Arc::new(Mutex::new(config.clone())),
+            ai_provider: Arc::new(Mutex::new(Arc::from(ai_provider))),
             is_recording: Arc::new(Mutex::new(false)),
             mode: Arc::new(Mutex::new(ViewMode::SideBySide)),
             session_recorder: Arc::new(Mutex::new(None)),
             running: Arc::new(Mutex::new(true)),
+            last_file_event: Arc::new(Mutex::new(std::time::Instant::now())),
+            idle: Arc::new(Mutex::new(false)),
             file_cache: Arc::new(Mutex::new(HashMap::new())),
+            recent_changes: Arc::new(Mutex::new(HashMap::new())),
+            code_scroll: Arc::new(Mutex::new(0)),
+            code_display: Arc::new(Mutex::new(CodeDisplay::default())),
+            thought_grouping: Arc::new(Mutex::new(ThoughtGrouping::default())),
+            thought_selection: Arc::new(Mutex::new(ScrollableThoughts::new())),
+            pending_edit: Arc::new(Mutex::new(None)),
+            last_applied_edit: Arc::new(Mutex::new(None)),
+            status_message: Arc::new(Mutex::new(None)),
+            file_picker: Arc::new(Mutex::new(None)),
+            recent_files: Arc::new(Mutex::new(VecDeque::new())),
+            annotation_draft: Arc::new(Mutex::new(None)),
+            suggestion_list: Arc::new(Mutex::new(None)),
+            request_type_picker: Arc::new(Mutex::new(None)),
+            dry_run_edits: Arc::new(Mutex::new(config.dry_run_edits)),
+            session_cost_limit: Arc::new(Mutex::new(config.max_session_cost_usd)),
+            state_version: Arc::new(AtomicU64::new(0)),
+            dropped_fs_events: Arc::new(AtomicU64::new(0)),
         })
     }
 
-    pub async fn new_with_recording() -> Result<Self> {
+    /// `resume` appends to an existing session instead of starting a new one
+    pub async fn new_with_recording(resume: Option<&str>) -> Result<Self> {
         let app = Self::new().await?;
 
-        let recorder = SessionRecorder::new()?;
+        let mut recorder = match resume {
+            Some(id) => SessionRecorder::resume(id)?,
+            None => SessionRecorder::new(&*app.get_config().await)?,
+        };
+        let config = app.get_config().await;
+        recorder.set_max_events(config.session_max_events);
+        recorder.set_retention_policy(config.session_retention.clone());
         *app.session_recorder.lock().await = Some(recorder);
         *app.is_recording.lock().await = true;
 
         Ok(app)
     }
 
-    pub async fn run(&mut self) -> Result<()> {
+    /// Runs until quit. `headless` swaps the TUI for a plain stream of thoughts on stdout.
+    pub async fn run(&mut self, headless: bool) -> Result<()> {
         tracing::info!("Starting CoCo application loop");
 
         let app_clone = self.clone();
@@ -189,24 +446,61 @@ impl App {
         // Start UI event handler
         let ui_handler = tokio::spawn(Self::handle_ui_events(app_clone.clone()));
 
+        // Start config hot-reload handler
+        let config_handler = tokio::spawn(Self::handle_config_reloads(app_clone.clone()));
+
+        // Start idle timeout handler
+        let idle_handler = tokio::spawn(Self::handle_idle_timeout(app_clone.clone()));
+
+        // Start thought expiry handler
+        let expiry_handler = tokio::spawn(Self::handle_thought_expiry(app_clone.clone()));
+
+        // Start periodic analysis handler
+        let periodic_handler = tokio::spawn(Self::handle_periodic_analysis(app_clone.clone()));
+
         // Start file watcher
-        let mut monitor = crate::watcher::FileMonitor::new(self.file_tx.clone()).await?;
-        monitor.watch(std::path::Path::new(".")).await?;
+        let config = self.get_config().await;
+        let debounce_delay = std::time::Duration::from_millis(config.watch_debounce_ms);
+        let mut monitor = crate::watcher::FileMonitor::new(
+            self.file_tx.clone(),
+            debounce_delay,
+            config.file_size_limits(),
+            config.exclude_dirs.clone(),
+            crate::watcher::load_cocoignore(crate::util::project_root()),
+            config.notify_channel_capacity,
+            self.dropped_fs_events.clone(),
+        ).await?;
+        for dir in &config.watch_directories {
+            let path = std::path::Path::new(dir);
+            if !path.exists() {
+                tracing::warn!("Not watching {}: directory does not exist", dir);
+                continue;
+            }
+            monitor.watch(path, config.recursive_watch).await?;
+        }
         let watcher_task = tokio::spawn(async move {
             monitor.run().await
         });
 
         // Start UI
-        let mut ui = crate::ui::UI::new(self.clone()).await?;
-        let ui_task = tokio::spawn(async move {
-            ui.run().await
-        });
+        let ui_task = if headless {
+            tokio::spawn(crate::ui::headless::run(self.clone()))
+        } else {
+            let mut ui = crate::ui::UI::new(self.clone()).await?;
+            tokio::spawn(async move {
+                ui.run().await
+            })
+        };
 
         // Wait for any task to complete (usually UI task when user quits)
         tokio::select! {
             _ = file_handler => tracing::info!("File handler completed"),
             _ = ai_handler => tracing::info!("AI handler completed"),
             _ = ui_handler => tracing::info!("UI handler completed"),
+            _ = config_handler => tracing::info!("Config handler completed"),
+            _ = idle_handler => tracing::info!("Idle handler completed"),
+            _ = expiry_handler => tracing::info!("Thought expiry handler completed"),
+            _ = periodic_handler => tracing::info!("Periodic analysis handler completed"),
             _ = watcher_task => tracing::info!("Watcher task completed"),
             result = ui_task => {
                 match result {
@@ -227,55 +521,229 @@ impl App {
         Ok(())
     }
 
+    /// Saves the session and marks the app idle once no file has changed for
+    /// `idle_pause_minutes`; the next file event ends it
+    async fn handle_idle_timeout(app: App) -> Result<()> {
+        let mut ticker = tokio::time::interval(IDLE_CHECK_INTERVAL);
+
+        while app.is_running().await {
+            ticker.tick().await;
+
+            let minutes = app.get_config().await.idle_pause_minutes;
+            if minutes == 0 || app.is_idle().await {
+                continue;
+            }
+            if app.last_file_event.lock().await.elapsed() < std::time::Duration::from_secs(minutes * 60) {
+                continue;
+            }
+
+            if let Some(recorder) = app.session_recorder.lock().await.as_mut() {
+                if let Err(e) = recorder.save() {
+                    tracing::error!("Failed to save session before idling: {}", e);
+                }
+            }
+            *app.idle.lock().await = true;
+            tracing::info!("No file changes for {} minutes, idling until the next one", minutes);
+            app.mark_changed();
+        }
+
+        Ok(())
+    }
+
+    async fn handle_thought_expiry(app: App) -> Result<()> {
+        let mut ticker = tokio::time::interval(THOUGHT_EXPIRY_INTERVAL);
+
+        while app.is_running().await {
+            ticker.tick().await;
+
+            let ttl = app.get_config().await.thought_ttl_seconds;
+            if ttl == 0 {
+                continue;
+            }
+            let expired = app.expire_thoughts(chrono::Duration::seconds(ttl as i64), Utc::now()).await;
+            if expired > 0 {
+                tracing::debug!("Dropped {} thoughts older than {}s", expired, ttl);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Re-analyzes the file on screen every `periodic_analysis_secs`. The interval counts
+    /// from the latest file event too, so a save and a periodic pass never fire back to back.
+    async fn handle_periodic_analysis(app: App) -> Result<()> {
+        let mut ticker = tokio::time::interval(PERIODIC_ANALYSIS_CHECK_INTERVAL);
+        let mut analyzer = crate::ai::analyzer::CodeAnalyzer::new();
+        analyzer.set_max_line_length(app.get_config().await.max_line_length);
+        let mut last_run = std::time::Instant::now();
+
+        while app.is_running().await {
+            ticker.tick().await;
+
+            let config = app.get_config().await;
+            let Some(interval) = config.periodic_analysis_secs.map(std::time::Duration::from_secs) else {
+                continue;
+            };
+            // Nobody is at the keyboard to read the result
+            if config.manual_mode || app.is_idle().await {
+                continue;
+            }
+            if last_run.elapsed() < interval || app.last_file_event.lock().await.elapsed() < interval {
+                continue;
+            }
+            let Some(file_path) = app.get_current_file().await else {
+                continue;
+            };
+            last_run = std::time::Instant::now();
+
+            tracing::debug!("Periodic analysis of {}", file_path);
+            if let Err(e) = app.analyze_file(&analyzer, &file_path).await {
+                tracing::warn!("Skipping periodic analysis of {}: {}", file_path, e);
+            }
+            app.mark_changed();
+        }
+
+        Ok(())
+    }
+
+    /// Analyzes `file_path` outside the save path: through the AI queue, or with the
+    /// local checks for local-only extensions. Files over `ai_trigger_max_bytes` are refused.
+    async fn analyze_file(&self, analyzer: &crate::ai::analyzer::CodeAnalyzer, file_path: &str) -> Result<()> {
+        let config = self.get_config().await;
+        let request = self.build_request_for(AiRequestType::Analyze, file_path).await?;
+        if request.content.len() > config.ai_trigger_max_bytes {
+            return Err(CocoError::InvalidAction(format!(
+                "{} bytes is over COCO_AI_TRIGGER_MAX_BYTES ({})",
+                request.content.len(),
+                config.ai_trigger_max_bytes
+            )));
+        }
+
+        if config.uses_ai_for(std::path::Path::new(file_path)) {
+            self.enqueue_ai_request(request);
+        } else {
+            let thoughts = analyzer.analyze_code_patterns(&request.content, request.file_path.as_deref());
+            Self::apply_ai_result(self, analyzer, request, Ok(thoughts)).await;
+        }
+        Ok(())
+    }
+
     async fn handle_file_events(app: App) -> Result<()> {
         let mut rx = app.file_rx.lock().await;
+        let mut analyzer = crate::ai::analyzer::CodeAnalyzer::new();
+        analyzer.set_max_line_length(app.get_config().await.max_line_length);
 
         while let Some(event) = rx.recv().await {
             tracing::debug!("Handling file event: {:?}", event.path);
 
+            *app.last_file_event.lock().await = std::time::Instant::now();
+            if std::mem::take(&mut *app.idle.lock().await) {
+                tracing::info!("File changed, resuming from idle");
+            }
+
+            // Files opened from the picker arrive as access events rather than modifications
+            let opened = matches!(event.event_type, notify::EventKind::Access(_));
+
             // Update current file and code
             let path_str = event.path.to_string_lossy().to_string();
-            *app.current_file.lock().await = Some(path_str.clone());
+            let previous_file = app.current_file.lock().await.replace(path_str.clone());
             *app.current_code.lock().await = event.content.clone();
 
-            // Cache the file content with size limit
-            let mut cache = app.file_cache.lock().await;
-            cache.insert(path_str.clone(), event.content.clone());
-            // Keep cache size limited to prevent memory growth
-            if cache.len() > 3 {
-                cache.clear(); // Just clear everything
+            // Start at the top when switching to a different file
+            if previous_file.as_deref() != Some(path_str.as_str()) {
+                *app.code_scroll.lock().await = 0;
             }
 
+            let recent = {
+                let mut recent = app.recent_files.lock().await;
+                recent.retain(|path| *path != path_str);
+                recent.push_front(path_str.clone());
+                recent.truncate(RECENT_FILES_MAX);
+                recent.clone()
+            };
+
+            let config = app.get_config().await;
+
+            // Cache the file content, evicting the least recently changed files beyond the configured size
+            let mut cache = app.file_cache.lock().await;
+            let previous_content = cache.insert(path_str.clone(), event.content.clone());
+            evict_least_recent(&mut cache, &recent, config.file_cache_size.max(1));
+
             // Record event if recording
-            if *app.is_recording.lock().await {
+            if !opened && *app.is_recording.lock().await {
                 if let Some(recorder) = app.session_recorder.lock().await.as_mut() {
-                    recorder.record_event(EventType::FileChanged, serde_json::json!({
-                        "path": path_str,
-                        "size": event.content.len(),
-                        "timestamp": event.timestamp
-                    }));
+                    // Lets `replay --verify` re-run the local analyzer on what was saved
+                    let local_findings = config
+                        .session_record_content
+                        .then(|| crate::session::verify::local_findings(&analyzer, &event.content, &path_str));
+                    recorder.record_file_change(&path_str, &event.content, local_findings, config.max_line_length);
+                }
+            }
+
+            // Say what this save changed; the first save of a file has nothing to compare against
+            let previous_content = previous_content.filter(|_| !opened);
+            if !opened {
+                let ranges = previous_content
+                    .as_deref()
+                    .map(|previous| crate::ai::context::changed_ranges(previous, &event.content))
+                    .unwrap_or_default();
+                app.recent_changes.lock().await.insert(path_str.clone(), ranges);
+            }
+            let change = previous_content
+                .and_then(|previous| crate::ai::context::change_summary(&previous, &event.content));
+            if let Some((summary, first_line)) = &change {
+                if !config.is_thought_suppressed(&ThoughtType::Analyzing) {
+                    app.add_thought(Thought::new(
+                        ThoughtType::Analyzing,
+                        format!("Changed: {}", summary),
+                        Some(path_str.clone()),
+                        Some(*first_line),
+                        1.0,
+                    )).await;
                 }
             }
 
             // Trigger AI analysis only for reasonable file sizes
-            if event.content.len() < 5_000 { // Skip analysis for files > 5KB
+            let size_limit = config.max_file_size_for(&event.path);
+            if config.manual_mode {
+                tracing::debug!("Not analyzing {} in manual mode", path_str);
+            } else if opened && !config.analyze_on_focus {
+                tracing::debug!("Opened {} without analysis (analyze_on_focus is off)", path_str);
+            } else if event.content.len() <= config.ai_trigger_max_bytes && event.content.len() as u64 <= size_limit {
+                let root = crate::util::project_root();
+                let mut context = crate::ai::context::build_context(&event.path, &event.content, root, &cache);
+                if let Some((summary, _)) = change {
+                    context.insert("recent_change".to_string(), summary);
+                }
+
                 let ai_request = AiRequest {
                     id: uuid::Uuid::new_v4().to_string(),
                     request_type: AiRequestType::Analyze,
                     content: event.content,
                     file_path: Some(path_str),
-                    context: HashMap::new(),
+                    context,
                     priority: Priority::Medium,
+                    selection: None,
                 };
 
-                if let Err(e) = app.ai_tx.send(ai_request).await {
-                    tracing::error!("Failed to send AI request: {}", e);
+                if config.uses_ai_for(&event.path) {
+                    app.enqueue_ai_request(ai_request);
+                } else {
+                    // Local-only extensions get the pattern checks in place of a model response
+                    let thoughts = analyzer.analyze_code_patterns(&ai_request.content, ai_request.file_path.as_deref());
+                    Self::apply_ai_result(&app, &analyzer, ai_request, Ok(thoughts)).await;
                 }
             } else {
-                tracing::warn!("Skipping AI analysis for large file: {} bytes", event.content.len());
+                tracing::warn!(
+                    "Skipping analysis for large file: {} bytes (COCO_AI_TRIGGER_MAX_BYTES is {})",
+                    event.content.len(),
+                    config.ai_trigger_max_bytes
+                );
             }
 
 
+            app.mark_changed();
+
             if !*app.running.lock().await {
                 break;
             }
@@ -284,57 +752,124 @@ impl App {
         Ok(())
     }
 
+    /// Pulls requests off the queue and analyzes up to `ai_concurrency` of them at once
     async fn handle_ai_requests(app: App) -> Result<()> {
-        let mut rx = app.ai_rx.lock().await;
-        let ai_client = crate::ai::ClaudeClient::new(
-            app.config.anthropic_api_key.clone()
-                .ok_or_else(|| anyhow::anyhow!("Anthropic API key not configured"))?
-        )?;
+        let config = app.get_config().await;
+        let mut ai_client = app.ai_provider.lock().await.clone();
+        let mut analyzer = crate::ai::analyzer::CodeAnalyzer::new();
+        analyzer.set_max_line_length(config.max_line_length);
+        let analyzer = Arc::new(analyzer);
+        let workers = Arc::new(Semaphore::new(config.ai_concurrency));
+        let generations = Arc::new(AnalysisGenerations::default());
+        let mut pricing = crate::ai::usage::pricing_for(&config.ai_provider, &config.ai_model());
+        // Spent through providers replaced by a model switch, still counted against the limit
+        let mut earlier_cost = 0.0;
+        let mut cost_limit_reached = false;
+        let breaker = Arc::new(CircuitBreaker::new(
+            config.ai_failure_threshold,
+            std::time::Duration::from_secs(config.ai_failure_cooldown_secs),
+        ));
+        let thought_cache = Self::open_thought_cache(&config).map(|cache| Arc::new(Mutex::new(cache)));
+        let mut model = config.ai_model();
+        let mut include_suggestions = config.include_suggestions;
 
-        while let Some(request) = rx.recv().await {
+        loop {
+            let permit = workers.clone().acquire_owned().await.map_err(|_| CocoError::Stopped("AI worker pool"))?;
+            let request = app.ai_queue.pop().await;
+            app.mark_changed();
             tracing::debug!("Processing AI request: {}", request.id);
 
-            match ai_client.process_request(&request).await {
-                Ok(thoughts) => {
-                    let mut ai_thoughts = app.ai_thoughts.lock().await;
-                    ai_thoughts.extend(thoughts);
+            let current = app.ai_provider.lock().await.clone();
+            if !Arc::ptr_eq(&current, &ai_client) {
+                earlier_cost += ai_client.usage().cost_usd(pricing);
+                ai_client = current;
+                let config = app.get_config().await;
+                model = config.ai_model();
+                include_suggestions = config.include_suggestions;
+                pricing = crate::ai::usage::pricing_for(&config.ai_provider, &model);
+            }
 
-                    // Keep only last 5 thoughts to prevent memory growth
-                    if ai_thoughts.len() > 5 {
-                        let drain_count = ai_thoughts.len() - 5;
-                        ai_thoughts.drain(0..drain_count);
+            // Identical code and context analyzed by the same model, in this run or an earlier one
+            let cache_key = thought_cache
+                .as_ref()
+                .and_then(|_| ThoughtCache::key(&model, include_suggestions, &request));
+            if let (Some(cache), Some(key)) = (&thought_cache, &cache_key) {
+                let cached = cache.lock().await.get(key);
+                if let Some(thoughts) = cached {
+                    tracing::debug!("Reusing {} cached thoughts for request {}", thoughts.len(), request.id);
+                    Self::apply_ai_result(&app, &analyzer, request, Ok(thoughts)).await;
+                    if !*app.running.lock().await {
+                        break;
                     }
+                    continue;
+                }
+            }
 
-                    // Record AI response if recording
-                    if *app.is_recording.lock().await {
-                        if let Some(recorder) = app.session_recorder.lock().await.as_mut() {
-                            recorder.record_event(EventType::AiResponse, serde_json::json!({
-                                "request_id": request.id,
-                                "thoughts_count": ai_thoughts.len(),
-                                "timestamp": Utc::now()
-                            }));
-                        }
-                    }
+            // Past the session's cost limit, fall back to the local checks until it is raised
+            let spent = earlier_cost + ai_client.usage().cost_usd(pricing);
+            let limit = *app.session_cost_limit.lock().await;
+            if let Some(limit) = limit.filter(|limit| spent >= *limit) {
+                if !cost_limit_reached {
+                    cost_limit_reached = true;
+                    tracing::warn!("Session AI cost limit reached: ${:.2} of ${:.2}", spent, limit);
+                    app.add_thought(Self::cost_limit_thought(spent, limit)).await;
                 }
-                Err(e) => {
-                    tracing::error!("AI request failed: {}", e);
 
-                    // Add error thought
-                    let error_thought = Thought {
-                        id: uuid::Uuid::new_v4().to_string(),
-                        timestamp: Utc::now(),
-                        thought_type: ThoughtType::Error,
-                        content: format!("AI analysis failed: {}", e),
-                        file_path: request.file_path,
-                        line_number: None,
-                        confidence: 0.0,
-                        suggestions: vec![],
-                    };
+                Self::run_locally(&app, &analyzer, request, "the session's AI cost limit was reached").await;
+                if !*app.running.lock().await {
+                    break;
+                }
+                continue;
+            }
+            cost_limit_reached = false;
 
-                    app.ai_thoughts.lock().await.push(error_thought);
+            // While the provider cools down after repeated failures, only the local checks run
+            if let Some(wait) = breaker.acquire(std::time::Instant::now()) {
+                let reason = if wait.is_zero() {
+                    "AI is unavailable while a retry is checking on it".to_string()
+                } else {
+                    format!("AI is unavailable for another {}s", wait.as_secs().max(1))
+                };
+                Self::run_locally(&app, &analyzer, request, &reason).await;
+                if !*app.running.lock().await {
+                    break;
                 }
+                continue;
             }
 
+            let generation = generations.start(&request);
+            let worker_app = app.clone();
+            let (ai_client, analyzer, generations, breaker, thought_cache) =
+                (ai_client.clone(), analyzer.clone(), generations.clone(), breaker.clone(), thought_cache.clone());
+
+            tokio::spawn(async move {
+                if matches!(request.request_type, AiRequestType::Suggest) {
+                    let result = ai_client.suggest(&request).await;
+                    Self::track_provider_health(&worker_app, &breaker, result.is_ok()).await;
+                    // A newer request of the same kind for the file started meanwhile; its results win
+                    if generations.finish(&request, generation) {
+                        Self::apply_suggestions(&worker_app, request, result).await;
+                    }
+                } else {
+                    let result = ai_client.respond(&request).await;
+                    Self::track_provider_health(&worker_app, &breaker, result.is_ok()).await;
+                    if let (Some(cache), Some(key), Ok(thoughts)) = (&thought_cache, &cache_key, &result) {
+                        if let Err(e) = cache.lock().await.put(key, thoughts) {
+                            tracing::warn!("Failed to cache thoughts for request {}: {}", request.id, e);
+                        }
+                    }
+                    // A newer request of the same kind for the file started meanwhile; its results win
+                    if generations.finish(&request, generation) {
+                        Self::apply_ai_result(&worker_app, &analyzer, request, result).await;
+                    } else {
+                        tracing::debug!("Discarding stale results for request {}", request.id);
+                    }
+                }
+
+                worker_app.mark_changed();
+                drop(permit);
+            });
+
             if !*app.running.lock().await {
                 break;
             }
@@ -343,8 +878,193 @@ impl App {
         Ok(())
     }
 
+    /// The on-disk thought cache, or `None` when it is turned off or can't be opened
+    fn open_thought_cache(config: &Config) -> Option<ThoughtCache> {
+        if config.thought_cache_max_mb == 0 {
+            return None;
+        }
+
+        let max_bytes = config.thought_cache_max_mb * 1024 * 1024;
+        let max_age = std::time::Duration::from_secs(config.thought_cache_max_age_days * 24 * 60 * 60);
+        match crate::ai::cache::get_cache_directory().and_then(|dir| ThoughtCache::open(&dir, max_bytes, max_age)) {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                tracing::warn!("Thought cache unavailable, analyses won't be reused across runs: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Handles a request without the provider: analyses get the local pattern checks,
+    /// anything else is skipped with a status message saying why
+    async fn run_locally(app: &App, analyzer: &crate::ai::analyzer::CodeAnalyzer, request: AiRequest, reason: &str) {
+        if matches!(request.request_type, AiRequestType::Analyze) {
+            let thoughts = analyzer.analyze_code_patterns(&request.content, request.file_path.as_deref());
+            Self::apply_ai_result(app, analyzer, request, Ok(thoughts)).await;
+        } else {
+            tracing::info!("Skipping AI request {}: {}", request.id, reason);
+            app.set_status(format!("AI request skipped: {}", reason)).await;
+        }
+    }
+
+    /// Feeds a request's outcome to the circuit breaker, announcing once when it opens
+    async fn track_provider_health(app: &App, breaker: &CircuitBreaker, succeeded: bool) {
+        if succeeded {
+            breaker.record_success();
+        } else if let Some(cooldown) = breaker.record_failure(std::time::Instant::now()) {
+            tracing::warn!("AI provider keeps failing; pausing requests for {:?}", cooldown);
+            app.add_thought(Thought::new(
+                ThoughtType::Warning,
+                format!(
+                    "🔌 AI unavailable, retrying in {}s. Only local checks run until then.",
+                    cooldown.as_secs()
+                ),
+                None,
+                None,
+                1.0,
+            )).await;
+        }
+    }
+
+    async fn apply_ai_result(
+        app: &App,
+        analyzer: &crate::ai::analyzer::CodeAnalyzer,
+        request: AiRequest,
+        result: Result<Vec<Thought>, CocoError>,
+    ) {
+        match result {
+            Ok(mut thoughts) => {
+                let config = app.get_config().await;
+                // Only model output is held to the confidence threshold; local rule findings carry a rule id
+                thoughts.retain(|t| match t.rule_id {
+                    Some(_) => !config.is_thought_suppressed(&t.thought_type),
+                    None => config.is_thought_shown(t),
+                });
+
+                // Tell comments on code just touched apart from ones on older code
+                let recent_changes = app.recent_changes.lock().await;
+                for thought in &mut thoughts {
+                    let ranges = thought.file_path.as_ref().and_then(|path| recent_changes.get(path));
+                    thought.in_recent_change = matches!(
+                        (ranges, thought.line_number),
+                        (Some(ranges), Some(line)) if ranges.iter().any(|(start, end)| (*start..=*end).contains(&line))
+                    );
+                }
+                drop(recent_changes);
+
+                // Bring the most recently referenced line into view
+                let latest_line = thoughts.iter().rev().find_map(|t| t.line_number);
+                if let Some(line_number) = latest_line {
+                    if *app.current_file.lock().await == request.file_path {
+                        app.scroll_code_to_line(line_number).await;
+                    }
+                }
+
+                // Digest of this pass, pushed last so it survives the trim below
+                let summary = if config.analysis_summary
+                    && matches!(request.request_type, AiRequestType::Analyze)
+                    && !config.is_thought_suppressed(&ThoughtType::Complete)
+                {
+                    Some(Self::summary_thought(analyzer, &thoughts, request.file_path.clone()))
+                } else {
+                    None
+                };
+
+                thoughts.extend(summary);
+
+                // Record each thought as it is generated if recording
+                if *app.is_recording.lock().await {
+                    if let Some(recorder) = app.session_recorder.lock().await.as_mut() {
+                        recorder.record_thoughts(&thoughts);
+                    }
+                }
+
+                let thoughts_count = app.append_thoughts(thoughts).await;
+
+                // Record AI response if recording
+                if *app.is_recording.lock().await {
+                    if let Some(recorder) = app.session_recorder.lock().await.as_mut() {
+                        recorder.record_event(EventType::AiResponse, serde_json::json!({
+                            "request_id": request.id,
+                            "thoughts_count": thoughts_count,
+                            "timestamp": Utc::now()
+                        }));
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::error!("AI request failed: {}", e);
+
+                let error_thought = crate::ai::prompt::error_thought(&request, &e);
+                app.append_thoughts(vec![error_thought]).await;
+            }
+        }
+    }
+
+    /// Opens the suggestions list with the ideas returned for `request`, and records them
+    async fn apply_suggestions(app: &App, request: AiRequest, result: Result<Vec<Suggestion>, CocoError>) {
+        let file_path = request.file_path.clone().unwrap_or_default();
+        let suggestions = match result {
+            Ok(suggestions) => suggestions,
+            Err(e) => {
+                tracing::error!("Suggestions request failed: {}", e);
+                app.set_status(format!("Suggestions failed: {}", e)).await;
+                return;
+            }
+        };
+
+        if *app.is_recording.lock().await {
+            if let Some(recorder) = app.session_recorder.lock().await.as_mut() {
+                recorder.record_suggestions(&request.id, &suggestions, request.file_path.as_deref());
+            }
+        }
+
+        if suggestions.is_empty() {
+            app.set_status(format!("No suggestions for {}", file_path)).await;
+            return;
+        }
+
+        app.set_status(format!("{} suggestions for {}", suggestions.len(), file_path)).await;
+        *app.suggestion_list.lock().await = Some(SuggestionList { file_path, suggestions });
+    }
+
+    fn cost_limit_thought(spent: f64, limit: f64) -> Thought {
+        Thought::new(
+            ThoughtType::Warning,
+            format!(
+                "💸 AI cost limit reached: about ${:.2} spent of ${:.2}. Only local checks run now; press L to raise the limit for this session.",
+                spent, limit
+            ),
+            None,
+            None,
+            1.0,
+        )
+    }
+
+    fn summary_thought(
+        analyzer: &crate::ai::analyzer::CodeAnalyzer,
+        thoughts: &[Thought],
+        file_path: Option<String>,
+    ) -> Thought {
+        let confidence = if thoughts.is_empty() {
+            1.0
+        } else {
+            thoughts.iter().map(|t| t.confidence).sum::<f32>() / thoughts.len() as f32
+        };
+
+        Thought::new(
+            ThoughtType::Complete,
+            analyzer.generate_summary(thoughts),
+            file_path,
+            None,
+            confidence,
+        )
+    }
+
     async fn handle_ui_events(app: App) -> Result<()> {
         let mut rx = app.ui_rx.lock().await;
+        let mut analyzer = crate::ai::analyzer::CodeAnalyzer::new();
+        analyzer.set_max_line_length(app.get_config().await.max_line_length);
 
         while let Some(event) = rx.recv().await {
             tracing::debug!("Handling UI event: {:?}", event.event_type);
@@ -360,13 +1080,288 @@ impl App {
                     };
                     tracing::info!("View mode changed to: {:?}", *mode);
                 }
+                UiEventType::ToggleThoughtGrouping => {
+                    let mut grouping = app.thought_grouping.lock().await;
+                    grouping.by_file = !grouping.by_file;
+                    let by_file = grouping.by_file;
+                    drop(grouping);
+                    app.set_status(if by_file { "Thoughts grouped by file" } else { "Thoughts in order of arrival" }.to_string()).await;
+                }
+                UiEventType::ToggleCompactThoughts => {
+                    let mut grouping = app.thought_grouping.lock().await;
+                    grouping.compact = !grouping.compact;
+                    let compact = grouping.compact;
+                    drop(grouping);
+                    app.set_status(if compact { "Similar consecutive thoughts merged" } else { "Every thought listed" }.to_string()).await;
+                }
+                UiEventType::ToggleGroupCollapsed => {
+                    let thoughts = app.get_thoughts().await;
+                    let selected = app.get_selected_thought().await.and_then(|i| thoughts.get(i));
+                    let mut grouping = app.thought_grouping.lock().await;
+                    match selected {
+                        Some(thought) if grouping.by_file => {
+                            let file = crate::ui::widgets::thought_group(thought).to_string();
+                            if !grouping.collapsed.remove(&file) {
+                                grouping.collapsed.insert(file);
+                            }
+                        }
+                        _ => {
+                            drop(grouping);
+                            app.set_status("Group thoughts with g, then select one to fold its file".to_string()).await;
+                        }
+                    }
+                }
+                UiEventType::ToggleLineNumbers | UiEventType::ToggleSyntaxHighlighting => {
+                    let mut display = app.code_display.lock().await;
+                    let (setting, enabled) = if matches!(event.event_type, UiEventType::ToggleLineNumbers) {
+                        display.line_numbers = !display.line_numbers;
+                        ("Line numbers", display.line_numbers)
+                    } else {
+                        display.syntax_highlighting = !display.syntax_highlighting;
+                        ("Syntax highlighting", display.syntax_highlighting)
+                    };
+                    drop(display);
+                    app.set_status(format!("{} {}", setting, if enabled { "on" } else { "off" })).await;
+                }
+                UiEventType::ScrollCode(delta) => {
+                    let line_count = app.current_code.lock().await.lines().count();
+                    let mut scroll = app.code_scroll.lock().await;
+                    *scroll = scroll
+                        .saturating_add_signed(delta)
+                        .min(line_count.saturating_sub(1));
+                }
+                UiEventType::SelectNextThought | UiEventType::SelectPreviousThought => {
+                    let thoughts = app.get_thoughts().await;
+                    let selected = {
+                        let mut selection = app.thought_selection.lock().await;
+                        if matches!(event.event_type, UiEventType::SelectNextThought) {
+                            selection.select_next(thoughts.len());
+                        } else {
+                            selection.select_previous(thoughts.len());
+                        }
+                        selection.selected_index
+                    };
+
+                    // Jump to the finding, but only if it points into the file on screen
+                    if let Some(thought) = selected.and_then(|i| thoughts.get(i)) {
+                        if let Some(line_number) = thought.line_number {
+                            if *app.current_file.lock().await == thought.file_path {
+                                app.scroll_code_to_line(line_number).await;
+                            }
+                        }
+                    }
+                }
+                UiEventType::SelectFile => {
+                    let file_count = app.open_file_picker().await;
+                    if file_count == 0 {
+                        app.set_status("No files to open in this project".to_string()).await;
+                    }
+                }
+                UiEventType::OpenRecentFiles => {
+                    let file_count = app.open_recent_files().await;
+                    if file_count == 0 {
+                        app.set_status("No files opened or analyzed yet this session".to_string()).await;
+                    }
+                }
+                UiEventType::MoveFileSelection(delta) => {
+                    if let Some(picker) = app.file_picker.lock().await.as_mut() {
+                        picker.move_selection(delta);
+                    }
+                }
+                UiEventType::OpenSelectedFile => {
+                    if let Err(e) = app.open_selected_file().await {
+                        app.set_status(format!("Can't open file: {}", e)).await;
+                    }
+                }
+                UiEventType::CloseFilePicker => {
+                    *app.file_picker.lock().await = None;
+                }
+                UiEventType::DismissThought => {
+                    match app.dismiss_selected_thought().await {
+                        Some(_) => app.set_status("Thought dismissed".to_string()).await,
+                        None => app.set_status("Select a thought to dismiss with Up/Down".to_string()).await,
+                    }
+                }
+                UiEventType::Annotate => {
+                    if *app.is_recording.lock().await {
+                        *app.annotation_draft.lock().await = Some(String::new());
+                    } else {
+                        app.set_status("Annotations are saved only while recording (coco record)".to_string()).await;
+                    }
+                }
+                UiEventType::AnnotationInput(c) => {
+                    if let Some(draft) = app.annotation_draft.lock().await.as_mut() {
+                        if draft.chars().count() < ANNOTATION_MAX_CHARS {
+                            draft.push(c);
+                        }
+                    }
+                }
+                UiEventType::AnnotationBackspace => {
+                    if let Some(draft) = app.annotation_draft.lock().await.as_mut() {
+                        draft.pop();
+                    }
+                }
+                UiEventType::SubmitAnnotation => {
+                    let note = app.annotation_draft.lock().await.take().unwrap_or_default();
+                    if app.record_annotation(note.trim()).await {
+                        app.set_status("Annotation saved".to_string()).await;
+                    }
+                }
+                UiEventType::CancelAnnotation => {
+                    *app.annotation_draft.lock().await = None;
+                }
+                UiEventType::RaiseCostLimit => {
+                    let step = app.get_config().await.max_session_cost_usd;
+                    let mut limit = app.session_cost_limit.lock().await;
+                    match (*limit, step) {
+                        (Some(current), Some(step)) => {
+                            *limit = Some(current + step);
+                            drop(limit);
+                            app.set_status(format!("AI cost limit raised to ${:.2} for this session", current + step)).await;
+                        }
+                        _ => {
+                            drop(limit);
+                            app.set_status("No AI cost limit is set (COCO_MAX_SESSION_COST_USD)".to_string()).await;
+                        }
+                    }
+                }
+                UiEventType::RequestSuggestions => {
+                    match app.build_suggest_request().await {
+                        Some(request) => {
+                            app.set_status(format!("Asking for suggestions on {}", request.file_path.as_deref().unwrap_or_default())).await;
+                            app.enqueue_ai_request(request);
+                        }
+                        None => app.set_status("Open a file to ask for suggestions".to_string()).await,
+                    }
+                }
+                UiEventType::ExplainFile => {
+                    match app.build_explain_request().await {
+                        Some(request) => {
+                            app.set_status(format!("Asking for an explanation of {}", request.file_path.as_deref().unwrap_or_default())).await;
+                            app.enqueue_ai_request(request);
+                        }
+                        None => app.set_status("Open a file to have it explained".to_string()).await,
+                    }
+                }
+                UiEventType::FixSelectedThought => {
+                    match app.build_fix_request().await {
+                        Ok(request) => {
+                            app.set_status("Asking for a fix for the selected thought".to_string()).await;
+                            app.enqueue_ai_request(request);
+                        }
+                        Err(e) => app.set_status(format!("Can't ask for a fix: {}", e)).await,
+                    }
+                }
+                UiEventType::CloseSuggestions => {
+                    *app.suggestion_list.lock().await = None;
+                }
                 UiEventType::ClearThoughts => {
                     app.ai_thoughts.lock().await.clear();
+                    app.thought_selection.lock().await.selected_index = None;
                     tracing::info!("Cleared all AI thoughts");
                 }
+                UiEventType::MetaReview => {
+                    match app.build_meta_request().await {
+                        Some(request) => app.enqueue_ai_request(request),
+                        None => app.set_status("No analyzed files available for a project review yet".to_string()).await,
+                    }
+                }
+                UiEventType::OpenRequestTypePicker => {
+                    let thoughts = app.get_thoughts().await;
+                    let thought_file = app
+                        .get_selected_thought()
+                        .await
+                        .and_then(|i| thoughts.get(i))
+                        .and_then(|thought| thought.file_path.clone());
+                    match thought_file.or(app.get_current_file().await) {
+                        Some(file_path) => {
+                            *app.request_type_picker.lock().await = Some(RequestTypePicker { file_path, selected: 0 });
+                        }
+                        None => app.set_status("Open a file or select a thought to re-run it".to_string()).await,
+                    }
+                }
+                UiEventType::MoveRequestTypeSelection(delta) => {
+                    if let Some(picker) = app.request_type_picker.lock().await.as_mut() {
+                        picker.selected = picker.selected.saturating_add_signed(delta).min(REQUEST_TYPE_CHOICES.len() - 1);
+                    }
+                }
+                UiEventType::RunSelectedRequestType => {
+                    let Some(picker) = app.request_type_picker.lock().await.take() else {
+                        continue;
+                    };
+                    let (request_type, _) = &REQUEST_TYPE_CHOICES[picker.selected];
+                    match app.build_request_for(request_type.clone(), &picker.file_path).await {
+                        Ok(request) => {
+                            app.set_status(format!("Running {:?} on {}", request.request_type, picker.file_path)).await;
+                            app.enqueue_ai_request(request);
+                        }
+                        Err(e) => app.set_status(format!("Can't run {:?}: {}", request_type, e)).await,
+                    }
+                }
+                UiEventType::CloseRequestTypePicker => {
+                    *app.request_type_picker.lock().await = None;
+                }
+                UiEventType::ExportThoughts => {
+                    let thoughts = app.get_thoughts().await;
+                    let format = app.get_config().await.thought_export_format;
+                    match crate::report::export_thoughts(&thoughts, format) {
+                        Ok(path) => app.set_status(format!("Exported {} thoughts to {}", thoughts.len(), path.display())).await,
+                        Err(e) => {
+                            tracing::error!("Failed to export thoughts: {}", e);
+                            app.set_status(format!("Export failed: {}", e)).await;
+                        }
+                    }
+                }
+                UiEventType::Refresh => {
+                    match app.get_current_file().await {
+                        Some(file_path) => match app.analyze_file(&analyzer, &file_path).await {
+                            Ok(()) => app.set_status(format!("Re-analyzing {}", file_path)).await,
+                            Err(e) => app.set_status(format!("Can't re-analyze {}: {}", file_path, e)).await,
+                        },
+                        None => app.set_status("Open a file to analyze it".to_string()).await,
+                    }
+                }
+                UiEventType::CycleModel => {
+                    if let Err(e) = app.switch_to_next_model().await {
+                        tracing::error!("Failed to switch models: {}", e);
+                        app.set_status(format!("Model switch failed: {}", e)).await;
+                    }
+                }
                 UiEventType::AcceptSuggestion => {
-                    // TODO: Implement suggestion acceptance
-                    tracing::info!("Suggestion accepted");
+                    match app.preview_suggestion().await {
+                        Ok(()) => tracing::info!("Previewing suggestion"),
+                        Err(e) => app.set_status(format!("Can't apply suggestion: {}", e)).await,
+                    }
+                }
+                UiEventType::ConfirmEdit => {
+                    match app.confirm_pending_edit().await {
+                        Ok(path) if app.is_dry_run().await => {
+                            app.set_status(format!("Dry run: suggestion for {} recorded, nothing written", path)).await;
+                        }
+                        Ok(path) => app.set_status(format!("Applied suggestion to {} ([u] to undo)", path)).await,
+                        Err(e) => app.set_status(format!("Suggestion not applied: {}", e)).await,
+                    }
+                }
+                UiEventType::ToggleDryRun => {
+                    let mut dry_run = app.dry_run_edits.lock().await;
+                    *dry_run = !*dry_run;
+                    let message = if *dry_run {
+                        "Dry run on: accepted suggestions are shown and recorded, not written"
+                    } else {
+                        "Dry run off: accepted suggestions are written to disk"
+                    };
+                    drop(dry_run);
+                    app.set_status(message.to_string()).await;
+                }
+                UiEventType::CancelEdit => {
+                    *app.pending_edit.lock().await = None;
+                    tracing::info!("Suggestion preview cancelled");
+                }
+                UiEventType::UndoEdit => {
+                    match app.undo_last_edit().await {
+                        Ok(path) => app.set_status(format!("Reverted last change to {}", path)).await,
+                        Err(e) => app.set_status(format!("Nothing undone: {}", e)).await,
+                    }
                 }
                 UiEventType::RejectSuggestion => {
                     // TODO: Implement suggestion rejection
@@ -380,8 +1375,9 @@ impl App {
                 _ => {}
             }
 
-            // Record UI event if recording
-            if *app.is_recording.lock().await {
+            // Record UI event if recording; the note's keystrokes are recorded as the note itself
+            let typing_note = matches!(event.event_type, UiEventType::AnnotationInput(_) | UiEventType::AnnotationBackspace);
+            if !typing_note && *app.is_recording.lock().await {
                 if let Some(recorder) = app.session_recorder.lock().await.as_mut() {
                     recorder.record_event(EventType::UiAction, serde_json::json!({
                         "event_type": format!("{:?}", event.event_type),
@@ -390,6 +1386,34 @@ impl App {
                 }
             }
 
+            app.mark_changed();
+
+            if !*app.running.lock().await {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_config_reloads(app: App) -> Result<()> {
+        // Every handler ending stops the app, so without a watcher this one just idles
+        let mut watcher = match Config::config_path().and_then(crate::watcher::ConfigWatcher::new) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                tracing::warn!("Not watching the config file, changes need a restart: {}", e);
+                return std::future::pending().await;
+            }
+        };
+
+        while watcher.changed().await.is_some() {
+            match Config::load().await {
+                Ok(new_config) => app.apply_config(new_config).await,
+                Err(e) => tracing::warn!("Ignoring invalid config change, keeping previous config: {}", e),
+            }
+
+            app.mark_changed();
+
             if !*app.running.lock().await {
                 break;
             }
@@ -398,8 +1422,549 @@ impl App {
         Ok(())
     }
 
+    pub async fn apply_config(&self, new_config: Config) {
+        let old_config = self.get_config().await;
+        let changed_fields = old_config.changed_fields(&new_config);
+
+        if changed_fields.is_empty() {
+            return;
+        }
+
+        if let Err(e) = new_config.validate().await {
+            tracing::warn!("Ignoring invalid config change, keeping previous config: {}", e);
+            return;
+        }
+
+        // The watcher is already running on the old directories; those need a restart
+        if changed_fields.iter().any(|field| field == "watch_directories") {
+            tracing::warn!("watch_directories changed; restart CoCo to watch the new directories");
+        }
+        if changed_fields.iter().any(|field| field == "recursive_watch") {
+            tracing::warn!("recursive_watch changed; restart CoCo to apply it to the watcher");
+        }
+        if changed_fields.iter().any(|field| field == "exclude_dirs") {
+            tracing::warn!("exclude_dirs changed; the file picker uses it now, the watcher after a restart");
+        }
+        if changed_fields.iter().any(|field| field == "notify_channel_capacity") {
+            tracing::warn!("notify_channel_capacity changed; restart CoCo to resize the watcher's channel");
+        }
+        if changed_fields.iter().any(|field| field.starts_with("max_file_size")) {
+            tracing::warn!("File size limits changed; analysis uses them now, the watcher after a restart");
+        }
+
+        if changed_fields.iter().any(|field| field == "max_line_length") {
+            tracing::warn!("max_line_length changed; restart CoCo to apply it to the local checks");
+        }
+        if changed_fields.iter().any(|field| field == "ai_concurrency") {
+            tracing::warn!("ai_concurrency changed; restart CoCo to resize the analysis workers");
+        }
+        if changed_fields.iter().any(|field| field.starts_with("ai_failure_")) {
+            tracing::warn!("AI failure cooldown settings changed; restart CoCo to apply them");
+        }
+
+        // Requests already sent finish on the old provider; handle_ai_requests picks up the new one
+        let provider_changed = changed_fields.iter().any(|field| {
+            field == "ai_provider" || field == "include_suggestions" || field.starts_with("anthropic_") || field.starts_with("openai_")
+        });
+        if provider_changed {
+            match crate::ai::provider_from_config(&new_config) {
+                Ok(provider) => *self.ai_provider.lock().await = Arc::from(provider),
+                Err(e) => tracing::warn!("Keeping the previous AI provider, the new settings don't build one: {}", e),
+            }
+        }
+
+        self.ai_queue.set_capacity(new_config.ai_queue_capacity);
+        if let Some(recorder) = self.session_recorder.lock().await.as_mut() {
+            recorder.set_max_events(new_config.session_max_events);
+            recorder.set_retention_policy(new_config.session_retention.clone());
+        }
+        *self.config.lock().await = Arc::new(new_config);
+        tracing::info!("Config reloaded, changed fields: {}", changed_fields.join(", "));
+
+        // Record config change if recording
+        if *self.is_recording.lock().await {
+            if let Some(recorder) = self.session_recorder.lock().await.as_mut() {
+                recorder.record_event(EventType::ConfigChange, serde_json::json!({
+                    "type": "reload",
+                    "changed_fields": changed_fields,
+                    "timestamp": Utc::now()
+                }));
+            }
+        }
+    }
+
+    /// Moves to the next of `available_models`, rebuilding the provider for it and
+    /// re-analyzing the file on screen with the new model
+    pub async fn switch_to_next_model(&self) -> Result<()> {
+        let config = self.get_config().await;
+        let Some(model) = config.next_model() else {
+            self.set_status("No other models to switch to (COCO_AVAILABLE_MODELS)".to_string()).await;
+            return Ok(());
+        };
+
+        let previous_model = config.ai_model();
+        let mut new_config = (*config).clone();
+        new_config.set_ai_model(model.clone());
+        let provider = crate::ai::provider_from_config(&new_config)?;
+
+        *self.ai_provider.lock().await = Arc::from(provider);
+        *self.config.lock().await = Arc::new(new_config);
+        tracing::info!("Switched AI model from {} to {}", previous_model, model);
+
+        if *self.is_recording.lock().await {
+            if let Some(recorder) = self.session_recorder.lock().await.as_mut() {
+                recorder.record_event(EventType::ConfigChange, serde_json::json!({
+                    "type": "model_switch",
+                    "previous_model": previous_model,
+                    "model": model,
+                    "timestamp": Utc::now()
+                }));
+            }
+        }
+
+        match self.get_current_file().await {
+            Some(file_path) => {
+                let request = self.build_request_for(AiRequestType::Analyze, &file_path).await?;
+                self.enqueue_ai_request(request);
+                self.set_status(format!("Switched to {}, re-analyzing {}", model, file_path)).await;
+            }
+            None => self.set_status(format!("Switched to {}", model)).await,
+        }
+        Ok(())
+    }
+
+    /// Builds a diff preview for the selected thought's first applicable suggestion,
+    /// falling back to the latest thought that has one
+    pub async fn preview_suggestion(&self) -> Result<()> {
+        let thoughts = self.get_thoughts().await;
+        let selected = self.get_selected_thought().await.and_then(|i| thoughts.get(i));
+
+        let candidates: Vec<&Thought> = match selected {
+            Some(thought) => vec![thought],
+            None => thoughts.iter().rev().collect(),
+        };
+
+        let (thought, suggestion) = candidates
+            .into_iter()
+            .find_map(|thought| {
+                thought
+                    .suggestions
+                    .iter()
+                    .find(|s| crate::edits::is_applicable(thought, s))
+                    .map(|s| (thought, s))
+            })
+            .ok_or_else(|| CocoError::InvalidAction("no suggestion with code and a target line".to_string()))?;
+
+        let file_path = thought.file_path.as_deref().unwrap_or_default();
+        let original = tokio::fs::read_to_string(file_path).await?;
+        let edit = crate::edits::prepare_edit(thought, suggestion, &original)?;
+
+        *self.pending_edit.lock().await = Some(edit);
+        Ok(())
+    }
+
+    /// Writes the previewed change, keeping the previous content for undo. In dry-run
+    /// mode the change is only logged and recorded, and the file is left alone.
+    pub async fn confirm_pending_edit(&self) -> Result<String> {
+        let edit = self
+            .pending_edit
+            .lock()
+            .await
+            .take()
+            .ok_or_else(|| CocoError::InvalidAction("no suggestion is being previewed".to_string()))?;
+
+        // Never overwrite edits the user made while looking at the preview
+        let current = tokio::fs::read_to_string(&edit.file_path).await?;
+        if current != edit.original {
+            return Err(CocoError::InvalidAction(format!("{} changed since the preview", edit.file_path)));
+        }
+
+        let dry_run = self.is_dry_run().await;
+        if dry_run {
+            tracing::info!("Dry run: would apply suggestion '{}' to {}:\n{}", edit.title, edit.file_path, edit.diff);
+        } else {
+            tokio::fs::write(&edit.file_path, &edit.updated).await?;
+            tracing::info!("Applied suggestion '{}' to {}", edit.title, edit.file_path);
+        }
+
+        if *self.is_recording.lock().await {
+            if let Some(recorder) = self.session_recorder.lock().await.as_mut() {
+                // Full contents only where recordings keep contents anyway, and never past the size limit
+                let config = self.get_config().await;
+                let size_limit = config.max_file_size_for(std::path::Path::new(&edit.file_path));
+                let include_content = config.session_record_content
+                    && edit.original.len().max(edit.updated.len()) as u64 <= size_limit;
+                recorder.record_applied_edit(&edit, include_content, dry_run);
+            }
+        }
+
+        if dry_run {
+            return Ok(edit.file_path);
+        }
+
+        *self.last_applied_edit.lock().await = Some(AppliedEdit {
+            file_path: edit.file_path.clone(),
+            previous: edit.original,
+            applied: edit.updated,
+        });
+
+        Ok(edit.file_path)
+    }
+
+    /// Restores the file touched by the last applied suggestion
+    pub async fn undo_last_edit(&self) -> Result<String> {
+        let mut last_applied = self.last_applied_edit.lock().await;
+        let edit = last_applied
+            .as_ref()
+            .ok_or_else(|| CocoError::InvalidAction("no applied suggestion to revert".to_string()))?;
+
+        let current = tokio::fs::read_to_string(&edit.file_path).await?;
+        if current != edit.applied {
+            return Err(CocoError::InvalidAction(format!("{} changed after the suggestion was applied", edit.file_path)));
+        }
+
+        tokio::fs::write(&edit.file_path, &edit.previous).await?;
+        tracing::info!("Reverted suggestion applied to {}", edit.file_path);
+
+        let file_path = edit.file_path.clone();
+        *last_applied = None;
+        Ok(file_path)
+    }
+
+    pub async fn set_status(&self, message: String) {
+        tracing::info!("{}", message);
+        *self.status_message.lock().await = Some((message, std::time::Instant::now()));
+        self.mark_changed();
+
+        // Redraw once more when the message expires
+        let app = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(STATUS_MESSAGE_DURATION).await;
+            app.mark_changed();
+        });
+    }
+
+    pub fn mark_changed(&self) {
+        self.state_version.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get_state_version(&self) -> u64 {
+        self.state_version.load(Ordering::Relaxed)
+    }
+
+    pub async fn get_status_message(&self) -> Option<String> {
+        match &*self.status_message.lock().await {
+            Some((message, shown_at)) if shown_at.elapsed() < STATUS_MESSAGE_DURATION => Some(message.clone()),
+            _ => None,
+        }
+    }
+
+    pub async fn get_pending_edit(&self) -> Option<PendingEdit> {
+        self.pending_edit.lock().await.clone()
+    }
+
+    pub async fn get_annotation_draft(&self) -> Option<String> {
+        self.annotation_draft.lock().await.clone()
+    }
+
+    /// Records `note` against the current file, returning whether anything was recorded
+    async fn record_annotation(&self, note: &str) -> bool {
+        if note.is_empty() || !*self.is_recording.lock().await {
+            return false;
+        }
+
+        let current_file = self.get_current_file().await;
+        match self.session_recorder.lock().await.as_mut() {
+            Some(recorder) => {
+                recorder.record_annotation(note, current_file.as_deref());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub async fn get_suggestion_list(&self) -> Option<SuggestionList> {
+        self.suggestion_list.lock().await.clone()
+    }
+
+    pub async fn get_request_type_picker(&self) -> Option<RequestTypePicker> {
+        self.request_type_picker.lock().await.clone()
+    }
+
+    pub async fn get_file_picker(&self) -> Option<FilePicker> {
+        self.file_picker.lock().await.clone()
+    }
+
+    /// Lists the project's files for the picker, highlighting the current file.
+    /// Returns how many files were found; the picker only opens if there are any.
+    async fn open_file_picker(&self) -> usize {
+        let root = crate::util::project_root().to_path_buf();
+        let exclude_dirs = self.get_config().await.exclude_dirs.clone();
+        let files = tokio::task::spawn_blocking(move || {
+            let cocoignore = crate::watcher::load_cocoignore(&root);
+            crate::watcher::list_files(&root, FILE_PICKER_MAX_FILES, &exclude_dirs, &cocoignore)
+        })
+            .await
+            .unwrap_or_default();
+
+        let count = files.len();
+        if count > 0 {
+            let mut picker = FilePicker::new(files);
+            if let Some(current) = self.current_file.lock().await.as_deref() {
+                if let Some(index) = picker.files.iter().position(|f| f.as_path() == std::path::Path::new(current)) {
+                    picker.selected = index;
+                }
+            }
+            *self.file_picker.lock().await = Some(picker);
+        }
+        count
+    }
+
+    /// Opens the picker on this session's recent files, highlighting the one before
+    /// the current file so Enter flips back to it. Returns how many files it lists.
+    async fn open_recent_files(&self) -> usize {
+        let files: Vec<std::path::PathBuf> = self.recent_files.lock().await.iter().map(Into::into).collect();
+
+        let count = files.len();
+        if count > 0 {
+            let current = self.current_file.lock().await.clone();
+            let mut picker = FilePicker::with_title(files, "Recent files");
+            if count > 1 && picker.files[0].to_str() == current.as_deref() {
+                picker.selected = 1;
+            }
+            *self.file_picker.lock().await = Some(picker);
+        }
+        count
+    }
+
+    /// Closes the picker and sends the chosen file through the file event pipeline as
+    /// an access event, so it is displayed (and analyzed with `analyze_on_focus`)
+    /// like a saved file without being recorded as a change. Cached files are taken
+    /// from the cache instead of being read again.
+    async fn open_selected_file(&self) -> Result<()> {
+        let path = self
+            .file_picker
+            .lock()
+            .await
+            .take()
+            .and_then(|picker| picker.selected_file().cloned())
+            .ok_or_else(|| CocoError::InvalidAction("No file selected".to_string()))?;
+
+        let cached = self.file_cache.lock().await.get(path.to_string_lossy().as_ref()).cloned();
+        let content = match cached {
+            Some(content) => content,
+            None => {
+                let max_size = self.get_config().await.max_file_size_for(&path);
+                crate::watcher::read_file(&path, max_size).await?
+            }
+        };
+
+        let event = FileEvent {
+            path,
+            content,
+            event_type: notify::EventKind::Access(notify::event::AccessKind::Open(notify::event::AccessMode::Any)),
+            timestamp: Utc::now(),
+        };
+        self.file_tx
+            .send(event)
+            .await
+            .map_err(|_| CocoError::Stopped("File event handler"))
+    }
+
+    /// Queues a request for the AI handler without ever blocking the caller
+    pub fn enqueue_ai_request(&self, request: AiRequest) {
+        match self.ai_queue.push(request) {
+            PushOutcome::Queued => self.mark_changed(),
+            PushOutcome::Coalesced => tracing::debug!("Replaced pending AI request for the same file"),
+            PushOutcome::DroppedOldest(dropped) => tracing::warn!(
+                "AI queue full, dropped oldest request for {}",
+                dropped.file_path.as_deref().unwrap_or("project review")
+            ),
+        }
+    }
+
+    /// A `Suggest` request for the file on screen, or `None` if no file is open
+    pub async fn build_suggest_request(&self) -> Option<AiRequest> {
+        let file_path = self.get_current_file().await?;
+        let content = self.current_code.lock().await.clone();
+
+        let cache = self.file_cache.lock().await;
+        let root = crate::util::project_root();
+        let context = crate::ai::context::build_context(std::path::Path::new(&file_path), &content, root, &cache);
+
+        Some(AiRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            request_type: AiRequestType::Suggest,
+            content,
+            file_path: Some(file_path),
+            context,
+            priority: Priority::Medium,
+            selection: None,
+        })
+    }
+
+    /// A request of any type for `file_path`, taking its content from the screen, the
+    /// file cache or the disk. `Fix` and `Meta` go through their own builders.
+    pub async fn build_request_for(&self, request_type: AiRequestType, file_path: &str) -> Result<AiRequest> {
+        match request_type {
+            AiRequestType::Fix => return self.build_fix_request().await,
+            AiRequestType::Meta => {
+                return self
+                    .build_meta_request()
+                    .await
+                    .ok_or_else(|| CocoError::InvalidAction("no files analyzed yet".to_string()));
+            }
+            _ => {}
+        }
+
+        let on_screen = self.get_current_file().await.as_deref() == Some(file_path);
+        let cached = self.file_cache.lock().await.get(file_path).cloned();
+        let content = match (on_screen, cached) {
+            (true, _) => self.current_code.lock().await.clone(),
+            (false, Some(content)) => content,
+            (false, None) => {
+                let path = std::path::Path::new(file_path);
+                let max_size = self.get_config().await.max_file_size_for(path);
+                crate::watcher::read_file(path, max_size).await?
+            }
+        };
+
+        let cache = self.file_cache.lock().await;
+        let root = crate::util::project_root();
+        let context = crate::ai::context::build_context(std::path::Path::new(file_path), &content, root, &cache);
+
+        Ok(AiRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            request_type,
+            content,
+            file_path: Some(file_path.to_string()),
+            context,
+            priority: Priority::Medium,
+            selection: None,
+        })
+    }
+
+    /// An `Explain` request for the file on screen, or `None` if no file is open
+    pub async fn build_explain_request(&self) -> Option<AiRequest> {
+        let file_path = self.get_current_file().await?;
+
+        Some(AiRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            request_type: AiRequestType::Explain,
+            content: self.current_code.lock().await.clone(),
+            file_path: Some(file_path),
+            context: HashMap::new(),
+            priority: Priority::Medium,
+            selection: None,
+        })
+    }
+
+    /// A `Fix` request for the problem described by the selected thought, which
+    /// must be about the file on screen
+    pub async fn build_fix_request(&self) -> Result<AiRequest> {
+        let thoughts = self.get_thoughts().await;
+        let thought = self
+            .get_selected_thought()
+            .await
+            .and_then(|i| thoughts.get(i))
+            .ok_or_else(|| CocoError::InvalidAction("select a thought with Up/Down first".to_string()))?;
+
+        let current_file = self.get_current_file().await;
+        if thought.file_path.is_none() || thought.file_path != current_file {
+            return Err(CocoError::InvalidAction("the selected thought is not about the open file".to_string()));
+        }
+
+        let mut context = HashMap::new();
+        context.insert("error".to_string(), match thought.line_number {
+            Some(line) => format!("{} (line {})", thought.content, line),
+            None => thought.content.clone(),
+        });
+        if let Some(line) = thought.line_number {
+            context.insert("line_number".to_string(), line.to_string());
+        }
+
+        Ok(AiRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            request_type: AiRequestType::Fix,
+            content: self.current_code.lock().await.clone(),
+            file_path: current_file,
+            context,
+            priority: Priority::High,
+            selection: None,
+        })
+    }
+
+    /// Bundles the recently analyzed files, newest first, into a single Meta request
+    /// for a project-level review, or `None` if nothing has been analyzed yet
+    pub async fn build_meta_request(&self) -> Option<AiRequest> {
+        let recent = self.recent_files.lock().await.clone();
+        let cache = self.file_cache.lock().await;
+        if cache.is_empty() {
+            return None;
+        }
+
+        let mut content = String::new();
+        let mut included = Vec::new();
+
+        for path in by_recency(cache.keys(), &recent) {
+            let header = format!("// File: {}\n", path);
+            let mut section = format!("{}{}\n\n", header, cache[path]);
+            if content.len() + section.len() > META_REVIEW_MAX_BYTES {
+                if !included.is_empty() {
+                    tracing::debug!("Project review size limit reached, skipping {}", path);
+                    continue;
+                }
+
+                // The newest file always goes in, cut down to the limit if it is too large alone
+                let marker = "\n// ... truncated\n\n";
+                let mut end = META_REVIEW_MAX_BYTES.saturating_sub(header.len() + marker.len()).min(cache[path].len());
+                while !cache[path].is_char_boundary(end) {
+                    end -= 1;
+                }
+                section = format!("{}{}{}", header, &cache[path][..end], marker);
+            }
+            content.push_str(&section);
+            included.push(path.clone());
+        }
+
+        let mut context = HashMap::new();
+        context.insert("files_reviewed".to_string(), included.join(", "));
+
+        Some(AiRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            request_type: AiRequestType::Meta,
+            content,
+            file_path: None,
+            context,
+            priority: Priority::Low,
+            selection: None,
+        })
+    }
+
+    /// Scrolls the code panel so `line_number` (1-based) sits just below the top
+    pub async fn scroll_code_to_line(&self, line_number: usize) {
+        *self.code_scroll.lock().await = line_number.saturating_sub(1 + CODE_SCROLL_CONTEXT);
+    }
+
     pub async fn add_thought(&self, thought: Thought) {
-        self.ai_thoughts.lock().await.push(thought);
+        self.append_thoughts(vec![thought]).await;
+        self.mark_changed();
+    }
+
+    /// Adds thoughts to the panel, dropping the oldest beyond `max_thoughts`,
+    /// and returns how many are shown
+    async fn append_thoughts(&self, thoughts: Vec<Thought>) -> usize {
+        let max_thoughts = self.get_config().await.max_thoughts.max(1);
+        let mut ai_thoughts = self.ai_thoughts.lock().await;
+        ai_thoughts.extend(thoughts);
+
+        if ai_thoughts.len() > max_thoughts {
+            let drain_count = ai_thoughts.len() - max_thoughts;
+            ai_thoughts.drain(0..drain_count);
+            self.thought_selection.lock().await.remove_front(drain_count);
+        }
+
+        ai_thoughts.len()
     }
 
     pub async fn get_current_file(&self) -> Option<String> {
@@ -414,6 +1979,76 @@ impl App {
         self.ai_thoughts.lock().await.clone()
     }
 
+    /// Removes the selected thought from the panel, recording the dismissal
+    pub async fn dismiss_selected_thought(&self) -> Option<Thought> {
+        let thought = {
+            let mut thoughts = self.ai_thoughts.lock().await;
+            let mut selection = self.thought_selection.lock().await;
+            let index = selection.selected_index.filter(|&i| i < thoughts.len())?;
+            let thought = thoughts.remove(index);
+            selection.remove(index, thoughts.len());
+            thought
+        };
+
+        tracing::info!("Dismissed thought {}", thought.id);
+        self.mark_changed();
+
+        if *self.is_recording.lock().await {
+            if let Some(recorder) = self.session_recorder.lock().await.as_mut() {
+                recorder.record_thought_dismissed(&thought.id, thought.file_path.as_deref());
+            }
+        }
+
+        Some(thought)
+    }
+
+    /// Drops thoughts older than `ttl` at `now`, keeping the selection on the same
+    /// thought if it survives. Returns how many were dropped.
+    pub async fn expire_thoughts(&self, ttl: chrono::Duration, now: DateTime<Utc>) -> usize {
+        let mut thoughts = self.ai_thoughts.lock().await;
+        let mut selection = self.thought_selection.lock().await;
+
+        let expired: Vec<bool> = thoughts.iter().map(|t| now - t.timestamp > ttl).collect();
+        let count = expired.iter().filter(|e| **e).count();
+        if count == 0 {
+            return 0;
+        }
+
+        selection.selected_index = selection
+            .selected_index
+            .filter(|&i| !expired.get(i).copied().unwrap_or(true))
+            .map(|i| i - expired[..i].iter().filter(|e| **e).count());
+        let mut flags = expired.iter();
+        thoughts.retain(|_| !*flags.next().unwrap_or(&false));
+
+        self.mark_changed();
+        count
+    }
+
+    pub async fn get_selected_thought(&self) -> Option<usize> {
+        self.thought_selection.lock().await.selected_index
+    }
+
+    pub async fn is_dry_run(&self) -> bool {
+        *self.dry_run_edits.lock().await
+    }
+
+    pub async fn get_config(&self) -> Arc<Config> {
+        self.config.lock().await.clone()
+    }
+
+    pub async fn get_code_scroll(&self) -> usize {
+        *self.code_scroll.lock().await
+    }
+
+    pub async fn get_code_display(&self) -> CodeDisplay {
+        *self.code_display.lock().await
+    }
+
+    pub async fn get_thought_grouping(&self) -> ThoughtGrouping {
+        self.thought_grouping.lock().await.clone()
+    }
+
     pub async fn get_mode(&self) -> ViewMode {
         self.mode.lock().await.clone()
     }
@@ -421,4 +2056,61 @@ impl App {
     pub async fn is_running(&self) -> bool {
         *self.running.lock().await
     }
-}
\ No newline at end of file
+
+    pub async fn is_idle(&self) -> bool {
+        *self.idle.lock().await
+    }
+}
+
+/// `paths` ordered newest first by `recent`, with paths that dropped out of it last
+fn by_recency<'a>(paths: impl Iterator<Item = &'a String>, recent: &VecDeque<String>) -> Vec<&'a String> {
+    let mut paths: Vec<&String> = paths.collect();
+    paths.sort_by_key(|path| (recent.iter().position(|recent_path| recent_path == *path).unwrap_or(usize::MAX), *path));
+    paths
+}
+
+/// Drops cached files, least recent first by `recent`, until at most `capacity` remain.
+/// The newest file, at the front of `recent`, always stays.
+fn evict_least_recent(cache: &mut HashMap<String, String>, recent: &VecDeque<String>, capacity: usize) {
+    if cache.len() <= capacity {
+        return;
+    }
+
+    let evicted: Vec<String> = by_recency(cache.keys(), recent)
+        .into_iter()
+        .skip(capacity.max(1))
+        .cloned()
+        .collect();
+    for path in evicted {
+        cache.remove(&path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_by_recency() {
+        let recent: VecDeque<String> = ["c.rs", "a.rs"].iter().map(|path| path.to_string()).collect();
+        let paths: Vec<String> = ["a.rs", "b.rs", "c.rs", "d.rs"].iter().map(|path| path.to_string()).collect();
+
+        assert_eq!(by_recency(paths.iter(), &recent), vec!["c.rs", "a.rs", "b.rs", "d.rs"]);
+    }
+
+    #[test]
+    fn test_evict_least_recent_keeps_newest_files() {
+        let mut cache = HashMap::new();
+        let mut recent = VecDeque::new();
+        for i in 0..10 {
+            let path = format!("src/file{}.rs", i);
+            cache.insert(path.clone(), String::new());
+            recent.push_front(path);
+            evict_least_recent(&mut cache, &recent, 3);
+        }
+
+        let mut kept: Vec<&String> = cache.keys().collect();
+        kept.sort();
+        assert_eq!(kept, vec!["src/file7.rs", "src/file8.rs", "src/file9.rs"]);
+    }
+}
@@ -1,24 +1,142 @@
-use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
+use crate::app::{Thought, ThoughtType};
+use crate::error::{CocoError, Result};
+use crate::session::EventType;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub anthropic_api_key: Option<String>,
     pub openai_api_key: Option<String>,
     pub ai_provider: AiProvider,
+    /// API root of the OpenAI-compatible server used by the `OpenAI` and `Local` providers
+    pub openai_base_url: String,
+    pub openai_model: String,
+    /// Claude model used by the `Anthropic` provider
+    pub anthropic_model: String,
+    /// Models the `m` key cycles through, for whichever provider is configured
+    pub available_models: Vec<String>,
     pub file_patterns: Vec<String>,
     pub ignore_patterns: Vec<String>,
+    /// Largest file the watcher reads at all
     pub max_file_size: u64,
+    /// Per-extension overrides of `max_file_size`, keyed by lowercase extension without the dot
+    pub max_file_size_by_extension: HashMap<String, u64>,
+    /// Largest changed file that gets analyzed; bigger ones are still shown in the code panel
+    pub ai_trigger_max_bytes: usize,
+    /// Extensions sent to the AI provider; empty means all of them
+    pub ai_analysis_extensions: Vec<String>,
+    /// Extensions that only get the local pattern checks, never an AI request
+    pub local_only_extensions: Vec<String>,
     pub analysis_delay_ms: u64,
+    pub watch_debounce_ms: u64,
+    /// Filesystem events that can wait between notify and the watcher's event loop;
+    /// more arriving at once are dropped and counted in the status bar
+    pub notify_channel_capacity: usize,
     pub ui_theme: UiTheme,
     pub session_auto_save: bool,
+    /// Events a recording keeps in memory and on disk before `session_retention` drops some
     pub session_max_events: usize,
+    /// Which events survive when a recording reaches `session_max_events`
+    pub session_retention: RetentionPolicy,
+    /// Store file contents and local findings in recorded sessions, for `replay --verify`
+    pub session_record_content: bool,
+    /// Name recorded as the session's user, instead of the OS user
+    pub user_name: Option<String>,
+    /// Leave the user out of recorded sessions entirely
+    pub anonymize_sessions: bool,
     pub log_level: LogLevel,
     pub watch_directories: Vec<String>,
+    /// Also watch subdirectories of the watch directories
+    pub recursive_watch: bool,
+    /// Directories never descended into, by name (`vendor`) or by path from the
+    /// project root (`crates/api/generated`)
+    pub exclude_dirs: Vec<String>,
+    /// Supported files the watch directories may hold before startup asks for
+    /// confirmation (or `--force`); 0 turns the check off
+    pub max_watched_files: usize,
     pub auto_suggestions: bool,
+    /// Lowest confidence an AI thought needs to be shown, unless its type has its own
+    /// entry in `confidence_thresholds`
     pub suggestion_confidence_threshold: f32,
+    /// Per-type overrides of `suggestion_confidence_threshold`, e.g. 0.0 for Security
+    /// to see every security thought and 0.9 for Style to see only the surest ones
+    pub confidence_thresholds: HashMap<ThoughtType, f32>,
+    pub suppressed_thought_types: Vec<ThoughtType>,
+    /// Line length limit for the long-lines check in every language; `None` uses each
+    /// language's own (120 by default, 140 for Java and Kotlin, 160 for Go)
+    pub max_line_length: Option<usize>,
+    pub analysis_summary: bool,
+    /// Also analyze files opened from the file picker, not only files that change
+    pub analyze_on_focus: bool,
+    /// Never analyze on its own: saves, opened files and `periodic_analysis_secs` only
+    /// update the code panel, and analysis runs when asked for with a key
+    pub manual_mode: bool,
+    /// Attach the improvement suggestions parsed from an analysis to its thoughts and
+    /// show them under each thought; off gives a review-only panel
+    pub include_suggestions: bool,
+    /// Format of the file written when exporting the thoughts on screen
+    pub thought_export_format: ThoughtExportFormat,
+    /// Thoughts shown in the panel; older ones are dropped as new ones arrive
+    pub max_thoughts: usize,
+    /// Recently changed files kept in memory as context for analyses and project reviews
+    pub file_cache_size: usize,
+    pub ai_queue_capacity: usize,
+    /// AI requests analyzed at the same time
+    pub ai_concurrency: usize,
+    /// Estimated spend after which a session stops calling the AI provider; unlimited if unset
+    pub max_session_cost_usd: Option<f64>,
+    /// Consecutive failed AI requests after which the provider is left alone for a while
+    pub ai_failure_threshold: u32,
+    /// How long AI requests pause after `ai_failure_threshold` failures before trying again
+    pub ai_failure_cooldown_secs: u64,
+    pub render_fps: u32,
+    /// Minutes without file changes after which the session is saved and CoCo idles
+    /// until the next change; 0 never idles
+    pub idle_pause_minutes: u64,
+    /// Disk space for thoughts cached across runs in `~/.coco/cache`; 0 turns the cache off
+    pub thought_cache_max_mb: u64,
+    /// Days a cached analysis is reused before it is evicted
+    pub thought_cache_max_age_days: u64,
+    /// Seconds a thought stays in the panel before it is dropped; 0 keeps thoughts until trimmed
+    pub thought_ttl_seconds: u64,
+    /// Seconds between re-analyses of the file on screen, whether or not it was saved;
+    /// `None` analyzes on save only
+    pub periodic_analysis_secs: Option<u64>,
+    /// Accepting a suggestion shows and records the change without writing it; `D` toggles this at runtime
+    pub dry_run_edits: bool,
+}
+
+/// The size limits the file watcher needs, detached from the rest of the config
+#[derive(Debug, Clone)]
+pub struct FileSizeLimits {
+    pub default: u64,
+    pub by_extension: HashMap<String, u64>,
+}
+
+impl FileSizeLimits {
+    pub fn limit_for(&self, path: &Path) -> u64 {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| self.by_extension.get(&normalize_extension(ext)))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+fn normalize_extension(extension: &str) -> String {
+    extension.trim().trim_start_matches('.').to_lowercase()
+}
+
+fn parse_extensions(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(normalize_extension)
+        .filter(|ext| !ext.is_empty())
+        .collect()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,7 +146,16 @@ pub enum AiProvider {
     Local,
 }
 
+/// File format of thoughts exported from the TUI with `x`
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ThoughtExportFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct UiTheme {
     pub primary_color: String,
     pub secondary_color: String,
@@ -38,6 +165,57 @@ pub struct UiTheme {
     pub error_color: String,
     pub warning_color: String,
     pub success_color: String,
+    /// Lowest confidence shown in `confidence_high_color`
+    pub confidence_high: f32,
+    /// Lowest confidence shown in `confidence_medium_color`; anything below gets `confidence_low_color`
+    pub confidence_medium: f32,
+    /// Colors of the confidence scale, as names (`green`, `lightblue`) or `#rrggbb`
+    pub confidence_high_color: String,
+    pub confidence_medium_color: String,
+    pub confidence_low_color: String,
+}
+
+/// What a recording keeps when it reaches `session_max_events`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(clippy::enum_variant_names)]
+pub enum RetentionPolicy {
+    /// The most recent events, at most four fifths of `session_max_events`
+    KeepRecent(usize),
+    /// Every event of these types; all others are dropped
+    KeepByType(Vec<EventType>),
+    /// Everything; the recording grows without limit
+    KeepAll,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy::KeepRecent(8_000)
+    }
+}
+
+impl std::str::FromStr for RetentionPolicy {
+    type Err = String;
+
+    /// Parses `all`, `recent:N` or `types:AiResponse,ThoughtGenerated`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let (kind, argument) = value.split_once(':').unwrap_or((value, ""));
+        match kind.to_lowercase().as_str() {
+            "all" => Ok(RetentionPolicy::KeepAll),
+            "recent" => argument
+                .trim()
+                .parse()
+                .map(RetentionPolicy::KeepRecent)
+                .map_err(|_| format!("invalid event count: {}", argument)),
+            "types" => argument
+                .split(',')
+                .filter(|name| !name.trim().is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map(RetentionPolicy::KeepByType),
+            _ => Err(format!("unknown retention policy: {}", value)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +233,10 @@ impl Default for Config {
             anthropic_api_key: None,
             openai_api_key: None,
             ai_provider: AiProvider::Anthropic,
+            openai_base_url: "https://api.openai.com/v1".to_string(),
+            openai_model: "gpt-4o-mini".to_string(),
+            anthropic_model: crate::ai::claude::DEFAULT_MODEL.to_string(),
+            available_models: Vec::new(),
             file_patterns: vec![
                 "*.rs".to_string(),
                 "*.py".to_string(),
@@ -95,14 +277,49 @@ impl Default for Config {
                 "*.min.css".to_string(),
             ],
             max_file_size: 1024 * 1024, // 1MB
+            ai_trigger_max_bytes: 5_000,
+            max_file_size_by_extension: HashMap::new(),
+            ai_analysis_extensions: Vec::new(),
+            local_only_extensions: Vec::new(),
             analysis_delay_ms: 500,
+            watch_debounce_ms: 300,
+            notify_channel_capacity: 256,
             ui_theme: UiTheme::default(),
             session_auto_save: true,
             session_max_events: 10000,
+            session_retention: RetentionPolicy::default(),
+            session_record_content: false,
+            user_name: None,
+            anonymize_sessions: false,
             log_level: LogLevel::Info,
             watch_directories: vec![".".to_string()],
+            recursive_watch: true,
+            exclude_dirs: Vec::new(),
+            max_watched_files: 5_000,
             auto_suggestions: true,
             suggestion_confidence_threshold: 0.7,
+            confidence_thresholds: HashMap::new(),
+            suppressed_thought_types: Vec::new(),
+            max_line_length: None,
+            analysis_summary: true,
+            analyze_on_focus: false,
+            manual_mode: false,
+            include_suggestions: true,
+            thought_export_format: ThoughtExportFormat::Markdown,
+            max_thoughts: 5,
+            file_cache_size: 3,
+            ai_queue_capacity: 5,
+            ai_concurrency: 2,
+            max_session_cost_usd: None,
+            ai_failure_threshold: 3,
+            ai_failure_cooldown_secs: 30,
+            render_fps: 20,
+            idle_pause_minutes: 10,
+            thought_cache_max_mb: 50,
+            thought_cache_max_age_days: 30,
+            thought_ttl_seconds: 0,
+            periodic_analysis_secs: None,
+            dry_run_edits: false,
         }
     }
 }
@@ -118,6 +335,11 @@ impl Default for UiTheme {
             error_color: "#ef4444".to_string(),     // Red
             warning_color: "#f59e0b".to_string(),   // Amber
             success_color: "#22c55e".to_string(),   // Green
+            confidence_high: 0.8,
+            confidence_medium: 0.6,
+            confidence_high_color: "green".to_string(),
+            confidence_medium_color: "yellow".to_string(),
+            confidence_low_color: "red".to_string(),
         }
     }
 }
@@ -128,8 +350,7 @@ impl Config {
 
         let mut config = if config_path.exists() {
             let content = fs::read_to_string(&config_path).await?;
-            toml::from_str(&content)
-                .map_err(|e| anyhow::anyhow!("Failed to parse config file: {}", e))?
+            toml::from_str(&content)?
         } else {
             // Create default config
             Self::default()
@@ -154,8 +375,7 @@ impl Config {
             fs::create_dir_all(parent).await?;
         }
 
-        let content = toml::to_string_pretty(self)
-            .map_err(|e| anyhow::anyhow!("Failed to serialize config: {}", e))?;
+        let content = toml::to_string_pretty(self)?;
 
         fs::write(&config_path, content).await?;
 
@@ -163,13 +383,38 @@ impl Config {
         Ok(())
     }
 
-    fn config_path() -> Result<PathBuf> {
-        let home = dirs::home_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    /// `.coco/config.toml` in the project root if present, otherwise `~/.coco/config.toml`
+    pub fn config_path() -> Result<PathBuf> {
+        let project_config = crate::util::project_root().join(".coco").join("config.toml");
+        if project_config.exists() {
+            return Ok(project_config);
+        }
+
+        let home = dirs::home_dir().ok_or(CocoError::NoHomeDirectory)?;
 
         Ok(home.join(".coco").join("config.toml"))
     }
 
+    /// Names of the top-level fields whose values differ between `self` and `other`
+    pub fn changed_fields(&self, other: &Config) -> Vec<String> {
+        let (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) =
+            (serde_json::to_value(self), serde_json::to_value(other))
+        else {
+            return Vec::new();
+        };
+
+        let mut changed: Vec<String> = old
+            .keys()
+            .chain(new.keys())
+            .filter(|key| old.get(*key) != new.get(*key))
+            .cloned()
+            .collect();
+
+        changed.sort();
+        changed.dedup();
+        changed
+    }
+
     fn load_from_env(&mut self) {
         // Load API keys from environment
         if let Ok(key) = std::env::var("ANTHROPIC_API_KEY") {
@@ -195,6 +440,34 @@ impl Config {
             }
         }
 
+        // Load OpenAI-compatible endpoint settings
+        if let Ok(url) = std::env::var("COCO_OPENAI_BASE_URL") {
+            if !url.is_empty() {
+                self.openai_base_url = url;
+            }
+        }
+
+        if let Ok(model) = std::env::var("COCO_OPENAI_MODEL") {
+            if !model.is_empty() {
+                self.openai_model = model;
+            }
+        }
+
+        if let Ok(model) = std::env::var("COCO_ANTHROPIC_MODEL") {
+            if !model.is_empty() {
+                self.anthropic_model = model;
+            }
+        }
+
+        // Load the models to switch between (comma-separated, e.g. "claude-3-5-haiku-20241022,claude-sonnet-4-20250514")
+        if let Ok(models) = std::env::var("COCO_AVAILABLE_MODELS") {
+            self.available_models = models
+                .split(',')
+                .map(|model| model.trim().to_string())
+                .filter(|model| !model.is_empty())
+                .collect();
+        }
+
         // Load log level
         if let Ok(level) = std::env::var("COCO_LOG_LEVEL") {
             match level.to_lowercase().as_str() {
@@ -214,6 +487,34 @@ impl Config {
             }
         }
 
+        // Load the size up to which changed files are analyzed
+        if let Ok(size) = std::env::var("COCO_AI_TRIGGER_MAX_BYTES") {
+            if let Ok(size) = size.parse::<usize>() {
+                self.ai_trigger_max_bytes = size;
+            }
+        }
+
+        // Load per-extension max file sizes (comma-separated, e.g. "json=20000,rs=200000")
+        if let Ok(sizes) = std::env::var("COCO_MAX_FILE_SIZE_BY_EXTENSION") {
+            for entry in sizes.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                match entry.split_once('=').map(|(ext, size)| (ext, size.trim().parse::<u64>())) {
+                    Some((ext, Ok(size))) => {
+                        self.max_file_size_by_extension.insert(normalize_extension(ext), size);
+                    }
+                    _ => tracing::warn!("Invalid max file size override: {}", entry),
+                }
+            }
+        }
+
+        // Load AI vs local-only extensions (comma-separated, e.g. "rs,py")
+        if let Ok(extensions) = std::env::var("COCO_AI_ANALYSIS_EXTENSIONS") {
+            self.ai_analysis_extensions = parse_extensions(&extensions);
+        }
+
+        if let Ok(extensions) = std::env::var("COCO_LOCAL_ONLY_EXTENSIONS") {
+            self.local_only_extensions = parse_extensions(&extensions);
+        }
+
         // Load analysis delay
         if let Ok(delay) = std::env::var("COCO_ANALYSIS_DELAY_MS") {
             if let Ok(delay) = delay.parse::<u64>() {
@@ -221,17 +522,261 @@ impl Config {
             }
         }
 
+        // Load watcher debounce delay
+        if let Ok(delay) = std::env::var("COCO_WATCH_DEBOUNCE_MS") {
+            if let Ok(delay) = delay.parse::<u64>() {
+                self.watch_debounce_ms = delay;
+            }
+        }
+
+        // Load notify channel capacity
+        if let Ok(capacity) = std::env::var("COCO_NOTIFY_CHANNEL_CAPACITY") {
+            if let Ok(capacity) = capacity.parse::<usize>() {
+                self.notify_channel_capacity = capacity;
+            }
+        }
+
+        // Load recursive watching setting
+        if let Ok(recursive) = std::env::var("COCO_RECURSIVE_WATCH") {
+            self.recursive_watch = recursive.to_lowercase() == "true";
+        }
+
+        // Load excluded directories (comma-separated, e.g. "vendor,src/generated")
+        if let Ok(dirs) = std::env::var("COCO_EXCLUDE_DIRS") {
+            self.exclude_dirs = dirs
+                .split(',')
+                .map(|dir| dir.trim().trim_matches('/').to_string())
+                .filter(|dir| !dir.is_empty())
+                .collect();
+        }
+
+        // Load the startup file-count limit
+        if let Ok(max) = std::env::var("COCO_MAX_WATCHED_FILES") {
+            if let Ok(max) = max.parse::<usize>() {
+                self.max_watched_files = max;
+            }
+        }
+
         // Load auto suggestions setting
         if let Ok(auto) = std::env::var("COCO_AUTO_SUGGESTIONS") {
             self.auto_suggestions = auto.to_lowercase() == "true";
         }
 
+        // Load analysis summary setting
+        if let Ok(summary) = std::env::var("COCO_ANALYSIS_SUMMARY") {
+            self.analysis_summary = summary.to_lowercase() == "true";
+        }
+
+        // Load session content recording setting
+        if let Ok(record) = std::env::var("COCO_SESSION_RECORD_CONTENT") {
+            self.session_record_content = record.to_lowercase() == "true";
+        }
+
+        // Load the confidence color scale cutoffs (high,medium, e.g. "0.9,0.5")
+        if let Ok(cutoffs) = std::env::var("COCO_CONFIDENCE_CUTOFFS") {
+            let parsed: Vec<f32> = cutoffs.split(',').filter_map(|c| c.trim().parse().ok()).collect();
+            match parsed[..] {
+                [high, medium] => {
+                    self.ui_theme.confidence_high = high;
+                    self.ui_theme.confidence_medium = medium;
+                }
+                _ => tracing::warn!("Invalid confidence cutoffs: {}", cutoffs),
+            }
+        }
+
+        // Load the confidence color scale (high,medium,low, e.g. "blue,lightcyan,#e69f00")
+        if let Ok(colors) = std::env::var("COCO_CONFIDENCE_COLORS") {
+            let parsed: Vec<String> = colors.split(',').map(|c| c.trim().to_string()).collect();
+            match <[String; 3]>::try_from(parsed) {
+                Ok([high, medium, low]) => {
+                    self.ui_theme.confidence_high_color = high;
+                    self.ui_theme.confidence_medium_color = medium;
+                    self.ui_theme.confidence_low_color = low;
+                }
+                Err(_) => tracing::warn!("Invalid confidence colors: {}", colors),
+            }
+        }
+
+        // Load dry-run mode for suggestion edits
+        if let Ok(dry_run) = std::env::var("COCO_DRY_RUN_EDITS") {
+            self.dry_run_edits = dry_run.to_lowercase() == "true";
+        }
+
+        // Load session user settings
+        if let Ok(user) = std::env::var("COCO_USER") {
+            if !user.is_empty() {
+                self.user_name = Some(user);
+            }
+        }
+
+        if let Ok(anonymize) = std::env::var("COCO_ANONYMIZE_SESSIONS") {
+            self.anonymize_sessions = anonymize.to_lowercase() == "true";
+        }
+
+        // Load analyze-on-open setting
+        if let Ok(on_focus) = std::env::var("COCO_ANALYZE_ON_FOCUS") {
+            self.analyze_on_focus = on_focus.to_lowercase() == "true";
+        }
+
+        // Load manual mode
+        if let Ok(manual) = std::env::var("COCO_MANUAL_MODE") {
+            self.manual_mode = manual.to_lowercase() == "true";
+        }
+
+        if let Ok(include) = std::env::var("COCO_INCLUDE_SUGGESTIONS") {
+            self.include_suggestions = include.to_lowercase() == "true";
+        }
+
+        if let Ok(format) = std::env::var("COCO_THOUGHT_EXPORT_FORMAT") {
+            match format.to_lowercase().as_str() {
+                "markdown" | "md" => self.thought_export_format = ThoughtExportFormat::Markdown,
+                "json" => self.thought_export_format = ThoughtExportFormat::Json,
+                _ => tracing::warn!("Unknown thought export format: {}", format),
+            }
+        }
+
+        // Load memory limits
+        if let Ok(max) = std::env::var("COCO_MAX_THOUGHTS") {
+            if let Ok(max) = max.parse::<usize>() {
+                self.max_thoughts = max;
+            }
+        }
+
+        if let Ok(size) = std::env::var("COCO_FILE_CACHE_SIZE") {
+            if let Ok(size) = size.parse::<usize>() {
+                self.file_cache_size = size;
+            }
+        }
+
+        if let Ok(max) = std::env::var("COCO_SESSION_MAX_EVENTS") {
+            if let Ok(max) = max.parse::<usize>() {
+                self.session_max_events = max;
+            }
+        }
+
+        if let Ok(retention) = std::env::var("COCO_SESSION_RETENTION") {
+            match retention.parse::<RetentionPolicy>() {
+                Ok(policy) => self.session_retention = policy,
+                Err(e) => tracing::warn!("Ignoring COCO_SESSION_RETENTION: {}", e),
+            }
+        }
+
+        // Load AI queue capacity
+        if let Ok(capacity) = std::env::var("COCO_AI_QUEUE_CAPACITY") {
+            if let Ok(capacity) = capacity.parse::<usize>() {
+                self.ai_queue_capacity = capacity;
+            }
+        }
+
+        // Load AI concurrency
+        if let Ok(concurrency) = std::env::var("COCO_AI_CONCURRENCY") {
+            if let Ok(concurrency) = concurrency.parse::<usize>() {
+                self.ai_concurrency = concurrency;
+            }
+        }
+
+        // Load session cost limit
+        if let Ok(limit) = std::env::var("COCO_MAX_SESSION_COST_USD") {
+            if let Ok(limit) = limit.parse::<f64>() {
+                self.max_session_cost_usd = Some(limit);
+            }
+        }
+
+        // Load AI failure cooldown
+        if let Ok(threshold) = std::env::var("COCO_AI_FAILURE_THRESHOLD") {
+            if let Ok(threshold) = threshold.parse::<u32>() {
+                self.ai_failure_threshold = threshold;
+            }
+        }
+        if let Ok(cooldown) = std::env::var("COCO_AI_FAILURE_COOLDOWN_SECS") {
+            if let Ok(cooldown) = cooldown.parse::<u64>() {
+                self.ai_failure_cooldown_secs = cooldown;
+            }
+        }
+
+        // Load idle timeout
+        if let Ok(minutes) = std::env::var("COCO_IDLE_PAUSE_MINUTES") {
+            if let Ok(minutes) = minutes.parse::<u64>() {
+                self.idle_pause_minutes = minutes;
+            }
+        }
+
+        // Load cross-session thought cache limits
+        if let Ok(mb) = std::env::var("COCO_THOUGHT_CACHE_MAX_MB") {
+            if let Ok(mb) = mb.parse::<u64>() {
+                self.thought_cache_max_mb = mb;
+            }
+        }
+        if let Ok(days) = std::env::var("COCO_THOUGHT_CACHE_MAX_AGE_DAYS") {
+            if let Ok(days) = days.parse::<u64>() {
+                self.thought_cache_max_age_days = days;
+            }
+        }
+
+        // Load thought expiry
+        if let Ok(seconds) = std::env::var("COCO_THOUGHT_TTL_SECONDS") {
+            if let Ok(seconds) = seconds.parse::<u64>() {
+                self.thought_ttl_seconds = seconds;
+            }
+        }
+
+        // Load the periodic re-analysis interval (0 turns it off)
+        if let Ok(seconds) = std::env::var("COCO_PERIODIC_ANALYSIS_SECS") {
+            if let Ok(seconds) = seconds.parse::<u64>() {
+                self.periodic_analysis_secs = Some(seconds).filter(|&seconds| seconds > 0);
+            }
+        }
+
+        // Load render frame rate
+        if let Ok(fps) = std::env::var("COCO_RENDER_FPS") {
+            if let Ok(fps) = fps.parse::<u32>() {
+                self.render_fps = fps;
+            }
+        }
+
         // Load confidence threshold
         if let Ok(threshold) = std::env::var("COCO_CONFIDENCE_THRESHOLD") {
             if let Ok(threshold) = threshold.parse::<f32>() {
                 self.suggestion_confidence_threshold = threshold;
             }
         }
+
+        // Load line length override (0 goes back to per-language limits)
+        if let Ok(length) = std::env::var("COCO_MAX_LINE_LENGTH") {
+            if let Ok(length) = length.parse::<usize>() {
+                self.max_line_length = Some(length).filter(|&length| length > 0);
+            }
+        }
+
+        // Load per-type confidence thresholds (comma-separated, e.g. "security=0,style=0.9")
+        if let Ok(thresholds) = std::env::var("COCO_CONFIDENCE_THRESHOLDS") {
+            for entry in thresholds.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                let parsed = entry
+                    .split_once('=')
+                    .and_then(|(name, threshold)| Some((ThoughtType::from_name(name)?, threshold.trim().parse::<f32>().ok()?)));
+                match parsed {
+                    Some((thought_type, threshold)) => {
+                        self.confidence_thresholds.insert(thought_type, threshold);
+                    }
+                    None => tracing::warn!("Invalid confidence threshold override: {}", entry),
+                }
+            }
+        }
+
+        // Load suppressed thought types (comma-separated, e.g. "style,meta")
+        if let Ok(types) = std::env::var("COCO_SUPPRESSED_THOUGHT_TYPES") {
+            self.suppressed_thought_types = types
+                .split(',')
+                .filter(|name| !name.trim().is_empty())
+                .filter_map(|name| {
+                    let thought_type = ThoughtType::from_name(name);
+                    if thought_type.is_none() {
+                        tracing::warn!("Unknown thought type in COCO_SUPPRESSED_THOUGHT_TYPES: {}", name);
+                    }
+                    thought_type
+                })
+                .collect();
+        }
     }
 
     pub fn is_file_supported(&self, path: &std::path::Path) -> bool {
@@ -275,6 +820,108 @@ impl Config {
         }
     }
 
+    /// Size limit for a file, using its extension's override if there is one
+    pub fn max_file_size_for(&self, path: &Path) -> u64 {
+        self.file_size_limits().limit_for(path)
+    }
+
+    pub fn file_size_limits(&self) -> FileSizeLimits {
+        FileSizeLimits {
+            default: self.max_file_size,
+            by_extension: self
+                .max_file_size_by_extension
+                .iter()
+                .map(|(ext, size)| (normalize_extension(ext), *size))
+                .collect(),
+        }
+    }
+
+    /// Whether changes to `path` go to the AI provider rather than only the local checks
+    pub fn uses_ai_for(&self, path: &Path) -> bool {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(normalize_extension)
+            .unwrap_or_default();
+        let listed = |extensions: &[String]| {
+            extensions.iter().any(|ext| normalize_extension(ext) == extension)
+        };
+
+        if listed(&self.local_only_extensions) {
+            return false;
+        }
+        self.ai_analysis_extensions.is_empty() || listed(&self.ai_analysis_extensions)
+    }
+
+    /// Lowercase name of `ai_provider`, e.g. `"anthropic"`
+    pub fn ai_provider_name(&self) -> &'static str {
+        match self.ai_provider {
+            AiProvider::Anthropic => "anthropic",
+            AiProvider::OpenAI => "openai",
+            AiProvider::Local => "local",
+        }
+    }
+
+    /// Model the configured provider sends requests to
+    pub fn ai_model(&self) -> String {
+        match self.ai_provider {
+            AiProvider::Anthropic => self.anthropic_model.clone(),
+            AiProvider::OpenAI | AiProvider::Local => self.openai_model.clone(),
+        }
+    }
+
+    /// The model after the current one in `available_models`, wrapping around, or the
+    /// first listed one if the current model isn't listed. `None` when there is nothing to switch to.
+    pub fn next_model(&self) -> Option<String> {
+        let current = self.ai_model();
+        let next = match self.available_models.iter().position(|model| *model == current) {
+            Some(i) => &self.available_models[(i + 1) % self.available_models.len()],
+            None => self.available_models.first()?,
+        };
+        (*next != current).then(|| next.clone())
+    }
+
+    /// Points the configured provider at `model`
+    pub fn set_ai_model(&mut self, model: String) {
+        match self.ai_provider {
+            AiProvider::Anthropic => self.anthropic_model = model,
+            AiProvider::OpenAI | AiProvider::Local => self.openai_model = model,
+        }
+    }
+
+    /// The user to record in session metadata: `user_name` if set, otherwise the
+    /// OS user, and nobody when sessions are anonymized
+    pub fn session_user(&self) -> Option<String> {
+        if self.anonymize_sessions {
+            return None;
+        }
+
+        self.user_name.clone().or_else(|| {
+            std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .ok()
+        })
+    }
+
+    pub fn is_thought_suppressed(&self, thought_type: &ThoughtType) -> bool {
+        self.suppressed_thought_types.contains(thought_type)
+    }
+
+    /// The confidence threshold for `thought_type`, falling back to the global one
+    pub fn confidence_threshold_for(&self, thought_type: &ThoughtType) -> f32 {
+        self.confidence_thresholds
+            .get(thought_type)
+            .copied()
+            .unwrap_or(self.suggestion_confidence_threshold)
+    }
+
+    /// Whether an AI thought is shown: its type isn't suppressed and its confidence
+    /// reaches the threshold for its type
+    pub fn is_thought_shown(&self, thought: &Thought) -> bool {
+        !self.is_thought_suppressed(&thought.thought_type)
+            && thought.confidence >= self.confidence_threshold_for(&thought.thought_type)
+    }
+
     pub fn should_watch_directory(&self, path: &std::path::Path) -> bool {
         let path_str = path.to_string_lossy();
 
@@ -292,16 +939,18 @@ impl Config {
         match self.ai_provider {
             AiProvider::Anthropic => {
                 if self.anthropic_api_key.is_none() {
-                    return Err(anyhow::anyhow!(
-                        "Anthropic API key is required. Set ANTHROPIC_API_KEY environment variable."
-                    ));
+                    return Err(CocoError::MissingApiKey {
+                        provider: "Anthropic",
+                        env_var: "ANTHROPIC_API_KEY",
+                    });
                 }
             }
             AiProvider::OpenAI => {
                 if self.openai_api_key.is_none() {
-                    return Err(anyhow::anyhow!(
-                        "OpenAI API key is required. Set OPENAI_API_KEY environment variable."
-                    ));
+                    return Err(CocoError::MissingApiKey {
+                        provider: "OpenAI",
+                        env_var: "OPENAI_API_KEY",
+                    });
                 }
             }
             AiProvider::Local => {
@@ -309,17 +958,142 @@ impl Config {
             }
         }
 
+        if matches!(self.ai_provider, AiProvider::OpenAI | AiProvider::Local) {
+            if !self.openai_base_url.starts_with("http://") && !self.openai_base_url.starts_with("https://") {
+                return Err(CocoError::InvalidConfig(format!(
+                    "OpenAI base URL must start with http:// or https:// (got '{}'). Set COCO_OPENAI_BASE_URL.",
+                    self.openai_base_url
+                )));
+            }
+
+            if self.openai_model.trim().is_empty() {
+                return Err(CocoError::InvalidConfig("OpenAI model is required. Set COCO_OPENAI_MODEL.".to_string()));
+            }
+        }
+
+        if matches!(self.ai_provider, AiProvider::Anthropic) && self.anthropic_model.trim().is_empty() {
+            return Err(CocoError::InvalidConfig("Anthropic model is required. Set COCO_ANTHROPIC_MODEL.".to_string()));
+        }
+
         // Validate file size limits
         if self.max_file_size == 0 {
-            return Err(anyhow::anyhow!("Max file size must be greater than 0"));
+            return Err(CocoError::InvalidConfig("Max file size must be greater than 0".to_string()));
+        }
+
+        if self.ai_trigger_max_bytes == 0 {
+            return Err(CocoError::InvalidConfig("AI trigger max bytes must be greater than 0".to_string()));
+        }
+
+        for (extension, size) in &self.max_file_size_by_extension {
+            if *size == 0 {
+                return Err(CocoError::InvalidConfig(format!(
+                    "Max file size for .{} files must be greater than 0", extension
+                )));
+            }
+        }
+
+        // Validate watcher debounce delay
+        if !(10..=10_000).contains(&self.watch_debounce_ms) {
+            return Err(CocoError::InvalidConfig("Watch debounce delay must be between 10 and 10000 ms".to_string()));
+        }
+
+        if !(1..=100_000).contains(&self.notify_channel_capacity) {
+            return Err(CocoError::InvalidConfig("Notify channel capacity must be between 1 and 100000".to_string()));
+        }
+
+        // Validate AI queue capacity
+        if !(1..=100).contains(&self.ai_queue_capacity) {
+            return Err(CocoError::InvalidConfig("AI queue capacity must be between 1 and 100".to_string()));
+        }
+
+        // Validate memory limits
+        if !(1..=100).contains(&self.max_thoughts) {
+            return Err(CocoError::InvalidConfig("Max thoughts must be between 1 and 100".to_string()));
+        }
+
+        if !(1..=50).contains(&self.file_cache_size) {
+            return Err(CocoError::InvalidConfig("File cache size must be between 1 and 50".to_string()));
+        }
+
+        if self.session_max_events < 100 {
+            return Err(CocoError::InvalidConfig("Session max events must be at least 100".to_string()));
+        }
+
+        match &self.session_retention {
+            RetentionPolicy::KeepRecent(0) => {
+                return Err(CocoError::InvalidConfig("Session retention must keep at least one recent event".to_string()));
+            }
+            RetentionPolicy::KeepByType(types) if types.is_empty() => {
+                return Err(CocoError::InvalidConfig("Session retention must list at least one event type to keep".to_string()));
+            }
+            _ => {}
+        }
+
+        // Validate AI concurrency
+        if !(1..=8).contains(&self.ai_concurrency) {
+            return Err(CocoError::InvalidConfig("AI concurrency must be between 1 and 8".to_string()));
+        }
+
+        // Validate session cost limit
+        if self.max_session_cost_usd.is_some_and(|limit| limit <= 0.0) {
+            return Err(CocoError::InvalidConfig("Max session cost must be greater than 0".to_string()));
+        }
+
+        // Validate AI failure cooldown
+        if !(1..=20).contains(&self.ai_failure_threshold) {
+            return Err(CocoError::InvalidConfig("AI failure threshold must be between 1 and 20".to_string()));
+        }
+        if !(1..=3600).contains(&self.ai_failure_cooldown_secs) {
+            return Err(CocoError::InvalidConfig("AI failure cooldown must be between 1 and 3600 seconds".to_string()));
+        }
+
+        if self.idle_pause_minutes > 1440 {
+            return Err(CocoError::InvalidConfig("Idle pause must be at most 1440 minutes (0 turns it off)".to_string()));
+        }
+
+        if let Some(seconds) = self.periodic_analysis_secs {
+            if !(5..=86400).contains(&seconds) {
+                return Err(CocoError::InvalidConfig("Periodic analysis interval must be between 5 and 86400 seconds".to_string()));
+            }
+        }
+
+        if self.thought_cache_max_mb > 1024 {
+            return Err(CocoError::InvalidConfig("Thought cache size must be at most 1024 MB (0 turns it off)".to_string()));
+        }
+
+        if !(1..=365).contains(&self.thought_cache_max_age_days) {
+            return Err(CocoError::InvalidConfig("Thought cache age must be between 1 and 365 days".to_string()));
         }
 
         // Validate confidence threshold
         if !(0.0..=1.0).contains(&self.suggestion_confidence_threshold) {
-            return Err(anyhow::anyhow!(
-                "Confidence threshold must be between 0.0 and 1.0"
+            return Err(CocoError::InvalidConfig("Confidence threshold must be between 0.0 and 1.0".to_string()));
+        }
+        for (thought_type, threshold) in &self.confidence_thresholds {
+            if !(0.0..=1.0).contains(threshold) {
+                return Err(CocoError::InvalidConfig(format!(
+                    "Confidence threshold for {:?} thoughts must be between 0.0 and 1.0", thought_type
+                )));
+            }
+        }
+
+        // Validate the confidence color scale
+        let theme = &self.ui_theme;
+        if !(0.0..=1.0).contains(&theme.confidence_medium)
+            || !(0.0..=1.0).contains(&theme.confidence_high)
+            || theme.confidence_medium > theme.confidence_high
+        {
+            return Err(CocoError::InvalidConfig(
+                "Confidence cutoffs must be between 0.0 and 1.0, the medium one no higher than the high one".to_string(),
             ));
         }
+        for color in [&theme.confidence_high_color, &theme.confidence_medium_color, &theme.confidence_low_color] {
+            if color.parse::<ratatui::style::Color>().is_err() {
+                return Err(CocoError::InvalidConfig(format!(
+                    "Unknown confidence color '{}'; use a color name or #rrggbb", color
+                )));
+            }
+        }
 
         // Validate watch directories exist
         for dir in &self.watch_directories {
@@ -341,4 +1115,97 @@ impl Config {
             LogLevel::Trace => tracing::Level::TRACE,
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_fields() {
+        let old = Config::default();
+        assert!(old.changed_fields(&old.clone()).is_empty());
+
+        let mut new = old.clone();
+        new.analysis_delay_ms = 1000;
+        new.suggestion_confidence_threshold = 0.9;
+
+        assert_eq!(
+            old.changed_fields(&new),
+            vec!["analysis_delay_ms".to_string(), "suggestion_confidence_threshold".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_confidence_threshold_for() {
+        let config = Config {
+            suggestion_confidence_threshold: 0.7,
+            confidence_thresholds: HashMap::from([(ThoughtType::Security, 0.0), (ThoughtType::Style, 0.9)]),
+            ..Config::default()
+        };
+
+        assert_eq!(config.confidence_threshold_for(&ThoughtType::Security), 0.0);
+        assert_eq!(config.confidence_threshold_for(&ThoughtType::Style), 0.9);
+        assert_eq!(config.confidence_threshold_for(&ThoughtType::Warning), 0.7);
+
+        // Survives the round trip through the config file
+        let saved: Config = toml::from_str(&toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert_eq!(saved.confidence_thresholds, config.confidence_thresholds);
+    }
+
+    #[test]
+    fn test_max_file_size_for_extension() {
+        let config = Config {
+            max_file_size: 100_000,
+            max_file_size_by_extension: HashMap::from([(".JSON".to_string(), 20_000)]),
+            ..Config::default()
+        };
+
+        assert_eq!(config.max_file_size_for(Path::new("data/fixtures.json")), 20_000);
+        assert_eq!(config.max_file_size_for(Path::new("src/main.rs")), 100_000);
+        assert_eq!(config.max_file_size_for(Path::new("Makefile")), 100_000);
+    }
+
+    #[test]
+    fn test_uses_ai_for() {
+        let mut config = Config::default();
+        assert!(config.uses_ai_for(Path::new("src/main.rs")));
+
+        config.local_only_extensions = vec!["RS".to_string()];
+        assert!(!config.uses_ai_for(Path::new("src/main.rs")));
+        assert!(config.uses_ai_for(Path::new("app.py")));
+
+        config.ai_analysis_extensions = vec![".py".to_string()];
+        assert!(config.uses_ai_for(Path::new("app.py")));
+        assert!(!config.uses_ai_for(Path::new("index.js")));
+    }
+
+    #[test]
+    fn test_next_model() {
+        let mut config = Config::default();
+        assert_eq!(config.next_model(), None);
+
+        config.available_models = vec!["model-a".to_string(), "model-b".to_string()];
+        assert_eq!(config.next_model().as_deref(), Some("model-a"));
+
+        config.set_ai_model("model-a".to_string());
+        assert_eq!(config.next_model().as_deref(), Some("model-b"));
+        config.set_ai_model("model-b".to_string());
+        assert_eq!(config.next_model().as_deref(), Some("model-a"));
+        assert_eq!(config.openai_model, "gpt-4o-mini");
+
+        config.available_models = vec!["model-b".to_string()];
+        assert_eq!(config.next_model(), None);
+    }
+
+    #[test]
+    fn test_session_user() {
+        let mut config = Config {
+            user_name: Some("reviewer".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(config.session_user().as_deref(), Some("reviewer"));
+
+        config.anonymize_sessions = true;
+        assert_eq!(config.session_user(), None);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::error::{CocoError, Result};
+use similar::TextDiff;
+
+use crate::app::{ActionType, Suggestion, Thought};
+
+/// A suggestion turned into a concrete change to a file, waiting for confirmation
+#[derive(Debug, Clone)]
+pub struct PendingEdit {
+    pub file_path: String,
+    pub suggestion_id: String,
+    pub title: String,
+    pub original: String,
+    pub updated: String,
+    pub diff: String,
+}
+
+/// The last change written to disk, kept so it can be reverted
+#[derive(Debug, Clone)]
+pub struct AppliedEdit {
+    pub file_path: String,
+    pub previous: String,
+    pub applied: String,
+}
+
+/// Whether a suggestion carries enough information to be applied to a file
+pub fn is_applicable(thought: &Thought, suggestion: &Suggestion) -> bool {
+    thought.file_path.is_some()
+        && target_range(thought, suggestion).is_some()
+        && (suggestion.code_snippet.is_some() || matches!(suggestion.action_type, ActionType::Delete))
+}
+
+/// The inclusive, 1-based lines a suggestion applies to: its own `target_range`,
+/// falling back to its thought's line
+pub fn target_range(thought: &Thought, suggestion: &Suggestion) -> Option<(usize, usize)> {
+    suggestion
+        .target_range
+        .or_else(|| thought.line_number.map(|line| (line, line)))
+}
+
+/// Applies a suggestion to its target lines: `Delete` removes them, `Insert` puts
+/// the snippet above the first one, and every other action replaces them with the snippet
+pub fn prepare_edit(thought: &Thought, suggestion: &Suggestion, original: &str) -> Result<PendingEdit> {
+    let file_path = thought
+        .file_path
+        .clone()
+        .ok_or_else(|| CocoError::InvalidEdit("Thought is not tied to a file".to_string()))?;
+    let (start, end) = target_range(thought, suggestion)
+        .ok_or_else(|| CocoError::InvalidEdit("Suggestion has no lines to apply to".to_string()))?;
+
+    let mut lines: Vec<&str> = original.lines().collect();
+    if start == 0 || end < start || end > lines.len() {
+        return Err(CocoError::InvalidEdit(format!("Lines {}-{} are outside {} ({} lines)", start, end, file_path, lines.len())));
+    }
+    let range = (start - 1)..end;
+
+    let snippet = || {
+        suggestion
+            .code_snippet
+            .as_deref()
+            .map(|code| code.lines().collect::<Vec<_>>())
+            .ok_or_else(|| CocoError::InvalidEdit("Suggestion has no code to apply".to_string()))
+    };
+
+    match suggestion.action_type {
+        ActionType::Delete => {
+            lines.drain(range);
+        }
+        ActionType::Insert => {
+            lines.splice(range.start..range.start, snippet()?);
+        }
+        _ => {
+            lines.splice(range, snippet()?);
+        }
+    }
+
+    let newline = if original.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut updated = lines.join(newline);
+    if original.ends_with('\n') {
+        updated.push_str(newline);
+    }
+
+    if updated == original {
+        return Err(CocoError::InvalidEdit(format!("Suggestion would not change {}", file_path)));
+    }
+
+    Ok(PendingEdit {
+        diff: unified_diff(&file_path, original, &updated),
+        file_path,
+        suggestion_id: suggestion.id.clone(),
+        title: suggestion.title.clone(),
+        original: original.to_string(),
+        updated,
+    })
+}
+
+pub fn unified_diff(file_path: &str, old: &str, new: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{}", file_path), &format!("b/{}", file_path))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{Priority, ThoughtType};
+
+    fn thought(line_number: usize) -> Thought {
+        Thought::new(
+            ThoughtType::Suggesting,
+            String::new(),
+            Some("src/lib.rs".to_string()),
+            Some(line_number),
+            0.9,
+        )
+    }
+
+    fn suggestion(action_type: ActionType, code: Option<&str>) -> Suggestion {
+        Suggestion {
+            id: "s".to_string(),
+            title: "Fix it".to_string(),
+            description: String::new(),
+            code_snippet: code.map(str::to_string),
+            action_type,
+            priority: Priority::Medium,
+            target_range: None,
+        }
+    }
+
+    #[test]
+    fn test_prepare_edit_actions() {
+        let original = "a\nb\nc\n";
+
+        let replaced = prepare_edit(&thought(2), &suggestion(ActionType::Fix, Some("B1\nB2")), original).unwrap();
+        assert_eq!(replaced.updated, "a\nB1\nB2\nc\n");
+        assert!(replaced.diff.contains("-b\n"));
+        assert!(replaced.diff.contains("+B2\n"));
+
+        let inserted = prepare_edit(&thought(1), &suggestion(ActionType::Insert, Some("x")), original).unwrap();
+        assert_eq!(inserted.updated, "x\na\nb\nc\n");
+
+        let deleted = prepare_edit(&thought(3), &suggestion(ActionType::Delete, None), original).unwrap();
+        assert_eq!(deleted.updated, "a\nb\n");
+
+        assert!(prepare_edit(&thought(9), &suggestion(ActionType::Delete, None), original).is_err());
+        assert!(prepare_edit(&thought(2), &suggestion(ActionType::Replace, Some("b")), original).is_err());
+    }
+
+    #[test]
+    fn test_prepare_edit_target_range() {
+        let original = "a\nb\nc\nd\n";
+
+        let mut replace = suggestion(ActionType::Replace, Some("BC"));
+        replace.target_range = Some((2, 3));
+        assert_eq!(prepare_edit(&thought(1), &replace, original).unwrap().updated, "a\nBC\nd\n");
+
+        let mut delete = suggestion(ActionType::Delete, None);
+        delete.target_range = Some((1, 2));
+        assert_eq!(prepare_edit(&thought(4), &delete, original).unwrap().updated, "c\nd\n");
+
+        delete.target_range = Some((3, 9));
+        assert!(prepare_edit(&thought(1), &delete, original).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use crate::ai::retry::{ApiStatusError, FailureKind, RetriesExhausted};
+
+/// The failures callers of the library may want to tell apart. The binary reports
+/// them through `anyhow`, which every variant converts into.
+#[derive(Debug, thiserror::Error)]
+pub enum CocoError {
+    #[error("{provider} API key is required. Set {env_var} environment variable.")]
+    MissingApiKey {
+        provider: &'static str,
+        env_var: &'static str,
+    },
+
+    /// A setting is out of range or inconsistent with another
+    #[error("{0}")]
+    InvalidConfig(String),
+
+    #[error("Failed to parse config file: {0}")]
+    ConfigParse(#[from] toml::de::Error),
+
+    #[error("Failed to serialize config: {0}")]
+    ConfigSerialize(#[from] toml::ser::Error),
+
+    #[error("Could not find home directory")]
+    NoHomeDirectory,
+
+    /// One attempt at an API call was answered with an error status
+    #[error(transparent)]
+    Api(#[from] ApiStatusError),
+
+    /// Every attempt at an API call failed; `kind` says why the last one did
+    #[error(transparent)]
+    RetriesExhausted(#[from] RetriesExhausted),
+
+    #[error("Unexpected AI response: {0}")]
+    InvalidResponse(String),
+
+    #[error("File too large: {size} bytes (limit {limit})")]
+    FileTooLarge { size: u64, limit: u64 },
+
+    #[error("File appears to contain binary data")]
+    BinaryFile,
+
+    #[error("Failed to create file watcher: {0}")]
+    WatcherSetup(#[source] notify::Error),
+
+    #[error("File watcher failed on {}: {source}", path.display())]
+    Watch {
+        path: PathBuf,
+        #[source]
+        source: notify::Error,
+    },
+
+    /// An AI suggestion can't be turned into an edit of its file
+    #[error("{0}")]
+    InvalidEdit(String),
+
+    /// A user action has nothing to act on right now, e.g. undo with nothing applied
+    #[error("{0}")]
+    InvalidAction(String),
+
+    #[error("Cannot load session {id}: {source}")]
+    SessionLoad {
+        id: String,
+        #[source]
+        source: Box<CocoError>,
+    },
+
+    /// A session file is malformed, or lacks the event asked for
+    #[error("{0}")]
+    InvalidSession(String),
+
+    /// A task the app relies on has stopped, e.g. during shutdown
+    #[error("{0} has stopped")]
+    Stopped(&'static str),
+
+    #[error("Background task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl CocoError {
+    /// Whether the provider turned the request away for sending too many
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            CocoError::Api(error) => error.status.as_u16() == 429,
+            CocoError::RetriesExhausted(exhausted) => exhausted.kind == FailureKind::RateLimited,
+            _ => false,
+        }
+    }
+}
+
+pub type Result<T, E = CocoError> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_classes() {
+        let rate_limited = CocoError::Api(ApiStatusError {
+            status: reqwest::StatusCode::TOO_MANY_REQUESTS,
+            body: String::new(),
+            retry_after: None,
+        });
+        assert!(rate_limited.is_rate_limited());
+        assert!(!CocoError::BinaryFile.is_rate_limited());
+
+        let missing = CocoError::MissingApiKey { provider: "Anthropic", env_var: "ANTHROPIC_API_KEY" };
+        assert_eq!(missing.to_string(), "Anthropic API key is required. Set ANTHROPIC_API_KEY environment variable.");
+
+        let session = CocoError::SessionLoad { id: "abc".to_string(), source: Box::new(CocoError::InvalidSession("Unbalanced JSON at byte 7".to_string())) };
+        assert_eq!(session.to_string(), "Cannot load session abc: Unbalanced JSON at byte 7");
+
+        // The binary keeps using anyhow, and can still get the typed error back
+        let error: anyhow::Error = CocoError::FileTooLarge { size: 10, limit: 5 }.into();
+        assert!(matches!(error.downcast_ref::<CocoError>(), Some(CocoError::FileTooLarge { size: 10, limit: 5 })));
+    }
+}
@@ -0,0 +1,28 @@
+//! CoCo as a library, for editor plugins and other tools that want its analysis,
+//! AI providers or recorded sessions without the terminal UI. The `coco` binary is
+//! a thin command line on top of this crate.
+
+pub mod ai;
+pub mod app;
+pub mod config;
+pub mod edits;
+pub mod error;
+pub mod report;
+pub mod session;
+pub mod watcher;
+
+// Used by the binary; not part of the supported API
+#[doc(hidden)]
+pub mod logging;
+#[doc(hidden)]
+pub mod ui;
+#[doc(hidden)]
+pub mod util;
+
+pub use ai::analyzer::{AnalysisReport, CodeAnalyzer, CodeMetrics};
+pub use ai::claude::ClaudeProvider;
+pub use ai::{provider_from_config, AiProvider};
+pub use app::{AiRequest, AiRequestType, App, Thought, ThoughtType};
+pub use config::Config;
+pub use error::CocoError;
+pub use session::{export_session, list_sessions, load_session, search, ExportFormat, Session, SessionRecorder};
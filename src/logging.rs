@@ -0,0 +1,38 @@
+use crate::error::{CocoError, Result};
+use std::path::PathBuf;
+use tracing_appender::non_blocking::WorkerGuard;
+
+pub fn get_logs_directory() -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .ok_or(CocoError::NoHomeDirectory)?;
+
+    let logs_dir = home.join(".coco").join("logs");
+    std::fs::create_dir_all(&logs_dir)?;
+
+    Ok(logs_dir)
+}
+
+/// Logs to `~/.coco/logs/coco.log.YYYY-MM-DD`, rotated daily. Used while the TUI
+/// owns the terminal, where anything written to stderr would corrupt the display.
+///
+/// The returned guard flushes buffered lines on drop and must be held until exit.
+pub fn init_file_logging(level: tracing::Level) -> Result<WorkerGuard> {
+    let appender = tracing_appender::rolling::daily(get_logs_directory()?, "coco.log");
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    tracing_subscriber::fmt()
+        .with_writer(writer)
+        .with_ansi(false)
+        .with_max_level(level)
+        .init();
+
+    Ok(guard)
+}
+
+/// Logs to stderr, for subcommands that don't take over the terminal
+pub fn init_stderr_logging(level: tracing::Level) {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(level)
+        .init();
+}
@@ -1,14 +1,12 @@
 use clap::{Parser, Subcommand};
+use indicatif::{ProgressBar, ProgressStyle};
 use anyhow::Result;
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
-mod app;
-mod ui;
-mod ai;
-mod watcher;
-mod session;
-mod config;
-
-use app::App;
+use coco::{ai, app, config, logging, report, session, ui, util, watcher};
+use coco::App;
 
 #[derive(Parser)]
 #[command(name = "coco")]
@@ -22,75 +20,537 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Start watching (default)
-    Start,
+    Start {
+        /// Print thoughts to stdout instead of opening the TUI
+        #[arg(long)]
+        headless: bool,
+        /// Watch even if the directory holds more files than COCO_MAX_WATCHED_FILES
+        #[arg(long)]
+        force: bool,
+        /// Show and record accepted suggestions without writing them (COCO_DRY_RUN_EDITS)
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Record session
-    Record,
+    Record {
+        /// Print thoughts to stdout instead of opening the TUI
+        #[arg(long)]
+        headless: bool,
+        /// Continue recording into an existing session instead of starting a new one
+        #[arg(long, value_name = "ID")]
+        resume: Option<String>,
+        /// Watch even if the directory holds more files than COCO_MAX_WATCHED_FILES
+        #[arg(long)]
+        force: bool,
+        /// Show and record accepted suggestions without writing them (COCO_DRY_RUN_EDITS)
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Analyze files once and print the findings, e.g. in CI
+    Analyze {
+        /// Files to analyze
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Also ask the configured AI provider, not just the local pattern checks
+        #[arg(long)]
+        ai: bool,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = report::OutputFormat::Text)]
+        format: report::OutputFormat,
+    },
+    /// Analyze the files staged for commit; fails if any has an error or security finding
+    Staged {
+        /// Also ask the configured AI provider, not just the local pattern checks
+        #[arg(long)]
+        ai: bool,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = report::OutputFormat::Text)]
+        format: report::OutputFormat,
+    },
     /// Replay session
-    Replay { id: String },
+    Replay {
+        id: String,
+        /// Playback speed multiplier (e.g. 2.0 plays twice as fast)
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+        /// Wait for Enter between events
+        #[arg(long)]
+        interactive: bool,
+        /// Only show these event types (comma-separated, e.g. FileChanged,AiResponse)
+        #[arg(long, value_delimiter = ',')]
+        only: Vec<session::EventType>,
+        /// Hide these event types (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        skip: Vec<session::EventType>,
+        /// Only show events for files whose path contains this text
+        #[arg(long)]
+        file: Option<String>,
+        /// Start at this event number (0-based, counted after filtering)
+        #[arg(long)]
+        from: Option<usize>,
+        /// Stop before this event number (0-based, counted after filtering)
+        #[arg(long)]
+        to: Option<usize>,
+        /// Hide UI actions such as key presses
+        #[arg(long)]
+        quiet: bool,
+        /// Hide thoughts and responses below this confidence (0.0-1.0)
+        #[arg(long)]
+        min_confidence: Option<f32>,
+        /// Instead of playing back, re-run the local analyzer on the recorded file
+        /// contents and report findings that differ from the recording
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Write a session report to a file
+    Export {
+        id: String,
+        /// Report format
+        #[arg(long, value_enum, default_value_t = session::ExportFormat::Html)]
+        format: session::ExportFormat,
+        /// Output file (default: <id>.<format> in the current directory)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// Only export these event types (comma-separated, e.g. AiRequest,AiResponse)
+        #[arg(long, value_delimiter = ',')]
+        only: Vec<session::EventType>,
+        /// Leave out events before this RFC 3339 time (e.g. 2024-05-01T14:00:00Z)
+        #[arg(long)]
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        /// Leave out events after this RFC 3339 time
+        #[arg(long)]
+        until: Option<chrono::DateTime<chrono::Utc>>,
+    },
+    /// Combine several recorded sessions into a new one, ordered by time
+    Merge {
+        /// Sessions to merge
+        #[arg(required = true, num_args = 2..)]
+        ids: Vec<String>,
+        /// Write the merged session to this file instead of the sessions directory
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
     /// List sessions
     List,
+    /// Find events in all recorded sessions that mention some text
+    Search {
+        /// Text to look for in event data, recorded file contents and file paths (case-insensitive)
+        query: String,
+        /// Only search these event types (comma-separated, e.g. ThoughtGenerated,AiResponse)
+        #[arg(long = "type", value_delimiter = ',')]
+        types: Vec<session::EventType>,
+    },
+    /// Print a single event from a session without loading the whole file
+    Show {
+        id: String,
+        /// Event id, or an RFC 3339 timestamp to show the first event at or after it
+        event: String,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Setup logging
-    tracing_subscriber::fmt::init();
-
     // Load environment variables
     dotenv::dotenv().ok();
 
     let cli = Cli::parse();
 
+    // Config is loaded before logging starts so COCO_LOG_LEVEL takes effect
+    let log_level = config::Config::load().await?.get_tracing_level();
+
+    // The TUI owns the terminal, so it logs to a file instead of stderr
+    let _log_guard = match cli.command {
+        None
+        | Some(Commands::Start { headless: false, .. })
+        | Some(Commands::Record { headless: false, .. }) => {
+            Some(logging::init_file_logging(log_level)?)
+        }
+        _ => {
+            logging::init_stderr_logging(log_level);
+            None
+        }
+    };
+
     match cli.command {
-        None | Some(Commands::Start) => start_coco().await?,
-        Some(Commands::Record) => start_recording().await?,
-        Some(Commands::Replay { id }) => replay_session(&id).await?,
+        None => start_coco(false, false, false).await?,
+        Some(Commands::Start { headless, force, dry_run }) => start_coco(headless, force, dry_run).await?,
+        Some(Commands::Record { headless, resume, force, dry_run }) => {
+            start_recording(headless, resume.as_deref(), force, dry_run).await?
+        }
+        Some(Commands::Replay { id, verify: true, .. }) => verify_session(&id)?,
+        Some(Commands::Replay { id, speed, interactive, only, mut skip, file, from, to, quiet, min_confidence, .. }) => {
+            if quiet && !skip.contains(&session::EventType::UiAction) {
+                skip.push(session::EventType::UiAction);
+            }
+
+            let options = session::PlaybackOptions {
+                speed_multiplier: speed,
+                skip_events: skip,
+                only_events: if only.is_empty() { None } else { Some(only) },
+                interactive,
+                filter_file_path: file,
+                start_from_event: from,
+                end_at_event: to,
+                min_confidence,
+                ..Default::default()
+            };
+            replay_session(&id, options).await?
+        }
+        Some(Commands::Analyze { paths, ai, format }) => analyze_files(&paths, ai, format).await?,
+        Some(Commands::Staged { ai, format }) => analyze_staged(ai, format).await?,
+        Some(Commands::Export { id, format, output, only, since, until }) => {
+            let output = output.unwrap_or_else(|| PathBuf::from(format!("{}.{}", id, format.extension())));
+            let options = session::ExportOptions {
+                only_types: if only.is_empty() { None } else { Some(only) },
+                from: since,
+                to: until,
+            };
+            session::export_session(&id, &output.to_string_lossy(), format, &options)?;
+            println!("📄 Exported session {} to {}", id, output.display());
+        }
+        Some(Commands::Merge { ids, output }) => merge_sessions(&ids, output.as_deref())?,
         Some(Commands::List) => list_sessions()?,
+        Some(Commands::Search { query, types }) => search_sessions(&query, &types)?,
+        Some(Commands::Show { id, event }) => show_event(&id, &event)?,
     }
 
     Ok(())
 }
 
-async fn start_coco() -> Result<()> {
+async fn start_coco(headless: bool, force: bool, dry_run: bool) -> Result<()> {
     tracing::info!("Starting CoCo v2.0...");
 
     // Initialize application
     let mut app = App::new().await?;
+    if dry_run {
+        *app.dry_run_edits.lock().await = true;
+    }
 
     // Validate configuration
-    app.config.validate().await?;
+    let config = app.get_config().await;
+    config.validate().await?;
+    check_watch_size(&config, force)?;
 
     // Start main application loop
-    app.run().await?;
+    app.run(headless).await?;
 
     Ok(())
 }
 
-async fn start_recording() -> Result<()> {
+async fn start_recording(headless: bool, resume: Option<&str>, force: bool, dry_run: bool) -> Result<()> {
     tracing::info!("Starting CoCo v2.0 with session recording...");
 
     // Initialize application with recording enabled
-    let mut app = App::new_with_recording().await?;
+    let mut app = App::new_with_recording(resume).await?;
+    if dry_run {
+        *app.dry_run_edits.lock().await = true;
+    }
 
     // Validate configuration
-    app.config.validate().await?;
+    let config = app.get_config().await;
+    config.validate().await?;
+    check_watch_size(&config, force)?;
 
     // Start main application loop
-    app.run().await?;
+    app.run(headless).await?;
 
     Ok(())
 }
 
-async fn replay_session(id: &str) -> Result<()> {
+/// Stops before watching a directory tree with more supported files than
+/// `max_watched_files`, unless the user confirms or passed `--force`
+fn check_watch_size(config: &config::Config, force: bool) -> Result<()> {
+    if force || config.max_watched_files == 0 {
+        return Ok(());
+    }
+
+    let limit = config.max_watched_files + 1;
+    let cocoignore = watcher::load_cocoignore(util::project_root());
+    let count: usize = config
+        .watch_directories
+        .iter()
+        .map(|dir| {
+            watcher::count_files(Path::new(dir), limit, &config.exclude_dirs, &cocoignore, |path| config.is_file_supported(path))
+        })
+        .sum();
+
+    if count <= config.max_watched_files {
+        return Ok(());
+    }
+
+    tracing::warn!("Found more than {} supported files to watch", config.max_watched_files);
+    eprintln!(
+        "⚠️  Found more than {} supported files under {}. Watching them all can flood CoCo with changes.",
+        config.max_watched_files,
+        config.watch_directories.join(", ")
+    );
+
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow::anyhow!(
+            "Too many files to watch. Run CoCo in a smaller directory, raise COCO_MAX_WATCHED_FILES, or pass --force."
+        ));
+    }
+
+    eprint!("Watch anyway? [y/N] ");
+    std::io::stderr().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Not watching; pass --force to skip this check"))
+    }
+}
+
+async fn replay_session(id: &str, options: session::PlaybackOptions) -> Result<()> {
     tracing::info!("Replaying session: {}", id);
 
+    if options.speed_multiplier <= 0.0 {
+        return Err(anyhow::anyhow!("Playback speed must be greater than 0"));
+    }
+
+    if options.min_confidence.is_some_and(|c| !(0.0..=1.0).contains(&c)) {
+        return Err(anyhow::anyhow!("Minimum confidence must be between 0.0 and 1.0"));
+    }
+
     // Load and replay session
     let session = session::load_session(id)?;
-    session::replay(session).await?;
+    session::replay(session, options).await?;
 
     Ok(())
 }
 
+fn verify_session(id: &str) -> Result<()> {
+    let session = session::load_session(id)?;
+    let report = session::verify::verify(&session);
+
+    for drift in &report.drifts {
+        println!("❌ Event {} ({}):", drift.event_index, drift.file_path);
+        for finding in &drift.missing {
+            println!("    - {}", finding);
+        }
+        for finding in &drift.unexpected {
+            println!("    + {}", finding);
+        }
+    }
+
+    println!(
+        "Checked {} file changes, {} drifted, {} recorded without content",
+        report.checked,
+        report.drifts.len(),
+        report.skipped
+    );
+
+    if report.checked == 0 {
+        println!("Record with COCO_SESSION_RECORD_CONTENT=true to make sessions verifiable");
+    }
+
+    if report.drifts.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Local analysis drifted from session {}", id))
+    }
+}
+
+async fn analyze_files(paths: &[PathBuf], use_ai: bool, format: report::OutputFormat) -> Result<()> {
+    let config = config::Config::load().await?;
+
+    let mut files = Vec::new();
+    for path in paths {
+        let size = std::fs::metadata(path)?.len();
+        let limit = config.max_file_size_for(path);
+        if size > limit {
+            tracing::warn!("Skipping {} ({} bytes exceeds the {} byte limit)", path.display(), size, limit);
+            continue;
+        }
+
+        files.push((path.clone(), std::fs::read_to_string(path)?));
+    }
+
+    let thoughts = analyze_contents(&config, &files, use_ai).await?;
+
+    match format {
+        report::OutputFormat::Text => {
+            for thought in &thoughts {
+                println!("{}", ui::headless::format_thought(thought));
+            }
+        }
+        report::OutputFormat::Github => print!("{}", report::thoughts_to_github_annotations(&thoughts)),
+        report::OutputFormat::Sarif => println!("{}", report::thoughts_to_sarif(&thoughts)?),
+    }
+
+    Ok(())
+}
+
+/// Runs the local checks, and the AI provider with `use_ai`, on each `(path, content)`.
+/// When the provider can't be set up or fails on a file, a warning goes to stderr and
+/// only the local findings are returned for it.
+async fn analyze_contents(config: &config::Config, files: &[(PathBuf, String)], use_ai: bool) -> Result<Vec<app::Thought>> {
+    let mut analyzer = ai::analyzer::CodeAnalyzer::new();
+    analyzer.set_suppressed_thought_types(config.suppressed_thought_types.clone());
+    analyzer.set_max_line_length(config.max_line_length);
+
+    // Provider failures are warnings, not findings, so an outage can't fail a pre-commit hook
+    let ai_client = if use_ai {
+        let provider = match config.validate().await {
+            Ok(()) => ai::provider_from_config(config),
+            Err(e) => Err(e),
+        };
+        match provider {
+            Ok(provider) => Some(provider),
+            Err(e) => {
+                eprintln!("⚠️  AI analysis unavailable, running local checks only: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let mut thoughts = Vec::new();
+    let progress = analysis_progress(files.len() as u64);
+
+    for (path, content) in files {
+        let file_path = path.display().to_string();
+        progress.set_message(file_path.clone());
+
+        thoughts.extend(analyzer.analyze_code_patterns(content, Some(&file_path)));
+
+        if let Some(ref client) = ai_client {
+            let request = app::AiRequest {
+                id: uuid::Uuid::new_v4().to_string(),
+                request_type: app::AiRequestType::Analyze,
+                context: ai::context::build_context(path, content, util::project_root(), &HashMap::new()),
+                content: content.clone(),
+                file_path: Some(file_path),
+                priority: app::Priority::Medium,
+                selection: None,
+            };
+            match client.analyze_code(&request).await {
+                Ok(ai_thoughts) => thoughts.extend(ai_thoughts.into_iter().filter(|t| config.is_thought_shown(t))),
+                Err(e) => progress.suspend(|| eprintln!("⚠️  AI analysis of {} failed, showing local findings only: {}", path.display(), e)),
+            }
+        }
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+
+    thoughts.retain(|t| !config.is_thought_suppressed(&t.thought_type));
+    Ok(thoughts)
+}
+
+/// A progress bar on stderr for analyzing `len` files, hidden when output is piped
+fn analysis_progress(len: u64) -> ProgressBar {
+    if !std::io::stdout().is_terminal() || !std::io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+
+    let progress = ProgressBar::new(len);
+    progress.set_style(
+        ProgressStyle::with_template("{bar:30.cyan/blue} {pos}/{len} ETA {eta} {wide_msg}")
+            .expect("progress template is valid")
+            .progress_chars("=> "),
+    );
+    progress
+}
+
+/// Analyzes the staged version of every supported file staged for commit, so it can
+/// run as a pre-commit hook: errors and security findings make it fail
+async fn analyze_staged(use_ai: bool, format: report::OutputFormat) -> Result<()> {
+    let config = config::Config::load().await?;
+    let root = util::project_root();
+
+    let staged = git(root, &["diff", "--cached", "--name-only", "--diff-filter=ACMR", "--relative"])?;
+    let cocoignore = watcher::load_cocoignore(root);
+    let mut files = Vec::new();
+    for name in staged.lines().filter(|name| !name.is_empty()) {
+        let path = root.join(name);
+        if !config.is_file_supported(&path) || watcher::is_cocoignored(&path, &cocoignore) {
+            continue;
+        }
+
+        // The index may differ from the working tree; what gets committed is what counts
+        let content = git(root, &["show", &format!(":./{}", name)])?;
+        let limit = config.max_file_size_for(&path);
+        if content.len() as u64 > limit {
+            tracing::warn!("Skipping {} ({} bytes exceeds the {} byte limit)", name, content.len(), limit);
+            continue;
+        }
+        files.push((PathBuf::from(name), content));
+    }
+
+    if files.is_empty() {
+        println!("✅ No staged files to analyze");
+        return Ok(());
+    }
+
+    let thoughts = analyze_contents(&config, &files, use_ai).await?;
+
+    match format {
+        report::OutputFormat::Text => {
+            for (path, _) in &files {
+                let file_path = path.display().to_string();
+                let file_thoughts: Vec<_> = thoughts
+                    .iter()
+                    .filter(|t| t.file_path.as_deref() == Some(file_path.as_str()))
+                    .collect();
+                println!("📄 {} ({} thoughts)", file_path, file_thoughts.len());
+                for thought in file_thoughts {
+                    println!("   {}", ui::headless::format_thought(thought).replace('\n', "\n   "));
+                }
+            }
+        }
+        report::OutputFormat::Github => print!("{}", report::thoughts_to_github_annotations(&thoughts)),
+        report::OutputFormat::Sarif => println!("{}", report::thoughts_to_sarif(&thoughts)?),
+    }
+
+    let blocking = thoughts
+        .iter()
+        .filter(|t| matches!(t.thought_type, app::ThoughtType::Error | app::ThoughtType::Security))
+        .count();
+    if blocking > 0 {
+        return Err(anyhow::anyhow!("{} error or security finding(s) in staged files", blocking));
+    }
+
+    Ok(())
+}
+
+/// Runs git in `dir` and returns its stdout, failing with git's stderr
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn merge_sessions(ids: &[String], output: Option<&Path>) -> Result<()> {
+    let merged = session::merge(ids)?;
+
+    let path = match output {
+        Some(path) => {
+            std::fs::write(path, serde_json::to_string_pretty(&merged)?)?;
+            path.to_path_buf()
+        }
+        None => session::save_session(&merged)?,
+    };
+
+    println!("🔗 Merged {} sessions ({} events) into {}", ids.len(), merged.events.len(), merged.id);
+    println!("   Saved to {}", path.display());
+    Ok(())
+}
+
 fn list_sessions() -> Result<()> {
     println!("📝 Recorded Sessions:");
 
@@ -102,12 +562,46 @@ fn list_sessions() -> Result<()> {
     }
 
     for session in sessions {
-        println!("   🎥 {} - {} events ({})",
+        let active_minutes = session.metadata.active_duration_ms
+            .unwrap_or_else(|| session.active_duration_ms()) / 60_000;
+        println!("   🎥 {} - {} events ({}, {}m active)",
             session.id,
             session.events.len(),
-            session.started_at.format("%Y-%m-%d %H:%M")
+            session.started_at.format("%Y-%m-%d %H:%M"),
+            active_minutes
+        );
+    }
+
+    Ok(())
+}
+
+fn search_sessions(query: &str, types: &[session::EventType]) -> Result<()> {
+    let hits = session::search(query, types)?;
+
+    if hits.is_empty() {
+        println!("🔍 No events mention \"{}\"", query);
+        return Ok(());
+    }
+
+    println!("🔍 {} events mention \"{}\":", hits.len(), query);
+    for hit in hits {
+        println!("   {} {} {:?} {}",
+            hit.session_id,
+            hit.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            hit.event_type,
+            hit.file_path.as_deref().unwrap_or("-")
         );
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+fn show_event(id: &str, event: &str) -> Result<()> {
+    let event = match chrono::DateTime::parse_from_rfc3339(event) {
+        Ok(timestamp) => session::index::load_event_at(id, timestamp.with_timezone(&chrono::Utc))?,
+        Err(_) => session::index::load_event(id, event)?,
+    };
+
+    println!("{}", serde_json::to_string_pretty(&event)?);
+    Ok(())
+}
@@ -0,0 +1,278 @@
+use crate::error::{CocoError, Result};
+use chrono::Utc;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::app::{Thought, ThoughtType};
+use crate::config::ThoughtExportFormat;
+
+/// How the one-shot `analyze` command prints its findings
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable, one thought per block
+    Text,
+    /// GitHub Actions workflow commands (`::error file=...,line=...::message`)
+    Github,
+    /// SARIF 2.1.0, for GitHub code scanning and other SARIF viewers
+    Sarif,
+}
+
+pub fn get_exports_directory() -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .ok_or(CocoError::NoHomeDirectory)?;
+
+    let exports_dir = home.join(".coco").join("exports");
+    std::fs::create_dir_all(&exports_dir)?;
+
+    Ok(exports_dir)
+}
+
+/// Renders thoughts as Markdown suitable for pasting into a PR comment
+pub fn thoughts_to_markdown(thoughts: &[Thought]) -> String {
+    let mut markdown = String::new();
+
+    markdown.push_str("# CoCo Thoughts\n\n");
+    markdown.push_str(&format!(
+        "_Exported {} · {} thoughts_\n",
+        Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+        thoughts.len()
+    ));
+
+    for thought in thoughts {
+        let location = match (&thought.file_path, thought.line_number) {
+            (Some(path), Some(line)) => format!(" · `{}:{}`", path, line),
+            (Some(path), None) => format!(" · `{}`", path),
+            _ => String::new(),
+        };
+
+        markdown.push_str(&format!(
+            "\n## {:?}{} ({:.0}%)\n\n",
+            thought.thought_type,
+            location,
+            thought.confidence * 100.0
+        ));
+        markdown.push_str(thought.content.trim());
+        markdown.push('\n');
+
+        if !thought.suggestions.is_empty() {
+            markdown.push_str("\n**Suggestions**\n\n");
+        }
+
+        for suggestion in &thought.suggestions {
+            markdown.push_str(&format!(
+                "- **{}** ({:?}, {:?}): {}\n",
+                suggestion.title,
+                suggestion.priority,
+                suggestion.action_type,
+                suggestion.description
+            ));
+
+            if let Some(ref code) = suggestion.code_snippet {
+                markdown.push_str("\n  ```\n");
+                for line in code.lines() {
+                    markdown.push_str(&format!("  {}\n", line));
+                }
+                markdown.push_str("  ```\n");
+            }
+        }
+    }
+
+    markdown
+}
+
+/// Writes thoughts as Markdown or JSON to a timestamped file in the exports directory
+pub fn export_thoughts(thoughts: &[Thought], format: ThoughtExportFormat) -> Result<PathBuf> {
+    let (content, extension) = match format {
+        ThoughtExportFormat::Markdown => (thoughts_to_markdown(thoughts), "md"),
+        ThoughtExportFormat::Json => (serde_json::to_string_pretty(thoughts)?, "json"),
+    };
+    let file_name = format!("thoughts-{}.{}", Utc::now().format("%Y%m%d-%H%M%S"), extension);
+    let output_path = get_exports_directory()?.join(file_name);
+
+    std::fs::write(&output_path, content)?;
+
+    tracing::info!("Exported {} thoughts to {}", thoughts.len(), output_path.display());
+    Ok(output_path)
+}
+
+/// Renders thoughts tied to a file as GitHub Actions annotations, one per line.
+/// Errors and security findings become `::error`, warnings and performance
+/// issues `::warning`, and everything else `::notice`.
+pub fn thoughts_to_github_annotations(thoughts: &[Thought]) -> String {
+    let mut output = String::new();
+
+    for thought in thoughts {
+        let Some(ref file_path) = thought.file_path else {
+            continue;
+        };
+
+        let level = match thought.thought_type {
+            ThoughtType::Error | ThoughtType::Security => "error",
+            ThoughtType::Warning | ThoughtType::Performance => "warning",
+            _ => "notice",
+        };
+
+        let mut properties = format!("file={}", escape_annotation_property(file_path));
+        if let Some(line) = thought.line_number {
+            properties.push_str(&format!(",line={}", line));
+        }
+        properties.push_str(&format!(",title={}", escape_annotation_property(&format!("CoCo {:?}", thought.thought_type))));
+
+        output.push_str(&format!("::{} {}::{}\n", level, properties, escape_annotation_data(thought.content.trim())));
+    }
+
+    output
+}
+
+/// Renders thoughts as a SARIF 2.1.0 log with a single run. Local analyzer findings
+/// use their `rule_id`; AI thoughts are grouped under `ai_<thought type>` rules.
+pub fn thoughts_to_sarif(thoughts: &[Thought]) -> Result<String> {
+    let mut rules: BTreeMap<String, String> = BTreeMap::new();
+    for thought in thoughts {
+        rules.entry(sarif_rule_id(thought)).or_insert_with(|| {
+            thought
+                .suggestions
+                .first()
+                .map(|suggestion| suggestion.title.clone())
+                .unwrap_or_else(|| format!("{:?} finding", thought.thought_type))
+        });
+    }
+    let rule_index: BTreeMap<&String, usize> = rules.keys().enumerate().map(|(i, id)| (id, i)).collect();
+
+    let results: Vec<serde_json::Value> = thoughts
+        .iter()
+        .map(|thought| {
+            let rule_id = sarif_rule_id(thought);
+            let level = match thought.thought_type {
+                ThoughtType::Error | ThoughtType::Security => "error",
+                ThoughtType::Warning | ThoughtType::Performance => "warning",
+                _ => "note",
+            };
+
+            let mut result = json!({
+                "ruleId": rule_id,
+                "ruleIndex": rule_index[&rule_id],
+                "level": level,
+                "message": { "text": thought.content.trim() },
+            });
+
+            if let Some(ref file_path) = thought.file_path {
+                let mut physical_location = json!({
+                    "artifactLocation": { "uri": file_path.replace('\\', "/") },
+                });
+                if let Some(line) = thought.line_number {
+                    physical_location["region"] = json!({ "startLine": line });
+                }
+                result["locations"] = json!([{ "physicalLocation": physical_location }]);
+            }
+
+            result
+        })
+        .collect();
+
+    let sarif = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "CoCo",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules
+                        .iter()
+                        .map(|(id, description)| json!({
+                            "id": id,
+                            "shortDescription": { "text": description },
+                        }))
+                        .collect::<Vec<_>>(),
+                }
+            },
+            "results": results,
+        }]
+    });
+
+    Ok(serde_json::to_string_pretty(&sarif)?)
+}
+
+fn sarif_rule_id(thought: &Thought) -> String {
+    thought
+        .rule_id
+        .clone()
+        .unwrap_or_else(|| format!("ai_{:?}", thought.thought_type).to_lowercase())
+}
+
+// Workflow commands are line-based, so newlines and the delimiters must be percent-encoded
+fn escape_annotation_data(value: &str) -> String {
+    value.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_annotation_property(value: &str) -> String {
+    escape_annotation_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thought(thought_type: ThoughtType, line_number: Option<usize>, content: &str) -> Thought {
+        Thought::new(thought_type, content.to_string(), Some("src/a,b.rs".to_string()), line_number, 0.8)
+    }
+
+    #[test]
+    fn test_github_annotations() {
+        let output = thoughts_to_github_annotations(&[
+            thought(ThoughtType::Security, Some(12), "Hardcoded key\n100% bad"),
+            thought(ThoughtType::Style, None, "Long lines"),
+        ]);
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "::error file=src/a%2Cb.rs,line=12,title=CoCo Security::Hardcoded key%0A100%25 bad");
+        assert_eq!(lines[1], "::notice file=src/a%2Cb.rs,title=CoCo Style::Long lines");
+    }
+
+    #[test]
+    fn test_sarif_report() {
+        let mut unwrap = thought(ThoughtType::Warning, Some(3), "Avoid unwrap");
+        unwrap.rule_id = Some("unwrap_usage".to_string());
+
+        let sarif: serde_json::Value = serde_json::from_str(
+            &thoughts_to_sarif(&[thought(ThoughtType::Security, None, "Hardcoded key"), unwrap]).unwrap()
+        ).unwrap();
+
+        let run = &sarif["runs"][0];
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "ai_security");
+        assert_eq!(run["tool"]["driver"]["rules"][1]["id"], "unwrap_usage");
+
+        let result = &run["results"][1];
+        assert_eq!(result["ruleId"], "unwrap_usage");
+        assert_eq!(result["ruleIndex"], 1);
+        assert_eq!(result["level"], "warning");
+        assert_eq!(result["locations"][0]["physicalLocation"]["region"]["startLine"], 3);
+        assert_eq!(run["results"][0]["level"], "error");
+        assert!(run["results"][0]["locations"][0]["physicalLocation"].get("region").is_none());
+    }
+
+    #[test]
+    fn test_ai_findings_keep_their_line() {
+        let request = crate::app::AiRequest {
+            id: "r".to_string(),
+            request_type: crate::app::AiRequestType::Analyze,
+            content: "fn main() {}\n".to_string(),
+            file_path: Some("src/main.rs".to_string()),
+            context: std::collections::HashMap::new(),
+            priority: crate::app::Priority::Medium,
+            selection: None,
+        };
+        let thoughts = crate::ai::prompt::parse_response("1. Security vulnerability: the query on line 7 interpolates user input.", &request, false);
+
+        let annotations = thoughts_to_github_annotations(&thoughts);
+        assert!(annotations.starts_with("::error file=src/main.rs,line=7,"), "{}", annotations);
+
+        let sarif: serde_json::Value = serde_json::from_str(&thoughts_to_sarif(&thoughts).unwrap()).unwrap();
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], "ai_security");
+        assert_eq!(result["locations"][0]["physicalLocation"]["region"]["startLine"], 7);
+    }
+}
@@ -0,0 +1,230 @@
+use crate::error::{CocoError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use super::{get_session_path, get_sessions_directory, SessionEvent};
+
+/// Sidecar index (`{id}.index.json`) mapping each event to its byte range in the
+/// session file, so single events can be read without parsing the whole session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionIndex {
+    /// Size of the session file the index was built from; a mismatch means it is stale
+    pub session_size: u64,
+    pub entries: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub event_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub offset: u64,
+    pub length: u64,
+}
+
+#[derive(Deserialize)]
+struct EventHeader {
+    id: String,
+    timestamp: DateTime<Utc>,
+}
+
+impl SessionIndex {
+    /// Builds an index by scanning the raw session JSON for the `events` array
+    pub fn build(content: &[u8]) -> Result<Self> {
+        let mut entries = Vec::new();
+
+        for (start, end) in event_spans(content)? {
+            let header: EventHeader = serde_json::from_slice(&content[start..end])?;
+            entries.push(IndexEntry {
+                event_id: header.id,
+                timestamp: header.timestamp,
+                offset: start as u64,
+                length: (end - start) as u64,
+            });
+        }
+
+        Ok(Self {
+            session_size: content.len() as u64,
+            entries,
+        })
+    }
+
+    pub fn find(&self, event_id: &str) -> Option<&IndexEntry> {
+        self.entries.iter().find(|entry| entry.event_id == event_id)
+    }
+
+    /// Position of the first event at or after `timestamp` (events are recorded in order)
+    pub fn seek(&self, timestamp: DateTime<Utc>) -> usize {
+        self.entries.partition_point(|entry| entry.timestamp < timestamp)
+    }
+}
+
+pub fn get_index_path(id: &str) -> Result<PathBuf> {
+    Ok(get_sessions_directory()?.join(format!("{}.index.json", id)))
+}
+
+pub fn is_index_file(path: &Path) -> bool {
+    path.to_str().is_some_and(|p| p.ends_with(".index.json"))
+}
+
+/// Loads the index for a session, rebuilding it if it is missing or out of date
+pub fn load_index(id: &str) -> Result<SessionIndex> {
+    let session_path = get_session_path(id)?;
+    let index_path = get_index_path(id)?;
+    let session_size = std::fs::metadata(&session_path)?.len();
+
+    if let Ok(content) = std::fs::read_to_string(&index_path) {
+        match serde_json::from_str::<SessionIndex>(&content) {
+            Ok(index) if index.session_size == session_size => return Ok(index),
+            Ok(_) => tracing::debug!("Session index for {} is stale, rebuilding", id),
+            Err(e) => tracing::warn!("Failed to parse session index {}: {}", index_path.display(), e),
+        }
+    }
+
+    let index = SessionIndex::build(&std::fs::read(&session_path)?)?;
+    std::fs::write(&index_path, serde_json::to_string(&index)?)?;
+
+    Ok(index)
+}
+
+/// Reads a single event from a session file using its index
+pub fn load_event(id: &str, event_id: &str) -> Result<SessionEvent> {
+    let index = load_index(id)?;
+    let entry = index
+        .find(event_id)
+        .ok_or_else(|| CocoError::InvalidSession(format!("Event {} not found in session {}", event_id, id)))?;
+
+    read_entry(id, entry)
+}
+
+/// Reads the first event recorded at or after `timestamp`
+pub fn load_event_at(id: &str, timestamp: DateTime<Utc>) -> Result<SessionEvent> {
+    let index = load_index(id)?;
+    let entry = index
+        .entries
+        .get(index.seek(timestamp))
+        .ok_or_else(|| CocoError::InvalidSession(format!("No events at or after {} in session {}", timestamp, id)))?;
+
+    read_entry(id, entry)
+}
+
+fn read_entry(id: &str, entry: &IndexEntry) -> Result<SessionEvent> {
+    let mut file = std::fs::File::open(get_session_path(id)?)?;
+    file.seek(SeekFrom::Start(entry.offset))?;
+
+    let mut buffer = vec![0u8; entry.length as usize];
+    file.read_exact(&mut buffer)?;
+
+    Ok(serde_json::from_slice(&buffer)?)
+}
+
+/// Byte ranges of each element of the top-level `events` array
+fn event_spans(content: &[u8]) -> Result<Vec<(usize, usize)>> {
+    let mut spans = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut string_start = 0;
+    let mut last_key: &[u8] = &[];
+    let mut events_depth = None;
+    let mut event_start = 0;
+
+    for (i, &byte) in content.iter().enumerate() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => {
+                    in_string = false;
+                    if depth == 1 {
+                        last_key = &content[string_start..i];
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        match byte {
+            b'"' => {
+                in_string = true;
+                string_start = i + 1;
+            }
+            b'{' | b'[' => {
+                if events_depth == Some(depth) && byte == b'{' {
+                    event_start = i;
+                }
+                if depth == 1 && byte == b'[' && last_key == b"events" {
+                    events_depth = Some(depth + 1);
+                }
+                depth += 1;
+            }
+            b'}' | b']' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| CocoError::InvalidSession(format!("Unbalanced JSON at byte {}", i)))?;
+                if events_depth == Some(depth) && byte == b'}' {
+                    spans.push((event_start, i + 1));
+                }
+                if events_depth == Some(depth + 1) && byte == b']' {
+                    return Ok(spans);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Err(CocoError::InvalidSession("Session file has no complete events array".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{EventContext, EventType, Session, SessionMetadata};
+    use serde_json::json;
+
+    #[test]
+    fn test_index_spans_match_events() {
+        let events: Vec<SessionEvent> = (0..3)
+            .map(|i| SessionEvent {
+                id: format!("event-{}", i),
+                timestamp: Utc::now() + chrono::Duration::seconds(i),
+                event_type: EventType::FileChanged,
+                data: json!({ "path": format!("src/{{weird}}\"[{}].rs", i) }),
+                context: EventContext::default(),
+            })
+            .collect();
+
+        let session = Session {
+            id: "test".to_string(),
+            started_at: Utc::now(),
+            ended_at: None,
+            events: events.clone(),
+            metadata: SessionMetadata {
+                coco_version: "0.1.0".to_string(),
+                working_directory: "/tmp".to_string(),
+                user: None,
+                ai_provider: "anthropic".to_string(),
+                ai_model: None,
+                total_duration_ms: None,
+                active_duration_ms: None,
+                total_file_changes: 3,
+                total_ai_requests: 0,
+                files_analyzed: Vec::new(),
+            },
+        };
+
+        let content = serde_json::to_string_pretty(&session).unwrap();
+        let index = SessionIndex::build(content.as_bytes()).unwrap();
+        assert_eq!(index.entries.len(), 3);
+
+        let entry = index.find("event-1").unwrap();
+        let start = entry.offset as usize;
+        let event: SessionEvent =
+            serde_json::from_str(&content[start..start + entry.length as usize]).unwrap();
+        assert_eq!(event.data, events[1].data);
+
+        assert_eq!(index.seek(events[2].timestamp), 2);
+    }
+}
@@ -1,7 +1,9 @@
+pub mod index;
 pub mod recorder;
 pub mod replay;
+pub mod verify;
 
-use anyhow::Result;
+use crate::error::{CocoError, Result};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use std::path::PathBuf;
@@ -21,7 +23,13 @@ pub struct SessionMetadata {
     pub working_directory: String,
     pub user: Option<String>,
     pub ai_provider: String,
+    /// Not recorded by sessions from before the model was tracked
+    #[serde(default)]
+    pub ai_model: Option<String>,
     pub total_duration_ms: Option<u64>,
+    /// Time with recorded activity, see `Session::active_duration_ms`
+    #[serde(default)]
+    pub active_duration_ms: Option<u64>,
     pub total_file_changes: usize,
     pub total_ai_requests: usize,
     pub files_analyzed: Vec<String>,
@@ -49,6 +57,40 @@ pub enum EventType {
     ThoughtGenerated,
     SuggestionAccepted,
     SuggestionRejected,
+    /// A note the user attached to a moment of the session
+    Annotation,
+    /// Improvement ideas returned for a suggestions request
+    SuggestionsGenerated,
+}
+
+impl EventType {
+    pub const ALL: [EventType; 13] = [
+        EventType::SessionStarted,
+        EventType::SessionEnded,
+        EventType::FileChanged,
+        EventType::AiRequest,
+        EventType::AiResponse,
+        EventType::UiAction,
+        EventType::Error,
+        EventType::ConfigChange,
+        EventType::ThoughtGenerated,
+        EventType::SuggestionAccepted,
+        EventType::SuggestionRejected,
+        EventType::Annotation,
+        EventType::SuggestionsGenerated,
+    ];
+}
+
+impl std::str::FromStr for EventType {
+    type Err = String;
+
+    /// Parses an event type name case-insensitively, e.g. `"fileChanged"` or `"FileChanged"`
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        EventType::ALL
+            .into_iter()
+            .find(|t| format!("{:?}", t).eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| format!("unknown event type: {}", name))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,9 +114,99 @@ impl Default for EventContext {
     }
 }
 
+/// Gaps between events longer than this count as idle time rather than activity
+const IDLE_GAP_MS: i64 = 5 * 60 * 1000;
+
+impl Session {
+    /// Time spent actively using CoCo: the gaps between consecutive events, with
+    /// each idle stretch capped at `IDLE_GAP_MS` and the downtime between an
+    /// end and a resume left out entirely
+    pub fn active_duration_ms(&self) -> u64 {
+        self.events
+            .windows(2)
+            .filter(|pair| pair[0].event_type != EventType::SessionEnded)
+            .map(|pair| {
+                pair[1]
+                    .timestamp
+                    .signed_duration_since(pair[0].timestamp)
+                    .num_milliseconds()
+                    .clamp(0, IDLE_GAP_MS) as u64
+            })
+            .sum()
+    }
+}
+
 // Re-export main types
 pub use recorder::SessionRecorder;
-pub use replay::SessionPlayer;
+pub use replay::{confidence_bucket, PlaybackOptions, SessionPlayer, SessionSummary, CONFIDENCE_BUCKETS};
+
+/// Computes the headline analytics of a session from its events
+pub fn summarize(session: &Session) -> SessionSummary {
+    let mut file_changes = 0;
+    let mut ai_requests = 0;
+    let mut ai_responses = 0;
+    let mut ui_actions = 0;
+    let mut errors = 0;
+    let mut total_ai_duration = 0u64;
+    let mut successful_ai_requests = 0;
+    let mut confidence_histogram = [0; CONFIDENCE_BUCKETS];
+
+    for event in &session.events {
+        match event.event_type {
+            EventType::FileChanged => file_changes += 1,
+            EventType::AiRequest => ai_requests += 1,
+            EventType::AiResponse => {
+                ai_responses += 1;
+                if let Some(duration) = event.context.duration_ms {
+                    total_ai_duration += duration;
+                }
+                if event.data.get("success").and_then(|v| v.as_bool()).unwrap_or(false) {
+                    successful_ai_requests += 1;
+                }
+            }
+            EventType::ThoughtGenerated => {
+                if let Some(confidence) = event.data.get("confidence").and_then(|c| c.as_f64()) {
+                    confidence_histogram[confidence_bucket(confidence)] += 1;
+                }
+            }
+            EventType::UiAction => ui_actions += 1,
+            EventType::Error => errors += 1,
+            _ => {}
+        }
+    }
+
+    let duration = session.ended_at
+        .map(|end| end.signed_duration_since(session.started_at))
+        .map(|d| d.num_milliseconds() as u64);
+
+    SessionSummary {
+        session_id: session.id.clone(),
+        started_at: session.started_at,
+        ended_at: session.ended_at,
+        duration_ms: duration,
+        active_duration_ms: session.active_duration_ms(),
+        total_events: session.events.len(),
+        file_changes,
+        ai_requests,
+        ai_responses,
+        ui_actions,
+        errors,
+        successful_ai_requests,
+        ai_success_rate: if ai_requests > 0 {
+            successful_ai_requests as f64 / ai_requests as f64
+        } else {
+            0.0
+        },
+        average_ai_response_time: if ai_responses > 0 {
+            total_ai_duration / ai_responses as u64
+        } else {
+            0
+        },
+        unique_files: session.metadata.files_analyzed.len(),
+        files_analyzed: session.metadata.files_analyzed.clone(),
+        confidence_histogram,
+    }
+}
 
 // Helper functions
 pub fn load_session(id: &str) -> Result<Session> {
@@ -97,7 +229,7 @@ pub fn list_sessions() -> Result<Vec<Session>> {
         let entry = entry?;
         let path = entry.path();
 
-        if path.extension().and_then(|s| s.to_str()) == Some("json") {
+        if path.extension().and_then(|s| s.to_str()) == Some("json") && !index::is_index_file(&path) {
             match std::fs::read_to_string(&path) {
                 Ok(content) => {
                     match serde_json::from_str::<Session>(&content) {
@@ -120,8 +252,120 @@ pub fn list_sessions() -> Result<Vec<Session>> {
     Ok(sessions)
 }
 
-pub async fn replay(session: Session) -> Result<()> {
-    let mut player = SessionPlayer::new(session);
+/// An event found by `search`
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub session_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub event_type: EventType,
+    pub file_path: Option<String>,
+}
+
+/// Events in every recorded session whose data or file path contains `query`,
+/// ignoring case. Newest sessions come first; events keep their recorded order.
+/// A non-empty `types` limits the search to those event types.
+pub fn search(query: &str, types: &[EventType]) -> Result<Vec<SearchHit>> {
+    let query = query.to_lowercase();
+    let mut hits = Vec::new();
+
+    for session in list_sessions()? {
+        for event in &session.events {
+            if (!types.is_empty() && !types.contains(&event.event_type)) || !event_matches(event, &query) {
+                continue;
+            }
+            hits.push(SearchHit {
+                session_id: session.id.clone(),
+                timestamp: event.timestamp,
+                event_type: event.event_type.clone(),
+                file_path: event_file_path(event),
+            });
+        }
+    }
+
+    Ok(hits)
+}
+
+/// Whether the event's data (including recorded file contents) or file path contains
+/// the already lowercased `query`
+fn event_matches(event: &SessionEvent, query: &str) -> bool {
+    event.data.to_string().to_lowercase().contains(query)
+        || event_file_path(event).is_some_and(|path| path.to_lowercase().contains(query))
+}
+
+fn event_file_path(event: &SessionEvent) -> Option<String> {
+    event.context.file_path.clone().or_else(|| {
+        ["path", "file_path"]
+            .iter()
+            .find_map(|key| event.data.get(*key).and_then(|p| p.as_str()).map(str::to_string))
+    })
+}
+
+/// Combines recorded sessions into a new one with a fresh id, e.g. to report on a day
+/// of short recordings together. The new session is not saved.
+pub fn merge(ids: &[String]) -> Result<Session> {
+    if ids.len() < 2 {
+        return Err(CocoError::InvalidAction("Merging needs at least two sessions".to_string()));
+    }
+
+    let sessions = ids
+        .iter()
+        .map(|id| load_session(id).map_err(|e| CocoError::SessionLoad { id: id.clone(), source: Box::new(e) }))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(merge_sessions(sessions))
+}
+
+/// Interleaves the events of `sessions` chronologically, so overlapping recordings
+/// read as one timeline, and adds up their counters. An event present in more than
+/// one session (the same session passed twice) is kept once. `sessions` must not be empty.
+fn merge_sessions(mut sessions: Vec<Session>) -> Session {
+    sessions.sort_by_key(|session| session.started_at);
+
+    let mut seen = std::collections::HashSet::new();
+    let mut events: Vec<SessionEvent> = sessions
+        .iter()
+        .flat_map(|session| session.events.iter().cloned())
+        .filter(|event| seen.insert(event.id.clone()))
+        .collect();
+    events.sort_by_key(|event| event.timestamp);
+
+    let mut files_analyzed: Vec<String> = Vec::new();
+    for file in sessions.iter().flat_map(|session| &session.metadata.files_analyzed) {
+        if !files_analyzed.contains(file) {
+            files_analyzed.push(file.clone());
+        }
+    }
+
+    let started_at = sessions[0].started_at;
+    let ended_at = sessions.iter().filter_map(|session| session.ended_at).max();
+    let last_activity = ended_at.or(events.last().map(|event| event.timestamp));
+
+    let mut merged = Session {
+        id: uuid::Uuid::new_v4().to_string(),
+        started_at,
+        ended_at,
+        events,
+        metadata: SessionMetadata {
+            total_duration_ms: last_activity.map(|end| end.signed_duration_since(started_at).num_milliseconds().max(0) as u64),
+            active_duration_ms: None,
+            total_file_changes: sessions.iter().map(|session| session.metadata.total_file_changes).sum(),
+            total_ai_requests: sessions.iter().map(|session| session.metadata.total_ai_requests).sum(),
+            files_analyzed,
+            ..sessions[0].metadata.clone()
+        },
+    };
+    merged.metadata.active_duration_ms = Some(merged.active_duration_ms());
+    merged
+}
+
+/// Writes `session` to the sessions directory, where `list`, `replay` and `export` find it
+pub fn save_session(session: &Session) -> Result<PathBuf> {
+    let session_path = get_session_path(&session.id)?;
+    std::fs::write(&session_path, serde_json::to_string_pretty(session)?)?;
+    Ok(session_path)
+}
+
+pub async fn replay(session: Session, options: PlaybackOptions) -> Result<()> {
+    let mut player = SessionPlayer::new(session).with_options(options);
     player.play().await
 }
 
@@ -129,13 +373,41 @@ pub fn delete_session(id: &str) -> Result<()> {
     let session_path = get_session_path(id)?;
     if session_path.exists() {
         std::fs::remove_file(&session_path)?;
+        let _ = std::fs::remove_file(index::get_index_path(id)?);
         tracing::info!("Deleted session: {}", id);
     }
     Ok(())
 }
 
-pub fn export_session(id: &str, output_path: &str, format: ExportFormat) -> Result<()> {
-    let session = load_session(id)?;
+/// Which events of a session an export includes; the default includes all of them
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    pub only_types: Option<Vec<EventType>>,
+    /// Leave out events before this time
+    pub from: Option<DateTime<Utc>>,
+    /// Leave out events after this time
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl ExportOptions {
+    /// `session` with only the events these options include, selected by type the
+    /// same way as replay
+    pub fn apply(&self, mut session: Session) -> Session {
+        let type_filter = PlaybackOptions {
+            only_events: self.only_types.clone(),
+            ..Default::default()
+        };
+        session.events.retain(|event| {
+            type_filter.includes(event)
+                && self.from.is_none_or(|from| event.timestamp >= from)
+                && self.to.is_none_or(|to| event.timestamp <= to)
+        });
+        session
+    }
+}
+
+pub fn export_session(id: &str, output_path: &str, format: ExportFormat, options: &ExportOptions) -> Result<()> {
+    let session = options.apply(load_session(id)?);
 
     match format {
         ExportFormat::Json => {
@@ -148,22 +420,39 @@ pub fn export_session(id: &str, output_path: &str, format: ExportFormat) -> Resu
         ExportFormat::Html => {
             export_session_to_html(&session, output_path)?;
         }
+        ExportFormat::Markdown => {
+            export_session_to_markdown(&session, output_path)?;
+        }
     }
 
     tracing::info!("Exported session {} to {} (format: {:?})", id, output_path, format);
     Ok(())
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
 pub enum ExportFormat {
     Json,
     Csv,
+    /// Standalone page with analytics and a color-coded timeline
     Html,
+    /// Analytics table and timeline, for pasting into issues or docs
+    Markdown,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Html => "html",
+            ExportFormat::Markdown => "md",
+        }
+    }
 }
 
 pub fn get_sessions_directory() -> Result<PathBuf> {
     let home = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+        .ok_or(CocoError::NoHomeDirectory)?;
 
     let sessions_dir = home.join(".coco").join("sessions");
     std::fs::create_dir_all(&sessions_dir)?;
@@ -200,6 +489,77 @@ fn export_session_to_csv(session: &Session, output_path: &str) -> Result<()> {
     Ok(())
 }
 
+/// Headline numbers shown at the top of session reports, as label/value pairs
+fn analytics_rows(summary: &SessionSummary) -> Vec<(&'static str, String)> {
+    let success_rate = if summary.ai_requests > 0 {
+        format!("{:.1}% ({} of {})", summary.ai_success_rate * 100.0, summary.successful_ai_requests, summary.ai_requests)
+    } else {
+        "n/a (no AI requests)".to_string()
+    };
+    let average_latency = if summary.ai_responses > 0 {
+        format!("{} ms", summary.average_ai_response_time)
+    } else {
+        "n/a".to_string()
+    };
+
+    vec![
+        ("AI Success Rate", success_rate),
+        ("Average AI Response Time", average_latency),
+        ("Unique Files", summary.unique_files.to_string()),
+        ("Errors", summary.errors.to_string()),
+    ]
+}
+
+fn export_session_to_markdown(session: &Session, output_path: &str) -> Result<()> {
+    let summary = summarize(session);
+    let mut markdown = String::new();
+
+    markdown.push_str(&format!("# CoCo Session Report\n\n**Session ID:** {}  \n", session.id));
+    markdown.push_str(&format!("**Started:** {}  \n", session.started_at.format("%Y-%m-%d %H:%M:%S UTC")));
+    if let Some(ended_at) = session.ended_at {
+        markdown.push_str(&format!("**Ended:** {}  \n", ended_at.format("%Y-%m-%d %H:%M:%S UTC")));
+    }
+
+    markdown.push_str("\n## Analytics\n\n| Metric | Value |\n|--------|-------|\n");
+    for (label, value) in analytics_rows(&summary) {
+        markdown.push_str(&format!("| {} | {} |\n", label, value));
+    }
+
+    if summary.confidence_histogram.iter().any(|count| *count > 0) {
+        let total: usize = summary.confidence_histogram.iter().sum();
+        markdown.push_str("\n## Thought Confidence\n\n| Confidence | Thoughts | |\n|------------|----------|---|\n");
+        for (label, count) in summary.confidence_rows() {
+            let bar = "█".repeat((count * 20).div_ceil(total));
+            markdown.push_str(&format!("| {} | {} | {} |\n", label, count, bar));
+        }
+    }
+
+    markdown.push_str("\n## Session Metadata\n\n");
+    markdown.push_str(&format!("- **CoCo Version:** {}\n", session.metadata.coco_version));
+    markdown.push_str(&format!("- **Working Directory:** {}\n", session.metadata.working_directory));
+    markdown.push_str(&format!("- **AI Provider:** {}\n", session.metadata.ai_provider));
+    if let Some(model) = &session.metadata.ai_model {
+        markdown.push_str(&format!("- **AI Model:** {}\n", model));
+    }
+    markdown.push_str(&format!("- **Total Events:** {}\n", summary.total_events));
+    markdown.push_str(&format!("- **File Changes:** {}\n", summary.file_changes));
+
+    markdown.push_str("\n## Events Timeline\n\n");
+    for event in &session.events {
+        markdown.push_str(&format!("- `{}` **{:?}**", event.timestamp.format("%H:%M:%S%.3f"), event.event_type));
+        if let Some(ref file_path) = event.context.file_path {
+            markdown.push_str(&format!(" `{}`", file_path));
+        }
+        if let Some(note) = event.data.get("note").and_then(|n| n.as_str()) {
+            markdown.push_str(&format!(" 📌 {}", note));
+        }
+        markdown.push('\n');
+    }
+
+    std::fs::write(output_path, markdown)?;
+    Ok(())
+}
+
 fn export_session_to_html(session: &Session, output_path: &str) -> Result<()> {
     let mut html = String::new();
 
@@ -225,12 +585,40 @@ fn export_session_to_html(session: &Session, output_path: &str) -> Result<()> {
         ));
     }
 
+    // Analytics
+    let summary = summarize(session);
+    html.push_str("<h3>Analytics</h3>\n");
+    html.push_str("<table class=\"analytics\">\n");
+    for (label, value) in analytics_rows(&summary) {
+        html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", label, value));
+    }
+    html.push_str("</table>\n");
+
+    // Confidence histogram
+    let total: usize = summary.confidence_histogram.iter().sum();
+    if total > 0 {
+        html.push_str("<h3>Thought Confidence</h3>\n");
+        html.push_str("<table class=\"histogram\">\n");
+        for (label, count) in summary.confidence_rows() {
+            html.push_str(&format!(
+                "<tr><th>{}</th><td><div class=\"bar\" style=\"width: {:.0}%\"></div></td><td>{}</td></tr>\n",
+                label,
+                count as f64 * 100.0 / total as f64,
+                count
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
     // Metadata
     html.push_str("<h3>Session Metadata</h3>\n");
     html.push_str("<ul>\n");
     html.push_str(&format!("<li><strong>CoCo Version:</strong> {}</li>\n", session.metadata.coco_version));
     html.push_str(&format!("<li><strong>Working Directory:</strong> {}</li>\n", session.metadata.working_directory));
     html.push_str(&format!("<li><strong>AI Provider:</strong> {}</li>\n", session.metadata.ai_provider));
+    if let Some(model) = &session.metadata.ai_model {
+        html.push_str(&format!("<li><strong>AI Model:</strong> {}</li>\n", model));
+    }
     html.push_str(&format!("<li><strong>Total Events:</strong> {}</li>\n", session.events.len()));
     html.push_str(&format!("<li><strong>File Changes:</strong> {}</li>\n", session.metadata.total_file_changes));
     html.push_str(&format!("<li><strong>AI Requests:</strong> {}</li>\n", session.metadata.total_ai_requests));
@@ -243,9 +631,10 @@ fn export_session_to_html(session: &Session, output_path: &str) -> Result<()> {
     for event in &session.events {
         let event_class = match event.event_type {
             EventType::FileChanged => "file-event",
-            EventType::AiRequest | EventType::AiResponse => "ai-event",
+            EventType::AiRequest | EventType::AiResponse | EventType::SuggestionsGenerated => "ai-event",
             EventType::UiAction => "ui-event",
             EventType::Error => "error-event",
+            EventType::Annotation => "annotation-event",
             _ => "other-event",
         };
 
@@ -267,13 +656,21 @@ fn export_session_to_html(session: &Session, output_path: &str) -> Result<()> {
         if let Some(ref file_path) = event.context.file_path {
             html.push_str(&format!(
                 "<div class=\"event-file\">{}</div>\n",
-                file_path
+                escape_html(file_path)
+            ));
+        }
+
+        // Notes are free text typed by the user, and event data may hold recorded source
+        if let Some(note) = event.data.get("note").and_then(|n| n.as_str()) {
+            html.push_str(&format!(
+                "<div class=\"event-note\">📌 {}</div>\n",
+                escape_html(note)
             ));
         }
 
         html.push_str(&format!(
             "<div class=\"event-data\">{}</div>\n",
-            serde_json::to_string_pretty(&event.data).unwrap_or_default()
+            escape_html(&serde_json::to_string_pretty(&event.data).unwrap_or_default())
         ));
 
         html.push_str("</div>\n");
@@ -286,6 +683,10 @@ fn export_session_to_html(session: &Session, output_path: &str) -> Result<()> {
     Ok(())
 }
 
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
 // CSS content for HTML reports
 const SESSION_REPORT_CSS: &str = r#"
 body {
@@ -328,6 +729,40 @@ h1, h2, h3 {
     border-left-color: #e74c3c;
 }
 
+.event.annotation-event {
+    border-left-color: #f1c40f;
+    background: #fffbea;
+}
+
+.analytics th {
+    text-align: left;
+    padding-right: 20px;
+}
+
+.histogram th {
+    text-align: right;
+    padding-right: 10px;
+    font-weight: normal;
+}
+
+.histogram td:nth-child(2) {
+    width: 300px;
+}
+
+.histogram .bar {
+    background: #9b59b6;
+    height: 14px;
+    min-width: 1px;
+    border-radius: 2px;
+}
+
+.event-note {
+    font-size: 15px;
+    font-weight: bold;
+    color: #2c3e50;
+    margin-bottom: 8px;
+}
+
 .event-time {
     font-size: 12px;
     color: #7f8c8d;
@@ -368,4 +803,316 @@ ul {
 li {
     margin-bottom: 8px;
 }
-"#;
\ No newline at end of file
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_active_duration_ms() {
+        let start = Utc::now();
+        let event = |minutes: i64, event_type: EventType| SessionEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: start + chrono::Duration::minutes(minutes),
+            event_type,
+            data: serde_json::Value::Null,
+            context: EventContext::default(),
+        };
+
+        let session = Session {
+            id: "test".to_string(),
+            started_at: start,
+            ended_at: None,
+            events: vec![
+                event(0, EventType::SessionStarted),
+                event(1, EventType::FileChanged),
+                event(61, EventType::FileChanged),    // an hour idle counts as five minutes
+                event(62, EventType::SessionEnded),
+                event(600, EventType::SessionStarted), // downtime before a resume is skipped
+                event(602, EventType::FileChanged),
+            ],
+            metadata: SessionMetadata {
+                coco_version: String::new(),
+                working_directory: String::new(),
+                user: None,
+                ai_provider: String::new(),
+                ai_model: None,
+                total_duration_ms: None,
+                active_duration_ms: None,
+                total_file_changes: 0,
+                total_ai_requests: 0,
+                files_analyzed: Vec::new(),
+            },
+        };
+
+        assert_eq!(session.active_duration_ms(), (1 + 5 + 1 + 2) * 60 * 1000);
+    }
+
+    #[test]
+    fn test_merge_sessions() {
+        let start = Utc::now();
+        let event = |minutes: i64, event_type: EventType| SessionEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: start + chrono::Duration::minutes(minutes),
+            event_type,
+            data: serde_json::Value::Null,
+            context: EventContext::default(),
+        };
+        let session = |id: &str, events: Vec<SessionEvent>, files: &[&str], changes: usize| Session {
+            id: id.to_string(),
+            started_at: events[0].timestamp,
+            ended_at: Some(events[events.len() - 1].timestamp),
+            events,
+            metadata: SessionMetadata {
+                coco_version: "2.0.0".to_string(),
+                working_directory: String::new(),
+                user: None,
+                ai_provider: String::new(),
+                ai_model: None,
+                total_duration_ms: None,
+                active_duration_ms: None,
+                total_file_changes: changes,
+                total_ai_requests: 1,
+                files_analyzed: files.iter().map(|f| f.to_string()).collect(),
+            },
+        };
+
+        // The later recording is passed first and overlaps the earlier one
+        let late = session("late", vec![event(10, EventType::SessionStarted), event(30, EventType::SessionEnded)], &["b.rs", "a.rs"], 2);
+        let early = session("early", vec![event(0, EventType::SessionStarted), event(20, EventType::SessionEnded)], &["a.rs"], 3);
+        let merged = merge_sessions(vec![late.clone(), early, late]);
+
+        assert!(merged.id != "late" && merged.id != "early");
+        assert_eq!(merged.started_at, start);
+        assert_eq!(merged.ended_at, Some(start + chrono::Duration::minutes(30)));
+        assert_eq!(merged.events.len(), 4);
+        assert!(merged.events.windows(2).all(|pair| pair[0].timestamp <= pair[1].timestamp));
+        assert_eq!(merged.metadata.files_analyzed, vec!["a.rs", "b.rs"]);
+        assert_eq!(merged.metadata.total_file_changes, 7);
+        assert_eq!(merged.metadata.total_ai_requests, 3);
+        assert_eq!(merged.metadata.total_duration_ms, Some(30 * 60 * 1000));
+    }
+
+    #[test]
+    fn test_confidence_histogram() {
+        let thought = |confidence: serde_json::Value| SessionEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            event_type: EventType::ThoughtGenerated,
+            data: serde_json::json!({ "confidence": confidence }),
+            context: EventContext::default(),
+        };
+
+        let session = Session {
+            id: "test".to_string(),
+            started_at: Utc::now(),
+            ended_at: None,
+            events: vec![
+                thought(serde_json::json!(0.1)),
+                thought(serde_json::json!(0.5)),
+                thought(serde_json::json!(0.95)),
+                thought(serde_json::json!(1.0)),
+                thought(serde_json::Value::Null),
+            ],
+            metadata: SessionMetadata {
+                coco_version: String::new(),
+                working_directory: String::new(),
+                user: None,
+                ai_provider: String::new(),
+                ai_model: None,
+                total_duration_ms: None,
+                active_duration_ms: None,
+                total_file_changes: 0,
+                total_ai_requests: 0,
+                files_analyzed: Vec::new(),
+            },
+        };
+
+        let summary = summarize(&session);
+        assert_eq!(summary.confidence_histogram, [1, 0, 1, 0, 2]);
+        assert_eq!(summary.confidence_rows()[1], ("20–40%".to_string(), 0));
+    }
+
+    #[test]
+    fn test_annotation_event_serde() {
+        // A session written before annotations and model tracking existed
+        let old = r#"{
+            "id": "old",
+            "started_at": "2024-01-01T00:00:00Z",
+            "ended_at": null,
+            "events": [{
+                "id": "e1",
+                "timestamp": "2024-01-01T00:00:01Z",
+                "event_type": "FileChanged",
+                "data": {"path": "src/main.rs"},
+                "context": {"file_path": null, "line_number": null, "user_action": null, "duration_ms": null, "metadata": {}}
+            }],
+            "metadata": {
+                "coco_version": "2.0.0",
+                "working_directory": "/repo",
+                "user": null,
+                "ai_provider": "anthropic",
+                "total_duration_ms": null,
+                "total_file_changes": 1,
+                "total_ai_requests": 0,
+                "files_analyzed": []
+            }
+        }"#;
+        let session: Session = serde_json::from_str(old).unwrap();
+        assert_eq!(session.events[0].event_type, EventType::FileChanged);
+        assert_eq!(session.metadata.ai_model, None);
+
+        let json = serde_json::to_string(&EventType::Annotation).unwrap();
+        assert_eq!(json, "\"Annotation\"");
+        assert_eq!(serde_json::from_str::<EventType>(&json).unwrap(), EventType::Annotation);
+        assert_eq!("annotation".parse::<EventType>(), Ok(EventType::Annotation));
+    }
+
+    #[test]
+    fn test_analytics_rows() {
+        let event = |event_type: EventType, data: serde_json::Value, duration_ms: Option<u64>| SessionEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            event_type,
+            data,
+            context: EventContext { duration_ms, ..Default::default() },
+        };
+
+        let session = Session {
+            id: "test".to_string(),
+            started_at: Utc::now(),
+            ended_at: None,
+            events: vec![
+                event(EventType::AiRequest, serde_json::Value::Null, None),
+                event(EventType::AiRequest, serde_json::Value::Null, None),
+                event(EventType::AiResponse, serde_json::json!({ "success": true }), Some(300)),
+                event(EventType::AiResponse, serde_json::json!({ "success": false }), Some(100)),
+                event(EventType::Error, serde_json::Value::Null, None),
+            ],
+            metadata: SessionMetadata {
+                coco_version: String::new(),
+                working_directory: String::new(),
+                user: None,
+                ai_provider: String::new(),
+                ai_model: None,
+                total_duration_ms: None,
+                active_duration_ms: None,
+                total_file_changes: 0,
+                total_ai_requests: 2,
+                files_analyzed: vec!["src/main.rs".to_string()],
+            },
+        };
+
+        let rows = analytics_rows(&summarize(&session));
+        assert_eq!(rows, vec![
+            ("AI Success Rate", "50.0% (1 of 2)".to_string()),
+            ("Average AI Response Time", "200 ms".to_string()),
+            ("Unique Files", "1".to_string()),
+            ("Errors", "1".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_event_matches() {
+        let event = |data: serde_json::Value, file_path: Option<&str>| SessionEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            event_type: EventType::ThoughtGenerated,
+            data,
+            context: EventContext { file_path: file_path.map(str::to_string), ..Default::default() },
+        };
+
+        let thought = event(serde_json::json!({ "content": "Possible SQL injection" }), None);
+        assert!(event_matches(&thought, "sql injection"));
+        assert!(!event_matches(&thought, "unwrap"));
+
+        let change = event(serde_json::json!({ "path": "src/Db.rs" }), None);
+        assert!(event_matches(&change, "db.rs"));
+        assert_eq!(event_file_path(&change).as_deref(), Some("src/Db.rs"));
+
+        let in_context = event(serde_json::Value::Null, Some("src/auth.rs"));
+        assert!(event_matches(&in_context, "auth"));
+    }
+
+    #[test]
+    fn test_export_options() {
+        let start = Utc::now();
+        let event = |seconds: i64, event_type: EventType| SessionEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: start + chrono::Duration::seconds(seconds),
+            event_type,
+            data: serde_json::Value::Null,
+            context: EventContext::default(),
+        };
+
+        let session = Session {
+            id: "test".to_string(),
+            started_at: start,
+            ended_at: None,
+            events: vec![
+                event(0, EventType::AiRequest),
+                event(10, EventType::UiAction),
+                event(20, EventType::AiResponse),
+                event(30, EventType::AiRequest),
+            ],
+            metadata: SessionMetadata {
+                coco_version: String::new(),
+                working_directory: String::new(),
+                user: None,
+                ai_provider: String::new(),
+                ai_model: None,
+                total_duration_ms: None,
+                active_duration_ms: None,
+                total_file_changes: 0,
+                total_ai_requests: 2,
+                files_analyzed: Vec::new(),
+            },
+        };
+
+        assert_eq!(ExportOptions::default().apply(session.clone()).events.len(), 4);
+
+        let options = ExportOptions {
+            only_types: Some(vec![EventType::AiRequest, EventType::AiResponse]),
+            from: Some(start + chrono::Duration::seconds(5)),
+            to: Some(start + chrono::Duration::seconds(25)),
+        };
+        let types: Vec<EventType> = options.apply(session).events.into_iter().map(|e| e.event_type).collect();
+        assert_eq!(types, vec![EventType::AiResponse]);
+    }
+
+    #[test]
+    fn test_html_export_escapes_event_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("session.html");
+        let session = Session {
+            id: "test".to_string(),
+            started_at: Utc::now(),
+            ended_at: None,
+            events: vec![SessionEvent {
+                id: "e".to_string(),
+                timestamp: Utc::now(),
+                event_type: EventType::FileChanged,
+                data: serde_json::json!({ "content": "</div><script>alert(1)</script>" }),
+                context: EventContext::default(),
+            }],
+            metadata: SessionMetadata {
+                coco_version: String::new(),
+                working_directory: String::new(),
+                user: None,
+                ai_provider: String::new(),
+                ai_model: None,
+                total_duration_ms: None,
+                active_duration_ms: None,
+                total_file_changes: 1,
+                total_ai_requests: 0,
+                files_analyzed: Vec::new(),
+            },
+        };
+
+        export_session_to_html(&session, output.to_str().unwrap()).unwrap();
+        let html = std::fs::read_to_string(output).unwrap();
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;/div&gt;&lt;script&gt;"));
+    }
+}
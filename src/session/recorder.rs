@@ -1,9 +1,12 @@
-use anyhow::Result;
+use crate::error::{CocoError, Result};
 use chrono::Utc;
 use serde_json::json;
 use std::path::PathBuf;
 use tokio::fs;
 
+use crate::app::{Suggestion, Thought};
+use crate::config::{Config, RetentionPolicy};
+use crate::edits::PendingEdit;
 use super::{Session, SessionEvent, SessionMetadata, EventType, EventContext};
 
 pub struct SessionRecorder {
@@ -12,10 +15,11 @@ pub struct SessionRecorder {
     auto_save_interval: usize,
     events_since_save: usize,
     max_events: usize,
+    retention: RetentionPolicy,
 }
 
 impl SessionRecorder {
-    pub fn new() -> Result<Self> {
+    pub fn new(config: &Config) -> Result<Self> {
         let id = uuid::Uuid::new_v4().to_string();
         let started_at = Utc::now();
 
@@ -24,17 +28,14 @@ impl SessionRecorder {
             .map(|p| p.to_string_lossy().to_string())
             .unwrap_or_else(|_| "unknown".to_string());
 
-        // Get current user
-        let user = std::env::var("USER")
-            .or_else(|_| std::env::var("USERNAME"))
-            .ok();
-
         let metadata = SessionMetadata {
             coco_version: env!("CARGO_PKG_VERSION").to_string(),
             working_directory,
-            user,
-            ai_provider: "anthropic".to_string(), // TODO: Get from config
+            user: config.session_user(),
+            ai_provider: config.ai_provider_name().to_string(),
+            ai_model: Some(config.ai_model()),
             total_duration_ms: None,
+            active_duration_ms: None,
             total_file_changes: 0,
             total_ai_requests: 0,
             files_analyzed: Vec::new(),
@@ -52,13 +53,7 @@ impl SessionRecorder {
         let sessions_dir = super::get_sessions_directory()?;
         let file_path = sessions_dir.join(format!("{}.json", id));
 
-        let mut recorder = Self {
-            session,
-            file_path,
-            auto_save_interval: 10, // Save every 10 events
-            events_since_save: 0,
-            max_events: 10000, // Limit session size
-        };
+        let mut recorder = Self::with_session(session, file_path);
 
         // Record session start event
         recorder.record_event_internal(
@@ -77,6 +72,45 @@ impl SessionRecorder {
         Ok(recorder)
     }
 
+    /// Reopens a recorded session, e.g. after a crash, so new events are appended to it
+    pub fn resume(id: &str) -> Result<Self> {
+        let mut session = super::load_session(id)
+            .map_err(|e| CocoError::SessionLoad { id: id.to_string(), source: Box::new(e) })?;
+
+        let previously_ended_at = session.ended_at.take();
+        let file_path = super::get_session_path(&session.id)?;
+
+        let mut recorder = Self::with_session(session, file_path);
+
+        let resumed_at = Utc::now();
+        recorder.record_event_internal(
+            EventType::SessionStarted,
+            json!({
+                "session_id": id,
+                "started_at": resumed_at,
+                "resumed": true,
+                "previously_ended_at": previously_ended_at
+            }),
+            EventContext::default(),
+        );
+
+        recorder.save()?;
+
+        tracing::info!("Resumed recording session: {} ({} events so far)", id, recorder.session.events.len());
+        Ok(recorder)
+    }
+
+    fn with_session(session: Session, file_path: PathBuf) -> Self {
+        Self {
+            session,
+            file_path,
+            auto_save_interval: 10, // Save every 10 events
+            events_since_save: 0,
+            max_events: 10000, // Limit session size
+            retention: RetentionPolicy::default(),
+        }
+    }
+
     pub fn record_event(&mut self, event_type: EventType, data: serde_json::Value) {
         self.record_event_with_context(event_type, data, EventContext::default());
     }
@@ -106,11 +140,7 @@ impl SessionRecorder {
     ) {
         // Check if we've hit the max events limit
         if self.session.events.len() >= self.max_events {
-            tracing::warn!("Session has reached maximum events limit ({}), rotating events", self.max_events);
-
-            // Keep only the last 80% of events
-            let keep_count = (self.max_events as f32 * 0.8) as usize;
-            self.session.events.drain(0..self.session.events.len() - keep_count);
+            self.rotate_events();
         }
 
         let event = SessionEvent {
@@ -137,7 +167,40 @@ impl SessionRecorder {
         tracing::debug!("Recorded event: {:?}", event_type);
     }
 
-    pub fn record_file_change(&mut self, file_path: &str, content_size: usize) {
+    /// Drops the events the retention policy doesn't keep
+    fn rotate_events(&mut self) {
+        let events = &mut self.session.events;
+        let before = events.len();
+
+        match &self.retention {
+            RetentionPolicy::KeepRecent(count) => {
+                let keep_count = (*count).min(self.max_events * 4 / 5).min(before);
+                events.drain(0..before - keep_count);
+            }
+            RetentionPolicy::KeepByType(types) => events.retain(|event| types.contains(&event.event_type)),
+            RetentionPolicy::KeepAll => {}
+        }
+
+        let dropped = before - events.len();
+        if dropped > 0 {
+            tracing::warn!(
+                "Session has reached maximum events limit ({}), dropped {} events",
+                self.max_events,
+                dropped
+            );
+        }
+    }
+
+    /// Records a saved file. `local_findings` also stores the content, what the local
+    /// analyzer found in it and the `max_line_length` it ran with, so `replay --verify`
+    /// can re-run the checks.
+    pub fn record_file_change(
+        &mut self,
+        file_path: &str,
+        content: &str,
+        local_findings: Option<serde_json::Value>,
+        max_line_length: Option<usize>,
+    ) {
         let mut context = EventContext::default();
         context.file_path = Some(file_path.to_string());
 
@@ -146,15 +209,18 @@ impl SessionRecorder {
             self.session.metadata.files_analyzed.push(file_path.to_string());
         }
 
-        self.record_event_with_context(
-            EventType::FileChanged,
-            json!({
-                "file_path": file_path,
-                "content_size": content_size,
-                "unique_files_count": self.session.metadata.files_analyzed.len()
-            }),
-            context,
-        );
+        let mut data = json!({
+            "file_path": file_path,
+            "content_size": content.len(),
+            "unique_files_count": self.session.metadata.files_analyzed.len()
+        });
+        if let Some(findings) = local_findings {
+            data["content"] = json!(content);
+            data["local_findings"] = findings;
+            data["max_line_length"] = json!(max_line_length);
+        }
+
+        self.record_event_with_context(EventType::FileChanged, data, context);
     }
 
     pub fn record_ai_request(&mut self, request_id: &str, request_type: &str, file_path: Option<&str>) {
@@ -241,33 +307,99 @@ impl SessionRecorder {
         );
     }
 
-    pub fn record_suggestion_action(
-        &mut self,
-        suggestion_id: &str,
-        action: &str, // "accepted" or "rejected"
-        file_path: Option<&str>,
-    ) {
-        let mut context = EventContext::default();
-        context.file_path = file_path.map(|s| s.to_string());
-        context.user_action = Some(action.to_string());
+    /// Records a `ThoughtGenerated` event for each thought
+    pub fn record_thoughts(&mut self, thoughts: &[Thought]) {
+        for thought in thoughts {
+            self.record_thought_generated(
+                &thought.id,
+                &format!("{:?}", thought.thought_type),
+                thought.confidence,
+                thought.file_path.as_deref(),
+            );
+        }
+    }
 
-        let event_type = match action {
-            "accepted" => EventType::SuggestionAccepted,
-            "rejected" => EventType::SuggestionRejected,
-            _ => EventType::UiAction,
+    /// Records a thought the user dismissed, as a rejection of the whole thought
+    pub fn record_thought_dismissed(&mut self, thought_id: &str, file_path: Option<&str>) {
+        let context = EventContext {
+            file_path: file_path.map(|s| s.to_string()),
+            user_action: Some("dismissed".to_string()),
+            ..Default::default()
         };
 
         self.record_event_with_context(
-            event_type,
+            EventType::SuggestionRejected,
             json!({
-                "suggestion_id": suggestion_id,
-                "action": action,
+                "thought_id": thought_id,
+                "action": "dismissed",
                 "file_path": file_path
             }),
             context,
         );
     }
 
+    /// Records a note the user attached to the current moment, e.g. "bug found here"
+    pub fn record_annotation(&mut self, note: &str, file_path: Option<&str>) {
+        let context = EventContext {
+            file_path: file_path.map(|s| s.to_string()),
+            ..Default::default()
+        };
+
+        self.record_event_with_context(
+            EventType::Annotation,
+            json!({
+                "note": note,
+                "file_path": file_path
+            }),
+            context,
+        );
+    }
+
+    /// Records an applied suggestion with its diff, and with the file before and after
+    /// when `include_content` is set, so replay can show what the edit did. `dry_run`
+    /// marks a suggestion that was accepted but not written.
+    pub fn record_applied_edit(&mut self, edit: &PendingEdit, include_content: bool, dry_run: bool) {
+        let context = EventContext {
+            file_path: Some(edit.file_path.clone()),
+            user_action: Some("accepted".to_string()),
+            ..Default::default()
+        };
+
+        let mut data = json!({
+            "suggestion_id": edit.suggestion_id,
+            "action": "accepted",
+            "file_path": edit.file_path,
+            "title": edit.title,
+            "diff": edit.diff,
+            "dry_run": dry_run
+        });
+        if include_content {
+            data["before"] = json!(edit.original);
+            data["after"] = json!(edit.updated);
+        }
+
+        self.record_event_with_context(EventType::SuggestionAccepted, data, context);
+    }
+
+    /// Records the improvement ideas returned for a suggestions request
+    pub fn record_suggestions(&mut self, request_id: &str, suggestions: &[Suggestion], file_path: Option<&str>) {
+        let context = EventContext {
+            file_path: file_path.map(|s| s.to_string()),
+            ..Default::default()
+        };
+
+        self.record_event_with_context(
+            EventType::SuggestionsGenerated,
+            json!({
+                "request_id": request_id,
+                "file_path": file_path,
+                "count": suggestions.len(),
+                "titles": suggestions.iter().map(|s| s.title.as_str()).collect::<Vec<_>>()
+            }),
+            context,
+        );
+    }
+
     pub fn save(&mut self) -> Result<()> {
         // Update session duration
         if let Some(first_event) = self.session.events.first() {
@@ -279,6 +411,7 @@ impl SessionRecorder {
                 self.session.metadata.total_duration_ms = Some(duration as u64);
             }
         }
+        self.session.metadata.active_duration_ms = Some(self.session.active_duration_ms());
 
         let json = serde_json::to_string_pretty(&self.session)?;
         std::fs::write(&self.file_path, json)?;
@@ -299,6 +432,7 @@ impl SessionRecorder {
                 self.session.metadata.total_duration_ms = Some(duration as u64);
             }
         }
+        self.session.metadata.active_duration_ms = Some(self.session.active_duration_ms());
 
         let json = serde_json::to_string_pretty(&self.session)?;
         fs::write(&self.file_path, json).await?;
@@ -357,6 +491,10 @@ impl SessionRecorder {
         self.max_events = max;
     }
 
+    pub fn set_retention_policy(&mut self, retention: RetentionPolicy) {
+        self.retention = retention;
+    }
+
     pub fn get_session_stats(&self) -> SessionStats {
         let mut stats = SessionStats::default();
 
@@ -435,4 +573,100 @@ impl Drop for SessionRecorder {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::ThoughtType;
+    use tempfile::TempDir;
+
+    fn thought(thought_type: ThoughtType) -> Thought {
+        Thought::new(thought_type, String::new(), Some("src/lib.rs".to_string()), None, 0.7)
+    }
+
+    fn empty_session() -> Session {
+        Session {
+            id: "test".to_string(),
+            started_at: Utc::now(),
+            ended_at: None,
+            events: Vec::new(),
+            metadata: SessionMetadata {
+                coco_version: String::new(),
+                working_directory: String::new(),
+                user: None,
+                ai_provider: String::new(),
+                ai_model: None,
+                total_duration_ms: None,
+                active_duration_ms: None,
+                total_file_changes: 0,
+                total_ai_requests: 0,
+                files_analyzed: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_record_thoughts_counts() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut recorder = SessionRecorder::with_session(empty_session(), temp_dir.path().join("test.json"));
+
+        let thoughts = vec![thought(ThoughtType::Warning), thought(ThoughtType::Style), thought(ThoughtType::Complete)];
+        recorder.record_thoughts(&thoughts);
+
+        let stats = recorder.get_session_stats();
+        assert_eq!(stats.thoughts_generated, thoughts.len());
+
+        let event = &recorder.session.events[0];
+        assert_eq!(event.event_type, EventType::ThoughtGenerated);
+        assert_eq!(event.data["thought_id"], thoughts[0].id);
+        assert_eq!(event.data["thought_type"], "Warning");
+    }
+
+    #[test]
+    fn test_record_file_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut recorder = SessionRecorder::with_session(empty_session(), temp_dir.path().join("test.json"));
+
+        recorder.record_file_change("src/lib.rs", "fn main() {}", None, None);
+        recorder.record_file_change("src/lib.rs", "fn main() { }", Some(json!([])), Some(100));
+
+        let metadata = &recorder.session.metadata;
+        assert_eq!(metadata.total_file_changes, 2);
+        assert_eq!(metadata.files_analyzed, vec!["src/lib.rs"]);
+
+        let events = &recorder.session.events;
+        assert_eq!(events[0].data["content_size"], 12);
+        assert_eq!(events[0].context.file_path.as_deref(), Some("src/lib.rs"));
+        assert!(events[0].data.get("content").is_none());
+        assert_eq!(events[1].data["content"], "fn main() { }");
+        assert_eq!(events[1].data["max_line_length"], 100);
+    }
+
+    #[test]
+    fn test_retention_policies() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let record = |retention: RetentionPolicy| {
+            let mut recorder = SessionRecorder::with_session(empty_session(), temp_dir.path().join("test.json"));
+            recorder.set_max_events(100);
+            recorder.set_retention_policy(retention);
+            for i in 0..150 {
+                let event_type = if i % 3 == 0 { EventType::AiResponse } else { EventType::UiAction };
+                recorder.record_event_internal(event_type, json!({ "index": i }), EventContext::default());
+            }
+            recorder.session.events.clone()
+        };
+
+        let recent = record(RetentionPolicy::KeepRecent(50));
+        // Rotated down to 50 at the 101st event, then filled back up to the limit
+        assert_eq!(recent.first().unwrap().data["index"], 50);
+        assert_eq!(recent.len(), 100);
+
+        let by_type = record(RetentionPolicy::KeepByType(vec![EventType::AiResponse]));
+        assert_eq!(by_type.iter().filter(|e| e.event_type == EventType::AiResponse).count(), 50);
+        assert!(by_type.len() < 100);
+
+        assert_eq!(record(RetentionPolicy::KeepAll).len(), 150);
+    }
+}
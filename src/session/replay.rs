@@ -1,4 +1,4 @@
-use anyhow::Result;
+use crate::error::Result;
 use chrono::{DateTime, Utc};
 use std::time::Duration;
 use tokio::time::{sleep, Instant};
@@ -10,7 +10,6 @@ pub struct SessionPlayer {
     options: PlaybackOptions,
     current_event_index: usize,
     playback_start_time: Option<Instant>,
-    session_start_time: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone)]
@@ -24,6 +23,47 @@ pub struct PlaybackOptions {
     pub filter_file_path: Option<String>,
     pub start_from_event: Option<usize>,
     pub end_at_event: Option<usize>,
+    /// Hides events carrying a `confidence` below this (0.0-1.0); events without one are kept
+    pub min_confidence: Option<f32>,
+}
+
+impl PlaybackOptions {
+    /// Whether `event` passes the skip, only, file and confidence filters. Shared with
+    /// exports so both select events the same way.
+    pub fn includes(&self, event: &SessionEvent) -> bool {
+        // Check skip list
+        if self.skip_events.contains(&event.event_type) {
+            return false;
+        }
+
+        // Check only list
+        if let Some(ref only_events) = self.only_events {
+            if !only_events.contains(&event.event_type) {
+                return false;
+            }
+        }
+
+        // Check file filter
+        if let Some(ref filter_path) = self.filter_file_path {
+            if let Some(ref event_path) = event.context.file_path {
+                if !event_path.contains(filter_path) {
+                    return false;
+                }
+            } else {
+                return false;
+            }
+        }
+
+        // Check confidence filter
+        if let Some(min_confidence) = self.min_confidence {
+            let confidence = event.data.get("confidence").and_then(|c| c.as_f64());
+            if confidence.is_some_and(|c| c < min_confidence as f64) {
+                return false;
+            }
+        }
+
+        true
+    }
 }
 
 impl Default for PlaybackOptions {
@@ -38,6 +78,7 @@ impl Default for PlaybackOptions {
             filter_file_path: None,
             start_from_event: None,
             end_at_event: None,
+            min_confidence: None,
         }
     }
 }
@@ -49,7 +90,6 @@ impl SessionPlayer {
             options: PlaybackOptions::default(),
             current_event_index: 0,
             playback_start_time: None,
-            session_start_time: None,
         }
     }
 
@@ -91,11 +131,6 @@ impl SessionPlayer {
             return Ok(());
         }
 
-        // Set starting point
-        if let Some(start_index) = self.options.start_from_event {
-            self.current_event_index = start_index.min(self.session.events.len());
-        }
-
         // Filter events if needed
         let events_to_play = self.filter_events();
 
@@ -104,7 +139,13 @@ impl SessionPlayer {
             return Ok(());
         }
 
-        println!("\n🎬 Starting playback of {} events...\n", events_to_play.len());
+        // Set starting point (indices refer to the filtered events)
+        if let Some(start_index) = self.options.start_from_event {
+            self.current_event_index = start_index.min(events_to_play.len());
+        }
+
+        println!("\n🎬 Starting playback of {} events...", events_to_play.len());
+        self.print_legend(&events_to_play);
 
         if self.options.interactive {
             println!("Interactive mode: Press Enter to continue to next event, 'q' to quit");
@@ -114,16 +155,18 @@ impl SessionPlayer {
         let session_start = self.session.started_at;
 
         self.playback_start_time = Some(playback_start);
-        self.session_start_time = Some(session_start);
 
-        for (index, event) in events_to_play.iter().enumerate() {
+        let mut previous_timestamp = session_start;
+
+        for (index, event) in events_to_play.iter().enumerate().skip(self.current_event_index) {
             if let Some(end_index) = self.options.end_at_event {
                 if index >= end_index {
                     break;
                 }
             }
 
-            self.play_event(event, index).await?;
+            self.play_event(event, index, previous_timestamp).await?;
+            previous_timestamp = event.timestamp;
 
             if self.options.interactive {
                 if self.wait_for_user_input().await? {
@@ -154,6 +197,10 @@ impl SessionPlayer {
         println!("Files Analyzed: {}", self.session.metadata.files_analyzed.len());
         println!("Working Directory: {}", self.session.metadata.working_directory);
         println!("CoCo Version: {}", self.session.metadata.coco_version);
+        match &self.session.metadata.ai_model {
+            Some(model) => println!("AI Provider: {} ({})", self.session.metadata.ai_provider, model),
+            None => println!("AI Provider: {}", self.session.metadata.ai_provider),
+        }
 
         if self.options.speed_multiplier != 1.0 {
             println!("Playback Speed: {}x", self.options.speed_multiplier);
@@ -164,47 +211,20 @@ impl SessionPlayer {
         self.session
             .events
             .iter()
-            .filter(|event| {
-                // Check skip list
-                if self.options.skip_events.contains(&event.event_type) {
-                    return false;
-                }
-
-                // Check only list
-                if let Some(ref only_events) = self.options.only_events {
-                    if !only_events.contains(&event.event_type) {
-                        return false;
-                    }
-                }
-
-                // Check file filter
-                if let Some(ref filter_path) = self.options.filter_file_path {
-                    if let Some(ref event_path) = event.context.file_path {
-                        if !event_path.contains(filter_path) {
-                            return false;
-                        }
-                    } else {
-                        return false;
-                    }
-                }
-
-                true
-            })
+            .filter(|event| self.options.includes(event))
             .cloned()
             .collect()
     }
 
-    async fn play_event(&self, event: &SessionEvent, index: usize) -> Result<()> {
-        // Calculate timing delay
-        if let Some(session_start) = self.session_start_time {
-            let event_offset = event.timestamp.signed_duration_since(session_start);
-            let target_delay_ms = (event_offset.num_milliseconds() as f64 / self.options.speed_multiplier) as u64;
+    async fn play_event(&self, event: &SessionEvent, index: usize, previous_timestamp: DateTime<Utc>) -> Result<()> {
+        // Wait for the gap since the previous event, scaled by playback speed
+        let event_gap = event.timestamp.signed_duration_since(previous_timestamp);
+        let target_delay_ms = (event_gap.num_milliseconds().max(0) as f64 / self.options.speed_multiplier) as u64;
 
-            if let Some(max_delay) = self.options.max_delay_ms {
-                let actual_delay = target_delay_ms.min(max_delay);
-                if actual_delay > 0 && !self.options.interactive {
-                    sleep(Duration::from_millis(actual_delay)).await;
-                }
+        if let Some(max_delay) = self.options.max_delay_ms {
+            let actual_delay = target_delay_ms.min(max_delay);
+            if actual_delay > 0 && !self.options.interactive {
+                sleep(Duration::from_millis(actual_delay)).await;
             }
         }
 
@@ -240,7 +260,8 @@ impl SessionPlayer {
         // Display event data for important events
         match event.event_type {
             EventType::FileChanged => {
-                if let Some(size) = event.data.get("content_size") {
+                // Sessions recorded before the keys were unified used `size`
+                if let Some(size) = event.data.get("content_size").or_else(|| event.data.get("size")) {
                     println!("  📄 File size: {} bytes", size);
                 }
             }
@@ -271,9 +292,38 @@ impl SessionPlayer {
                     println!("  📊 Confidence: {:.1}%", confidence.as_f64().unwrap_or(0.0) * 100.0);
                 }
             }
+            EventType::Annotation => {
+                if let Some(note) = event.data.get("note").and_then(|n| n.as_str()) {
+                    println!("  📌 {}", note);
+                }
+            }
+            EventType::SuggestionsGenerated => {
+                if let Some(titles) = event.data.get("titles").and_then(|t| t.as_array()) {
+                    for title in titles.iter().filter_map(|t| t.as_str()) {
+                        println!("  💡 {}", title);
+                    }
+                }
+            }
             EventType::SuggestionAccepted | EventType::SuggestionRejected => {
-                let action = if matches!(event.event_type, EventType::SuggestionAccepted) { "accepted" } else { "rejected" };
-                println!("  👤 User {} suggestion", action);
+                if event.data.get("action").and_then(|a| a.as_str()) == Some("dismissed") {
+                    println!("  👤 User dismissed thought");
+                } else {
+                    let dry_run = event.data.get("dry_run").and_then(|d| d.as_bool()) == Some(true);
+                    let action = match event.event_type {
+                        EventType::SuggestionAccepted if dry_run => "accepted (dry run, not written)",
+                        EventType::SuggestionAccepted => "accepted",
+                        _ => "rejected",
+                    };
+                    match event.data.get("title").and_then(|t| t.as_str()) {
+                        Some(title) => println!("  👤 User {} suggestion: {}", action, title),
+                        None => println!("  👤 User {} suggestion", action),
+                    }
+                }
+                if let Some(diff) = Self::applied_diff(event) {
+                    for line in diff.lines() {
+                        println!("    {}", line);
+                    }
+                }
             }
             _ => {}
         }
@@ -284,6 +334,30 @@ impl SessionPlayer {
         }
     }
 
+    /// The change an accepted suggestion made: its recorded diff, or one rebuilt from
+    /// the recorded file contents
+    fn applied_diff(event: &SessionEvent) -> Option<String> {
+        let data = &event.data;
+        if let Some(diff) = data.get("diff").and_then(|d| d.as_str()) {
+            return Some(diff.to_string());
+        }
+        let file_path = data.get("file_path").and_then(|p| p.as_str()).unwrap_or("file");
+        let before = data.get("before").and_then(|c| c.as_str())?;
+        let after = data.get("after").and_then(|c| c.as_str())?;
+        Some(crate::edits::unified_diff(file_path, before, after))
+    }
+
+    /// One line mapping icons to the event types that will actually be shown
+    fn print_legend(&self, events: &[SessionEvent]) {
+        let legend: Vec<String> = EventType::ALL
+            .iter()
+            .filter(|event_type| events.iter().any(|e| &e.event_type == *event_type))
+            .map(|event_type| format!("{} {:?}", self.get_event_icon(event_type), event_type))
+            .collect();
+
+        println!("Legend: {}\n", legend.join("  "));
+    }
+
     fn get_event_icon(&self, event_type: &EventType) -> &'static str {
         match event_type {
             EventType::SessionStarted => "🚀",
@@ -296,7 +370,9 @@ impl SessionPlayer {
             EventType::ConfigChange => "⚙️",
             EventType::ThoughtGenerated => "💡",
             EventType::SuggestionAccepted => "✅",
-            EventType::SuggestionRejected => "❌",
+            EventType::SuggestionRejected => "🚫",
+            EventType::Annotation => "📌",
+            EventType::SuggestionsGenerated => "🧰",
         }
     }
 
@@ -345,62 +421,7 @@ impl SessionPlayer {
 
     // Export functionality
     pub fn export_summary(&self) -> SessionSummary {
-        let mut file_changes = 0;
-        let mut ai_requests = 0;
-        let mut ai_responses = 0;
-        let mut ui_actions = 0;
-        let mut errors = 0;
-        let mut total_ai_duration = 0u64;
-        let mut successful_ai_requests = 0;
-
-        for event in &self.session.events {
-            match event.event_type {
-                EventType::FileChanged => file_changes += 1,
-                EventType::AiRequest => ai_requests += 1,
-                EventType::AiResponse => {
-                    ai_responses += 1;
-                    if let Some(duration) = event.context.duration_ms {
-                        total_ai_duration += duration;
-                    }
-                    if event.data.get("success").and_then(|v| v.as_bool()).unwrap_or(false) {
-                        successful_ai_requests += 1;
-                    }
-                }
-                EventType::UiAction => ui_actions += 1,
-                EventType::Error => errors += 1,
-                _ => {}
-            }
-        }
-
-        let duration = self.session.ended_at
-            .map(|end| end.signed_duration_since(self.session.started_at))
-            .map(|d| d.num_milliseconds() as u64);
-
-        SessionSummary {
-            session_id: self.session.id.clone(),
-            started_at: self.session.started_at,
-            ended_at: self.session.ended_at,
-            duration_ms: duration,
-            total_events: self.session.events.len(),
-            file_changes,
-            ai_requests,
-            ai_responses,
-            ui_actions,
-            errors,
-            successful_ai_requests,
-            ai_success_rate: if ai_requests > 0 {
-                successful_ai_requests as f64 / ai_requests as f64
-            } else {
-                0.0
-            },
-            average_ai_response_time: if ai_responses > 0 {
-                total_ai_duration / ai_responses as u64
-            } else {
-                0
-            },
-            unique_files: self.session.metadata.files_analyzed.len(),
-            files_analyzed: self.session.metadata.files_analyzed.clone(),
-        }
+        super::summarize(&self.session)
     }
 }
 
@@ -410,6 +431,8 @@ pub struct SessionSummary {
     pub started_at: DateTime<Utc>,
     pub ended_at: Option<DateTime<Utc>>,
     pub duration_ms: Option<u64>,
+    /// Time with recorded activity, excluding long idle stretches
+    pub active_duration_ms: u64,
     pub total_events: usize,
     pub file_changes: usize,
     pub ai_requests: usize,
@@ -421,9 +444,29 @@ pub struct SessionSummary {
     pub average_ai_response_time: u64,
     pub unique_files: usize,
     pub files_analyzed: Vec<String>,
+    /// Recorded thoughts by confidence, in 20% steps from 0–20% up to 80–100%
+    pub confidence_histogram: [usize; CONFIDENCE_BUCKETS],
+}
+
+/// Number of equal-width confidence ranges in `SessionSummary::confidence_histogram`
+pub const CONFIDENCE_BUCKETS: usize = 5;
+
+/// The histogram bucket for a confidence between 0 and 1; 100% falls in the top one
+pub fn confidence_bucket(confidence: f64) -> usize {
+    ((confidence.clamp(0.0, 1.0) * CONFIDENCE_BUCKETS as f64) as usize).min(CONFIDENCE_BUCKETS - 1)
 }
 
 impl SessionSummary {
+    /// Each histogram bucket's label, e.g. "20–40%", with its count of thoughts
+    pub fn confidence_rows(&self) -> Vec<(String, usize)> {
+        let step = 100 / CONFIDENCE_BUCKETS;
+        self.confidence_histogram
+            .iter()
+            .enumerate()
+            .map(|(i, count)| (format!("{}–{}%", i * step, (i + 1) * step), *count))
+            .collect()
+    }
+
     pub fn print(&self) {
         println!("Session Summary");
         println!("===============");
@@ -440,6 +483,9 @@ impl SessionSummary {
             println!("Duration: {}m {}s", minutes, seconds % 60);
         }
 
+        let active_seconds = self.active_duration_ms / 1000;
+        println!("Active: {}m {}s", active_seconds / 60, active_seconds % 60);
+
         println!("\nActivity:");
         println!("  Total Events: {}", self.total_events);
         println!("  File Changes: {}", self.file_changes);
@@ -452,6 +498,15 @@ impl SessionSummary {
         println!("  Success Rate: {:.1}%", self.ai_success_rate * 100.0);
         println!("  Average Response Time: {} ms", self.average_ai_response_time);
 
+        let largest = self.confidence_histogram.iter().copied().max().unwrap_or(0);
+        if largest > 0 {
+            println!("\nThought Confidence:");
+            for (label, count) in self.confidence_rows() {
+                let bar = "#".repeat((count * 30).div_ceil(largest));
+                println!("  {:>8} {:<30} {}", label, bar, count);
+            }
+        }
+
         println!("\nFiles:");
         println!("  Unique Files Analyzed: {}", self.unique_files);
 
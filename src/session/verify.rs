@@ -0,0 +1,188 @@
+use serde_json::json;
+
+use super::{EventType, Session};
+use crate::ai::analyzer::CodeAnalyzer;
+use crate::app::Thought;
+
+/// A recorded file change whose local findings no longer match what the analyzer produces
+#[derive(Debug, Clone)]
+pub struct Drift {
+    pub event_index: usize,
+    pub file_path: String,
+    /// Findings recorded at the time that the analyzer no longer reports
+    pub missing: Vec<String>,
+    /// Findings the analyzer reports now that were not recorded
+    pub unexpected: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub checked: usize,
+    /// File changes recorded without content, which can't be re-analyzed
+    pub skipped: usize,
+    pub drifts: Vec<Drift>,
+}
+
+/// Identifies a local finding independently of its generated id and wording,
+/// e.g. `unwrap_usage:12`
+pub fn finding_key(thought: &Thought) -> String {
+    let rule = thought
+        .rule_id
+        .clone()
+        .unwrap_or_else(|| format!("{:?}", thought.thought_type).to_lowercase());
+    match thought.line_number {
+        Some(line) => format!("{}:{}", rule, line),
+        None => rule,
+    }
+}
+
+/// Local findings for `content`, in the form stored in a `FileChanged` event
+pub fn local_findings(analyzer: &CodeAnalyzer, content: &str, file_path: &str) -> serde_json::Value {
+    let mut keys: Vec<String> = analyzer
+        .analyze_code_patterns(content, Some(file_path))
+        .iter()
+        .map(finding_key)
+        .collect();
+    keys.sort();
+    json!(keys)
+}
+
+/// Re-runs the local analyzer on every file change recorded with its content and
+/// compares the findings with the ones recorded alongside it
+pub fn verify(session: &Session) -> VerifyReport {
+    let mut analyzer = CodeAnalyzer::new();
+    let mut report = VerifyReport::default();
+
+    for (event_index, event) in session.events.iter().enumerate() {
+        if event.event_type != EventType::FileChanged {
+            continue;
+        }
+
+        let data = &event.data;
+        let file_path = data.get("file_path").or_else(|| data.get("path")).and_then(|p| p.as_str());
+        let content = data.get("content").and_then(|c| c.as_str());
+        let recorded = data.get("local_findings").and_then(|f| f.as_array());
+
+        let (Some(file_path), Some(content), Some(recorded)) = (file_path, content, recorded) else {
+            report.skipped += 1;
+            continue;
+        };
+
+        let mut recorded: Vec<String> = recorded
+            .iter()
+            .filter_map(|key| key.as_str().map(str::to_string))
+            .collect();
+        recorded.sort();
+
+        // Check under the line length limit the findings were recorded with
+        let max_line_length = data.get("max_line_length").and_then(|n| n.as_u64()).map(|n| n as usize);
+        analyzer.set_max_line_length(max_line_length);
+        let current: Vec<String> = serde_json::from_value(local_findings(&analyzer, content, file_path))
+            .unwrap_or_default();
+
+        report.checked += 1;
+        let missing = difference(&recorded, &current);
+        let unexpected = difference(&current, &recorded);
+        if !missing.is_empty() || !unexpected.is_empty() {
+            report.drifts.push(Drift {
+                event_index,
+                file_path: file_path.to_string(),
+                missing,
+                unexpected,
+            });
+        }
+    }
+
+    report
+}
+
+/// Items of sorted `left` not matched one-for-one in sorted `right`
+fn difference(left: &[String], right: &[String]) -> Vec<String> {
+    let mut remaining = right.to_vec();
+    left.iter()
+        .filter(|item| match remaining.iter().position(|other| other == *item) {
+            Some(index) => {
+                remaining.remove(index);
+                false
+            }
+            None => true,
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{EventContext, SessionEvent, SessionMetadata};
+    use chrono::Utc;
+
+    fn file_changed(data: serde_json::Value) -> SessionEvent {
+        SessionEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            event_type: EventType::FileChanged,
+            data,
+            context: EventContext::default(),
+        }
+    }
+
+    fn session(events: Vec<SessionEvent>) -> Session {
+        Session {
+            id: "s".to_string(),
+            started_at: Utc::now(),
+            ended_at: None,
+            events,
+            metadata: SessionMetadata {
+                coco_version: "2.0.0".to_string(),
+                working_directory: ".".to_string(),
+                user: None,
+                ai_provider: "anthropic".to_string(),
+                ai_model: None,
+                total_duration_ms: None,
+                active_duration_ms: None,
+                total_file_changes: 0,
+                total_ai_requests: 0,
+                files_analyzed: vec![],
+            },
+        }
+    }
+
+    #[test]
+    fn test_verify_reports_drift() {
+        let content = "fn main() {\n    let x = run().unwrap();\n}\n";
+        let findings = local_findings(&CodeAnalyzer::new(), content, "main.rs");
+
+        let session = session(vec![
+            file_changed(json!({ "path": "main.rs", "content": content, "local_findings": findings })),
+            file_changed(json!({ "path": "main.rs", "content": content, "local_findings": ["deep_nesting:1"] })),
+            file_changed(json!({ "path": "main.rs", "size": 10 })),
+        ]);
+
+        let report = verify(&session);
+        assert_eq!((report.checked, report.skipped), (2, 1));
+        assert_eq!(report.drifts.len(), 1);
+        assert_eq!(report.drifts[0].event_index, 1);
+        assert_eq!(report.drifts[0].missing, vec!["deep_nesting:1".to_string()]);
+        assert!(!report.drifts[0].unexpected.is_empty());
+    }
+
+    #[test]
+    fn test_verify_uses_recorded_max_line_length() {
+        let content = format!("fn main() {{\n    // {}\n}}\n", "x".repeat(150));
+        let mut analyzer = CodeAnalyzer::new();
+        analyzer.set_max_line_length(Some(200));
+        let findings = local_findings(&analyzer, &content, "main.rs");
+
+        let session = session(vec![file_changed(json!({
+            "path": "main.rs",
+            "content": content,
+            "local_findings": findings,
+            "max_line_length": 200
+        }))]);
+
+        let report = verify(&session);
+        assert_eq!(report.checked, 1);
+        assert!(report.drifts.is_empty());
+    }
+}
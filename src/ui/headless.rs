@@ -0,0 +1,62 @@
+use crate::error::Result;
+use std::collections::HashSet;
+use tokio::time::{interval, Duration};
+
+use crate::app::{App, Thought};
+use super::widgets;
+
+/// Stand-in for the TUI that prints each new thought to stdout as it arrives.
+/// Runs until Ctrl+C or until the app stops.
+pub async fn run(app: App) -> Result<()> {
+    tracing::info!("Starting headless thought stream");
+    println!("👀 CoCo is watching for changes (Ctrl+C to stop)");
+
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut ticker = interval(Duration::from_millis(200));
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = ticker.tick() => {}
+        }
+
+        let thoughts = app.get_thoughts().await;
+        for thought in thoughts.iter().filter(|t| !seen.contains(&t.id)) {
+            println!("{}", format_thought(thought));
+        }
+
+        // Older thoughts are trimmed from the front, so only current ids need remembering
+        seen = thoughts.into_iter().map(|t| t.id).collect();
+
+        if !app.is_running().await {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn format_thought(thought: &Thought) -> String {
+    let location = match (&thought.file_path, thought.line_number) {
+        (Some(path), Some(line)) => format!(" {}:{}", path, line),
+        (Some(path), None) => format!(" {}", path),
+        _ => String::new(),
+    };
+
+    let mut output = format!(
+        "[{}] {} {:?} ({:.0}%){}{} — {}",
+        thought.timestamp.format("%H:%M:%S"),
+        widgets::get_thought_icon(&thought.thought_type),
+        thought.thought_type,
+        thought.confidence * 100.0,
+        location,
+        if thought.in_recent_change { " ✎" } else { "" },
+        thought.content.trim().replace('\n', "\n    ")
+    );
+
+    for suggestion in &thought.suggestions {
+        output.push_str(&format!("\n    💡 {}: {}", suggestion.title, suggestion.description));
+    }
+
+    output
+}
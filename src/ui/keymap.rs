@@ -0,0 +1,259 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::app::UiEventType;
+
+/// What a key does in the main view, outside the pickers and prompts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    ToggleMode,
+    DismissThought,
+    Annotate,
+    RaiseCostLimit,
+    RequestSuggestions,
+    ExplainFile,
+    OpenRequestTypePicker,
+    OpenInEditor,
+    FixSelectedThought,
+    ToggleLineNumbers,
+    ToggleSyntaxHighlighting,
+    ToggleThoughtGrouping,
+    ToggleGroupCollapsed,
+    ToggleCompactThoughts,
+    ClearThoughts,
+    SelectFile,
+    OpenRecentFiles,
+    AcceptSuggestion,
+    RejectSuggestion,
+    UndoEdit,
+    ToggleDryRun,
+    PageUp,
+    PageDown,
+    SelectPreviousThought,
+    SelectNextThought,
+    MetaReview,
+    CycleModel,
+    ExportThoughts,
+    Refresh,
+    Help,
+}
+
+impl Action {
+    /// The line shown for this action in the help overlay
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::ToggleMode => "Toggle view mode",
+            Action::DismissThought => "Dismiss the selected thought",
+            Action::Annotate => "Annotate this moment of the recording",
+            Action::RaiseCostLimit => "Raise the session's AI cost limit by another COCO_MAX_SESSION_COST_USD",
+            Action::RequestSuggestions => "Ask for improvement ideas on the current file (s/Esc closes the list)",
+            Action::ExplainFile => "Ask for an explanation of the current file",
+            Action::OpenRequestTypePicker => "Re-run the selected thought's file as another request type",
+            Action::OpenInEditor => "Edit the current file in $EDITOR at the selected thought's line",
+            Action::FixSelectedThought => "Ask for a fix for the selected thought (preview it with y)",
+            Action::ToggleLineNumbers => "Toggle line numbers in the code panel",
+            Action::ToggleSyntaxHighlighting => "Toggle syntax highlighting in the code panel",
+            Action::ToggleThoughtGrouping => "Group thoughts by file",
+            Action::ToggleGroupCollapsed => "Fold or unfold the selected thought's file while grouped",
+            Action::ToggleCompactThoughts => "Merge similar consecutive thoughts into one (×N) row",
+            Action::ClearThoughts => "Clear thoughts",
+            Action::SelectFile => "Open a project file (analyzed on open with COCO_ANALYZE_ON_FOCUS)",
+            Action::OpenRecentFiles => "Jump back to a file opened or analyzed this session",
+            Action::AcceptSuggestion => "Preview suggestion as a diff (y/Enter applies, n/Esc cancels)",
+            Action::RejectSuggestion => "Reject suggestion",
+            Action::UndoEdit => "Undo the last applied suggestion",
+            Action::ToggleDryRun => "Toggle dry run: accepted suggestions are recorded, not written",
+            Action::PageUp => "Scroll the code panel up a page",
+            Action::PageDown => "Scroll the code panel down a page",
+            Action::SelectPreviousThought => "Select the previous thought and jump to its line",
+            Action::SelectNextThought => "Select the next thought and jump to its line",
+            Action::MetaReview => "Review recently analyzed files together",
+            Action::CycleModel => "Switch to the next model in COCO_AVAILABLE_MODELS and re-analyze",
+            Action::ExportThoughts => "Export thoughts to ~/.coco/exports (Markdown or JSON)",
+            Action::Refresh => "Re-analyze the current file",
+            Action::Help => "Show this help",
+        }
+    }
+
+    /// The event sent to the app for this action. Paging, opening the editor and
+    /// showing help need the terminal and are handled by the UI itself.
+    pub fn event_type(self) -> Option<UiEventType> {
+        let event_type = match self {
+            Action::Quit => UiEventType::Quit,
+            Action::ToggleMode => UiEventType::ToggleMode,
+            Action::DismissThought => UiEventType::DismissThought,
+            Action::Annotate => UiEventType::Annotate,
+            Action::RaiseCostLimit => UiEventType::RaiseCostLimit,
+            Action::RequestSuggestions => UiEventType::RequestSuggestions,
+            Action::ExplainFile => UiEventType::ExplainFile,
+            Action::OpenRequestTypePicker => UiEventType::OpenRequestTypePicker,
+            Action::FixSelectedThought => UiEventType::FixSelectedThought,
+            Action::ToggleLineNumbers => UiEventType::ToggleLineNumbers,
+            Action::ToggleSyntaxHighlighting => UiEventType::ToggleSyntaxHighlighting,
+            Action::ToggleThoughtGrouping => UiEventType::ToggleThoughtGrouping,
+            Action::ToggleGroupCollapsed => UiEventType::ToggleGroupCollapsed,
+            Action::ToggleCompactThoughts => UiEventType::ToggleCompactThoughts,
+            Action::ClearThoughts => UiEventType::ClearThoughts,
+            Action::SelectFile => UiEventType::SelectFile,
+            Action::OpenRecentFiles => UiEventType::OpenRecentFiles,
+            Action::AcceptSuggestion => UiEventType::AcceptSuggestion,
+            Action::RejectSuggestion => UiEventType::RejectSuggestion,
+            Action::UndoEdit => UiEventType::UndoEdit,
+            Action::ToggleDryRun => UiEventType::ToggleDryRun,
+            Action::SelectPreviousThought => UiEventType::SelectPreviousThought,
+            Action::SelectNextThought => UiEventType::SelectNextThought,
+            Action::MetaReview => UiEventType::MetaReview,
+            Action::CycleModel => UiEventType::CycleModel,
+            Action::ExportThoughts => UiEventType::ExportThoughts,
+            Action::Refresh => UiEventType::Refresh,
+            Action::Help => UiEventType::Help,
+            Action::OpenInEditor | Action::PageUp | Action::PageDown => return None,
+        };
+        Some(event_type)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    /// Modifiers that must be held; others (e.g. Shift for capitals) are ignored
+    pub modifiers: KeyModifiers,
+    pub action: Action,
+}
+
+/// The keys handled in the main view. Key handling and the help overlay both read
+/// it, so the help always lists what is actually bound.
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    bindings: Vec<KeyBinding>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let key = |code: KeyCode, action: Action| KeyBinding { code, modifiers: KeyModifiers::NONE, action };
+        let char = |c: char, action: Action| key(KeyCode::Char(c), action);
+
+        Self {
+            bindings: vec![
+                char('q', Action::Quit),
+                key(KeyCode::Esc, Action::Quit),
+                KeyBinding { code: KeyCode::Char('c'), modifiers: KeyModifiers::CONTROL, action: Action::Quit },
+                char('v', Action::ToggleMode),
+                char('d', Action::DismissThought),
+                char('a', Action::Annotate),
+                char('L', Action::RaiseCostLimit),
+                char('s', Action::RequestSuggestions),
+                char('e', Action::ExplainFile),
+                char('t', Action::OpenRequestTypePicker),
+                char('o', Action::OpenInEditor),
+                char('F', Action::FixSelectedThought),
+                char('#', Action::ToggleLineNumbers),
+                char('H', Action::ToggleSyntaxHighlighting),
+                char('g', Action::ToggleThoughtGrouping),
+                char('z', Action::ToggleGroupCollapsed),
+                char('k', Action::ToggleCompactThoughts),
+                char('c', Action::ClearThoughts),
+                char('f', Action::SelectFile),
+                char('R', Action::OpenRecentFiles),
+                char('y', Action::AcceptSuggestion),
+                char('n', Action::RejectSuggestion),
+                char('u', Action::UndoEdit),
+                char('D', Action::ToggleDryRun),
+                key(KeyCode::PageUp, Action::PageUp),
+                key(KeyCode::PageDown, Action::PageDown),
+                key(KeyCode::Up, Action::SelectPreviousThought),
+                key(KeyCode::Down, Action::SelectNextThought),
+                char('M', Action::MetaReview),
+                char('m', Action::CycleModel),
+                char('x', Action::ExportThoughts),
+                char('r', Action::Refresh),
+                key(KeyCode::F(5), Action::Refresh),
+                char('h', Action::Help),
+                key(KeyCode::F(1), Action::Help),
+            ],
+        }
+    }
+}
+
+impl KeyMap {
+    /// The action bound to `key`; the first matching binding wins, so Ctrl+C quits
+    /// rather than clearing thoughts
+    pub fn action_for(&self, key: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.code == key.code && key.modifiers.contains(binding.modifiers))
+            .map(|binding| binding.action)
+    }
+
+    /// One `keys - description` line per bound action, in binding order
+    pub fn help_lines(&self) -> Vec<String> {
+        let mut actions: Vec<(Action, Vec<String>)> = Vec::new();
+        for binding in &self.bindings {
+            let label = key_label(binding.code, binding.modifiers);
+            match actions.iter_mut().find(|(action, _)| *action == binding.action) {
+                Some((_, labels)) => labels.push(label),
+                None => actions.push((binding.action, vec![label])),
+            }
+        }
+
+        actions
+            .into_iter()
+            .map(|(action, labels)| format!("{} - {}", labels.join(", "), action.description()))
+            .collect()
+    }
+}
+
+/// How a key is written in the help, e.g. `q`, `Esc`, `Ctrl+C`
+fn key_label(code: KeyCode, modifiers: KeyModifiers) -> String {
+    let key = match code {
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => c.to_ascii_uppercase().to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::PageUp => "PgUp".to_string(),
+        KeyCode::PageDown => "PgDn".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        other => format!("{:?}", other),
+    };
+
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        format!("Ctrl+{}", key)
+    } else {
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_for() {
+        let keymap = KeyMap::default();
+        let press = |code: KeyCode, modifiers: KeyModifiers| keymap.action_for(&KeyEvent::new(code, modifiers));
+
+        assert_eq!(press(KeyCode::Char('c'), KeyModifiers::CONTROL), Some(Action::Quit));
+        assert_eq!(press(KeyCode::Char('c'), KeyModifiers::NONE), Some(Action::ClearThoughts));
+        assert_eq!(press(KeyCode::Char('D'), KeyModifiers::SHIFT), Some(Action::ToggleDryRun));
+        assert_eq!(press(KeyCode::F(1), KeyModifiers::NONE), Some(Action::Help));
+        assert_eq!(press(KeyCode::F(5), KeyModifiers::NONE), Some(Action::Refresh));
+        assert_eq!(press(KeyCode::Char('w'), KeyModifiers::NONE), None);
+    }
+
+    #[test]
+    fn test_help_lines_follow_bindings() {
+        let lines = KeyMap::default().help_lines();
+
+        assert_eq!(lines[0], "q, Esc, Ctrl+C - Quit");
+        assert!(lines.contains(&"r, F5 - Re-analyze the current file".to_string()));
+        assert!(lines.contains(&"h, F1 - Show this help".to_string()));
+
+        let keymap = KeyMap {
+            bindings: vec![KeyBinding { code: KeyCode::F(1), modifiers: KeyModifiers::NONE, action: Action::Help }],
+        };
+        assert_eq!(keymap.help_lines(), vec!["F1 - Show this help"]);
+    }
+}
@@ -1,9 +1,11 @@
+pub mod headless;
+pub mod keymap;
 pub mod renderer;
 pub mod widgets;
 
-use anyhow::Result;
+use crate::error::Result;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    event::{self, Event, KeyCode, KeyEvent},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -14,17 +16,29 @@ use ratatui::{
 use std::io;
 use tokio::time::{Duration, Instant};
 
+/// Frame rates outside this range are clamped
+const MIN_RENDER_FPS: u32 = 1;
+const MAX_RENDER_FPS: u32 = 60;
+
 use crate::app::{App, UiEvent, UiEventType};
+use keymap::{Action, KeyMap};
 
 pub struct UI {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     app: App,
     last_render: Instant,
     render_interval: Duration,
+    needs_redraw: bool,
+    rendered_version: Option<u64>,
+    keymap: KeyMap,
+    /// Whether the help overlay is shown; the next key closes it
+    help_open: bool,
 }
 
 impl UI {
     pub async fn new(app: App) -> Result<Self> {
+        install_panic_hook();
+
         enable_raw_mode()?;
         let mut stdout = io::stdout();
         execute!(stdout, EnterAlternateScreen)?;
@@ -32,11 +46,17 @@ impl UI {
         let backend = CrosstermBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
 
+        let fps = app.get_config().await.render_fps.clamp(MIN_RENDER_FPS, MAX_RENDER_FPS);
+
         Ok(Self {
             terminal,
             app,
             last_render: Instant::now(),
-            render_interval: Duration::from_millis(50), // 20 FPS
+            render_interval: Duration::from_millis(1000 / fps as u64),
+            needs_redraw: true,
+            rendered_version: None,
+            keymap: KeyMap::default(),
+            help_open: false,
         })
     }
 
@@ -53,6 +73,7 @@ impl UI {
                         }
                     }
                     Event::Resize(_, _) => {
+                        self.needs_redraw = true;
                         let ui_event = UiEvent {
                             event_type: UiEventType::Resize,
                             data: None,
@@ -67,10 +88,15 @@ impl UI {
                 }
             }
 
-            // Render at controlled intervals
-            if self.last_render.elapsed() >= self.render_interval {
+            // Render at controlled intervals, and only when app state changed or the terminal resized
+            let version = self.app.get_state_version();
+            if self.last_render.elapsed() >= self.render_interval
+                && (self.needs_redraw || self.rendered_version != Some(version))
+            {
                 self.render().await?;
                 self.last_render = Instant::now();
+                self.needs_redraw = false;
+                self.rendered_version = Some(version);
             }
 
             // Check if app is still running
@@ -78,8 +104,9 @@ impl UI {
                 break;
             }
 
-            // Small delay to prevent busy waiting
-            tokio::time::sleep(Duration::from_millis(10)).await;
+            // Small delay to prevent busy waiting; while idle, keys are picked up less eagerly
+            let delay = if self.app.is_idle().await { 100 } else { 10 };
+            tokio::time::sleep(Duration::from_millis(delay)).await;
         }
 
         self.cleanup()?;
@@ -93,100 +120,148 @@ impl UI {
             timestamp: chrono::Utc::now(),
         };
 
-        match key.code {
-            KeyCode::Char('q') | KeyCode::Esc => {
-                let quit_event = UiEvent {
-                    event_type: UiEventType::Quit,
-                    data: None,
-                    timestamp: chrono::Utc::now(),
-                };
-                if let Err(_) = self.app.ui_tx.try_send(quit_event) {
-                    tracing::warn!("UI channel full, dropping quit event");
-                }
-                return Ok(true);
+        // Any key closes the help overlay
+        if self.help_open {
+            self.help_open = false;
+            self.needs_redraw = true;
+            return Ok(false);
+        }
+
+        // While a suggestion diff is shown, keys only confirm or cancel it
+        if self.app.pending_edit.lock().await.is_some() {
+            let event_type = match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => UiEventType::ConfirmEdit,
+                KeyCode::Char('n') | KeyCode::Esc => UiEventType::CancelEdit,
+                _ => return Ok(false),
+            };
+            let edit_event = UiEvent {
+                event_type,
+                data: None,
+                timestamp: chrono::Utc::now(),
+            };
+            if self.app.ui_tx.try_send(edit_event).is_err() {
+                tracing::warn!("UI channel full, dropping edit confirmation event");
             }
-            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                let quit_event = UiEvent {
-                    event_type: UiEventType::Quit,
-                    data: None,
-                    timestamp: chrono::Utc::now(),
-                };
-                if let Err(_) = self.app.ui_tx.try_send(quit_event) {
-                    tracing::warn!("UI channel full, dropping quit event");
+            return Ok(false);
+        }
+
+        // While the file picker is open, keys move the highlight, open or close it
+        if self.app.file_picker.lock().await.is_some() {
+            let event_type = match key.code {
+                KeyCode::Up => UiEventType::MoveFileSelection(-1),
+                KeyCode::Down => UiEventType::MoveFileSelection(1),
+                KeyCode::PageUp => UiEventType::MoveFileSelection(-10),
+                KeyCode::PageDown => UiEventType::MoveFileSelection(10),
+                KeyCode::Enter => UiEventType::OpenSelectedFile,
+                KeyCode::Esc | KeyCode::Char('f') | KeyCode::Char('R') | KeyCode::Char('q') => {
+                    UiEventType::CloseFilePicker
                 }
-                return Ok(true);
+                _ => return Ok(false),
+            };
+            let picker_event = UiEvent {
+                event_type,
+                data: None,
+                timestamp: chrono::Utc::now(),
+            };
+            if self.app.ui_tx.try_send(picker_event).is_err() {
+                tracing::warn!("UI channel full, dropping file picker event");
             }
-            KeyCode::Char('v') => {
-                let toggle_event = UiEvent {
-                    event_type: UiEventType::ToggleMode,
-                    data: None,
-                    timestamp: chrono::Utc::now(),
-                };
-                if let Err(_) = self.app.ui_tx.try_send(toggle_event) {
-                    tracing::warn!("UI channel full, dropping toggle event");
-                }
+            return Ok(false);
+        }
+
+        // While the annotation prompt is open, keys edit the note
+        if self.app.annotation_draft.lock().await.is_some() {
+            let event_type = match key.code {
+                KeyCode::Enter => UiEventType::SubmitAnnotation,
+                KeyCode::Esc => UiEventType::CancelAnnotation,
+                KeyCode::Backspace => UiEventType::AnnotationBackspace,
+                KeyCode::Char(c) => UiEventType::AnnotationInput(c),
+                _ => return Ok(false),
+            };
+            let annotation_event = UiEvent {
+                event_type,
+                data: None,
+                timestamp: chrono::Utc::now(),
+            };
+            if self.app.ui_tx.try_send(annotation_event).is_err() {
+                tracing::warn!("UI channel full, dropping annotation event");
             }
-            KeyCode::Char('c') => {
-                let clear_event = UiEvent {
-                    event_type: UiEventType::ClearThoughts,
-                    data: None,
-                    timestamp: chrono::Utc::now(),
-                };
-                if let Err(_) = self.app.ui_tx.try_send(clear_event) {
-                    tracing::warn!("UI channel full, dropping clear event");
-                }
+            return Ok(false);
+        }
+
+        // While the request type picker is open, keys move the highlight, run or close it
+        if self.app.request_type_picker.lock().await.is_some() {
+            let event_type = match key.code {
+                KeyCode::Up => UiEventType::MoveRequestTypeSelection(-1),
+                KeyCode::Down => UiEventType::MoveRequestTypeSelection(1),
+                KeyCode::Enter => UiEventType::RunSelectedRequestType,
+                KeyCode::Esc | KeyCode::Char('t') | KeyCode::Char('q') => UiEventType::CloseRequestTypePicker,
+                _ => return Ok(false),
+            };
+            let picker_event = UiEvent {
+                event_type,
+                data: None,
+                timestamp: chrono::Utc::now(),
+            };
+            if self.app.ui_tx.try_send(picker_event).is_err() {
+                tracing::warn!("UI channel full, dropping request type picker event");
             }
-            KeyCode::Char('f') => {
-                let select_event = UiEvent {
-                    event_type: UiEventType::SelectFile,
-                    data: None,
-                    timestamp: chrono::Utc::now(),
-                };
-                if let Err(_) = self.app.ui_tx.try_send(select_event) {
-                    tracing::warn!("UI channel full, dropping select event");
-                }
+            return Ok(false);
+        }
+
+        // While the suggestions list is open, the same keys close it
+        if self.app.suggestion_list.lock().await.is_some() {
+            if !matches!(key.code, KeyCode::Esc | KeyCode::Char('s') | KeyCode::Char('q')) {
+                return Ok(false);
             }
-            KeyCode::Char('y') => {
-                let accept_event = UiEvent {
-                    event_type: UiEventType::AcceptSuggestion,
-                    data: None,
-                    timestamp: chrono::Utc::now(),
-                };
-                if let Err(_) = self.app.ui_tx.try_send(accept_event) {
-                    tracing::warn!("UI channel full, dropping accept event");
-                }
+            let close_event = UiEvent {
+                event_type: UiEventType::CloseSuggestions,
+                data: None,
+                timestamp: chrono::Utc::now(),
+            };
+            if self.app.ui_tx.try_send(close_event).is_err() {
+                tracing::warn!("UI channel full, dropping suggestions event");
             }
-            KeyCode::Char('n') => {
-                let reject_event = UiEvent {
-                    event_type: UiEventType::RejectSuggestion,
-                    data: None,
-                    timestamp: chrono::Utc::now(),
-                };
-                if let Err(_) = self.app.ui_tx.try_send(reject_event) {
-                    tracing::warn!("UI channel full, dropping reject event");
-                }
+            return Ok(false);
+        }
+
+        match self.keymap.action_for(&key) {
+            Some(Action::OpenInEditor) => {
+                self.open_in_editor().await?;
             }
-            KeyCode::Char('h') => {
-                let help_event = UiEvent {
-                    event_type: UiEventType::Help,
+            Some(action @ (Action::PageUp | Action::PageDown)) => {
+                // Roughly one code panel of lines, leaving room for borders and the status bar
+                let page = self.terminal.size()?.height.saturating_sub(5).max(1) as isize;
+                let delta = if action == Action::PageUp { -page } else { page };
+                let scroll_event = UiEvent {
+                    event_type: UiEventType::ScrollCode(delta),
                     data: None,
                     timestamp: chrono::Utc::now(),
                 };
-                if let Err(_) = self.app.ui_tx.try_send(help_event) {
-                    tracing::warn!("UI channel full, dropping help event");
+                if self.app.ui_tx.try_send(scroll_event).is_err() {
+                    tracing::warn!("UI channel full, dropping scroll event");
                 }
             }
-            KeyCode::Char('r') => {
-                let refresh_event = UiEvent {
-                    event_type: UiEventType::Refresh,
-                    data: None,
-                    timestamp: chrono::Utc::now(),
-                };
-                if let Err(_) = self.app.ui_tx.try_send(refresh_event) {
-                    tracing::warn!("UI channel full, dropping refresh event");
+            Some(action) => {
+                if action == Action::Help {
+                    self.help_open = true;
+                    self.needs_redraw = true;
+                }
+                if let Some(event_type) = action.event_type() {
+                    let action_event = UiEvent {
+                        event_type,
+                        data: None,
+                        timestamp: chrono::Utc::now(),
+                    };
+                    if self.app.ui_tx.try_send(action_event).is_err() {
+                        tracing::warn!("UI channel full, dropping {:?} event", action);
+                    }
+                }
+                if action == Action::Quit {
+                    return Ok(true);
                 }
             }
-            _ => {}
+            None => {}
         }
 
         // Send the key event for recording (non-blocking)
@@ -210,13 +285,68 @@ impl UI {
         renderer::AppData {
             current_file: self.app.get_current_file().await,
             current_code: self.app.get_current_code().await,
+            code_scroll: self.app.get_code_scroll().await,
+            code_display: self.app.get_code_display().await,
+            thought_grouping: self.app.get_thought_grouping().await,
             thoughts: self.app.get_thoughts().await,
+            selected_thought: self.app.get_selected_thought().await,
+            queue_depth: self.app.ai_queue.len(),
+            queue_capacity: self.app.ai_queue.capacity(),
+            pending_edit: self.app.get_pending_edit().await,
+            file_picker: self.app.get_file_picker().await,
+            annotation_draft: self.app.get_annotation_draft().await,
+            suggestion_list: self.app.get_suggestion_list().await,
+            request_type_picker: self.app.get_request_type_picker().await,
+            status_message: self.app.get_status_message().await,
             mode: self.app.get_mode().await,
             is_recording: *self.app.is_recording.lock().await,
-            config: self.app.config.clone(),
+            is_idle: self.app.is_idle().await,
+            dropped_fs_events: self.app.dropped_fs_events.load(std::sync::atomic::Ordering::Relaxed),
+            dry_run_edits: self.app.is_dry_run().await,
+            config: self.app.get_config().await,
+            help: self.help_open.then(|| self.keymap.help_lines()),
         }
     }
 
+    /// Suspends the TUI to edit the current file in `$VISUAL` or `$EDITOR`, at the
+    /// selected thought's line when it is about that file, and redraws on return
+    async fn open_in_editor(&mut self) -> Result<()> {
+        let thoughts = self.app.get_thoughts().await;
+        let selected = self.app.get_selected_thought().await.and_then(|i| thoughts.get(i));
+        let current_file = self.app.get_current_file().await;
+
+        let Some(file) = current_file.clone().or_else(|| selected.and_then(|t| t.file_path.clone())) else {
+            self.app.set_status("Open a file first to edit it".to_string()).await;
+            return Ok(());
+        };
+        let line = selected
+            .filter(|t| t.file_path.as_deref() == Some(file.as_str()))
+            .and_then(|t| t.line_number);
+
+        let editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_string());
+        let Some((program, args)) = editor_command(&editor, &file, line) else {
+            self.app.set_status("$EDITOR is empty".to_string()).await;
+            return Ok(());
+        };
+
+        tracing::info!("Opening {} in {}", file, program);
+        self.cleanup()?;
+        let status = std::process::Command::new(&program).args(&args).status();
+        enable_raw_mode()?;
+        execute!(self.terminal.backend_mut(), EnterAlternateScreen)?;
+        self.terminal.clear()?;
+        self.needs_redraw = true;
+
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => self.app.set_status(format!("{} exited with {}", program, status)).await,
+            Err(e) => self.app.set_status(format!("Could not start {}: {}", program, e)).await,
+        }
+        Ok(())
+    }
+
     fn cleanup(&mut self) -> Result<()> {
         disable_raw_mode()?;
         execute!(
@@ -228,8 +358,70 @@ impl UI {
     }
 }
 
+/// The program and arguments that open `file` in `editor`, a command line such as
+/// `vim` or `code -w`, jumping to `line` in the editors whose syntax for it is known
+fn editor_command(editor: &str, file: &str, line: Option<usize>) -> Option<(String, Vec<String>)> {
+    let mut words = editor.split_whitespace().map(str::to_string);
+    let program = words.next()?;
+    let mut args: Vec<String> = words.collect();
+
+    let name = std::path::Path::new(&program)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(&program)
+        .to_string();
+    match (name.as_str(), line) {
+        ("vi" | "vim" | "nvim" | "nano" | "emacs" | "micro" | "kak" | "joe" | "ne" | "mg", Some(line)) => {
+            args.push(format!("+{}", line));
+            args.push(file.to_string());
+        }
+        ("hx" | "helix" | "subl" | "zed", Some(line)) => args.push(format!("{}:{}", file, line)),
+        ("code" | "code-insiders" | "codium", Some(line)) => {
+            args.push("--goto".to_string());
+            args.push(format!("{}:{}", file, line));
+        }
+        _ => args.push(file.to_string()),
+    }
+    Some((program, args))
+}
+
+/// Restores the terminal before the default panic message is printed, so a panic
+/// in any task doesn't leave the shell in raw mode on the alternate screen
+fn install_panic_hook() {
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+
+    INSTALL.call_once(|| {
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = disable_raw_mode();
+            let _ = execute!(io::stdout(), LeaveAlternateScreen, crossterm::cursor::Show);
+            default_hook(info);
+        }));
+    });
+}
+
 impl Drop for UI {
     fn drop(&mut self) {
         let _ = self.cleanup();
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_command() {
+        let command = |editor: &str, line: Option<usize>| editor_command(editor, "src/main.rs", line);
+
+        assert_eq!(command("vim", Some(12)), Some(("vim".to_string(), vec!["+12".to_string(), "src/main.rs".to_string()])));
+        assert_eq!(command("/usr/bin/nvim", None), Some(("/usr/bin/nvim".to_string(), vec!["src/main.rs".to_string()])));
+        assert_eq!(
+            command("code -w", Some(3)),
+            Some(("code".to_string(), vec!["-w".to_string(), "--goto".to_string(), "src/main.rs:3".to_string()]))
+        );
+        assert_eq!(command("hx", Some(7)), Some(("hx".to_string(), vec!["src/main.rs:7".to_string()])));
+        assert_eq!(command("ed", Some(7)), Some(("ed".to_string(), vec!["src/main.rs".to_string()])));
+        assert_eq!(command("  ", Some(7)), None);
+    }
+}
@@ -7,19 +7,40 @@ use ratatui::{
     },
     Frame,
 };
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use crate::app::{Thought, ThoughtType, ViewMode};
+use crate::app::{CodeDisplay, RequestTypePicker, SuggestionList, Thought, ThoughtType, ViewMode, REQUEST_TYPE_CHOICES};
 use crate::config::Config;
+use crate::edits::PendingEdit;
 use super::widgets;
 
 pub struct AppData {
     pub current_file: Option<String>,
     pub current_code: String,
+    pub code_scroll: usize,
+    pub code_display: CodeDisplay,
+    pub thought_grouping: widgets::ThoughtGrouping,
     pub thoughts: Vec<Thought>,
+    pub selected_thought: Option<usize>,
+    pub queue_depth: usize,
+    pub queue_capacity: usize,
+    pub pending_edit: Option<PendingEdit>,
+    pub file_picker: Option<widgets::FilePicker>,
+    pub annotation_draft: Option<String>,
+    pub suggestion_list: Option<SuggestionList>,
+    pub request_type_picker: Option<RequestTypePicker>,
+    pub status_message: Option<String>,
     pub mode: ViewMode,
     pub is_recording: bool,
+    pub is_idle: bool,
+    /// Filesystem events the watcher dropped so far
+    pub dropped_fs_events: u64,
+    /// Accepted suggestions are recorded but not written
+    pub dry_run_edits: bool,
     pub config: Arc<Config>,
+    /// Lines of the help overlay, built from the live key bindings while it is open
+    pub help: Option<Vec<String>>,
 }
 
 pub fn render_frame(frame: &mut Frame, app_data: &AppData) {
@@ -35,8 +56,202 @@ pub fn render_frame(frame: &mut Frame, app_data: &AppData) {
     // Render status bar at the bottom
     render_status_bar(frame, app_data, size);
 
-    // Render help overlay if needed
-    // This would be triggered by a help state in the app
+    if let Some(ref list) = app_data.suggestion_list {
+        render_suggestion_list(frame, list, size);
+    }
+
+    if let Some(ref picker) = app_data.request_type_picker {
+        render_request_type_picker(frame, picker, size);
+    }
+
+    if let Some(ref picker) = app_data.file_picker {
+        render_file_picker(frame, picker, size);
+    }
+
+    if let Some(ref edit) = app_data.pending_edit {
+        render_edit_preview(frame, edit, app_data.dry_run_edits, size);
+    }
+
+    if let Some(ref draft) = app_data.annotation_draft {
+        render_annotation_prompt(frame, draft, size);
+    }
+
+    if let Some(ref help) = app_data.help {
+        render_help_overlay(frame, help, size);
+    }
+}
+
+fn render_file_picker(frame: &mut Frame, picker: &widgets::FilePicker, area: Rect) {
+    let popup_area = centered_rect(60, 70, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    // Keep the highlighted file in view, scrolling a page at a time
+    let visible = popup_area.height.saturating_sub(2).max(1) as usize;
+    let first = picker.selected / visible * visible;
+    let root = crate::util::project_root();
+
+    let lines: Vec<Line> = picker
+        .files
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible)
+        .map(|(index, path)| {
+            let name = path.strip_prefix(root).unwrap_or(path).display().to_string();
+            if index == picker.selected {
+                Line::styled(format!("▶ {}", name), Style::default().fg(Color::Black).bg(Color::Cyan))
+            } else {
+                Line::styled(format!("  {}", name), Style::default().fg(Color::White))
+            }
+        })
+        .collect();
+
+    let list = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(format!(
+                    " {} ({}/{}) [↑/↓] move  [Enter] open  [Esc] close ",
+                    picker.title,
+                    picker.selected + 1,
+                    picker.files.len()
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .style(Style::default().bg(Color::Black));
+
+    frame.render_widget(list, popup_area);
+}
+
+fn render_request_type_picker(frame: &mut Frame, picker: &RequestTypePicker, area: Rect) {
+    let popup_area = centered_rect(50, 40, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let lines: Vec<Line> = REQUEST_TYPE_CHOICES
+        .iter()
+        .enumerate()
+        .map(|(index, (_, label))| {
+            if index == picker.selected {
+                Line::styled(format!("▶ {}", label), Style::default().fg(Color::Black).bg(Color::Magenta))
+            } else {
+                Line::styled(format!("  {}", label), Style::default().fg(Color::White))
+            }
+        })
+        .collect();
+
+    let root = crate::util::project_root();
+    let file_name = std::path::Path::new(&picker.file_path)
+        .strip_prefix(root)
+        .unwrap_or(std::path::Path::new(&picker.file_path))
+        .display()
+        .to_string();
+
+    let list = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(format!(" Re-run {} as [↑/↓] move  [Enter] run  [Esc] close ", file_name))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Magenta)),
+        )
+        .style(Style::default().bg(Color::Black));
+
+    frame.render_widget(list, popup_area);
+}
+
+fn render_suggestion_list(frame: &mut Frame, list: &SuggestionList, area: Rect) {
+    let popup_area = centered_rect(70, 70, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let mut lines: Vec<Line> = Vec::new();
+    for (index, suggestion) in list.suggestions.iter().enumerate() {
+        lines.push(Line::styled(
+            format!("{}. {} ({:?})", index + 1, suggestion.title, suggestion.priority),
+            Style::default().fg(Color::Yellow),
+        ));
+        for line in suggestion.description.lines().filter(|l| !l.trim().is_empty()) {
+            lines.push(Line::styled(format!("   {}", line.trim()), Style::default().fg(Color::White)));
+        }
+        if let Some(code) = &suggestion.code_snippet {
+            for line in code.lines() {
+                lines.push(Line::styled(format!("   │ {}", line), Style::default().fg(Color::Green)));
+            }
+        }
+        lines.push(Line::from(""));
+    }
+
+    let list_widget = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .title(format!(" Suggestions for {} [s/Esc] close ", list.file_path))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .style(Style::default().bg(Color::Black));
+
+    frame.render_widget(list_widget, popup_area);
+}
+
+fn render_annotation_prompt(frame: &mut Frame, draft: &str, area: Rect) {
+    let popup_area = centered_rect(60, 20, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let prompt = Paragraph::new(format!("{}▏", draft))
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .title(" Annotate this moment [Enter] save  [Esc] cancel ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .style(Style::default().fg(Color::White).bg(Color::Black));
+
+    frame.render_widget(prompt, popup_area);
+}
+
+fn render_edit_preview(frame: &mut Frame, edit: &PendingEdit, dry_run: bool, area: Rect) {
+    let popup_area = centered_rect(80, 70, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let diff_lines: Vec<Line> = edit
+        .diff
+        .lines()
+        .map(|line| {
+            let color = if line.starts_with("+++") || line.starts_with("---") {
+                Color::White
+            } else if line.starts_with('+') {
+                Color::Green
+            } else if line.starts_with('-') {
+                Color::Red
+            } else if line.starts_with("@@") {
+                Color::Cyan
+            } else {
+                Color::Gray
+            };
+            Line::styled(line.to_string(), Style::default().fg(color))
+        })
+        .collect();
+
+    let title = if dry_run {
+        format!(" Dry run \"{}\": [y/Enter] record without writing  [n/Esc] cancel ", edit.title)
+    } else {
+        format!(" Apply \"{}\"? [y/Enter] apply  [n/Esc] cancel ", edit.title)
+    };
+    let preview = Paragraph::new(diff_lines)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .style(Style::default().bg(Color::Black));
+
+    frame.render_widget(preview, popup_area);
 }
 
 fn render_side_by_side(frame: &mut Frame, app_data: &AppData, area: Rect) {
@@ -117,6 +332,11 @@ fn render_code_panel(frame: &mut Frame, app_data: &AppData, area: Rect) {
         frame.render_widget(placeholder, area);
     } else {
         let code_widget = widgets::CodeWidget::new(&app_data.current_code)
+            .scroll(app_data.code_scroll)
+            .line_numbers(app_data.code_display.line_numbers)
+            .syntax_highlighting(app_data.code_display.syntax_highlighting)
+            .highlight_lines_with_colors(referenced_line_colors(app_data))
+            .selected_line(selected_line(app_data))
             .block(block)
             .style(Style::default().fg(Color::White));
 
@@ -124,6 +344,41 @@ fn render_code_panel(frame: &mut Frame, app_data: &AppData, area: Rect) {
     }
 }
 
+/// Colors for each line of the current file that a thought points at, using the
+/// most severe thought type when several thoughts reference the same line
+fn referenced_line_colors(app_data: &AppData) -> HashMap<usize, Color> {
+    let mut most_severe: HashMap<usize, &ThoughtType> = HashMap::new();
+
+    for thought in &app_data.thoughts {
+        if thought.file_path.is_none() || thought.file_path != app_data.current_file {
+            continue;
+        }
+
+        if let Some(line_number) = thought.line_number {
+            let entry = most_severe.entry(line_number).or_insert(&thought.thought_type);
+            if thought.thought_type.severity() > entry.severity() {
+                *entry = &thought.thought_type;
+            }
+        }
+    }
+
+    most_severe
+        .into_iter()
+        .map(|(line, thought_type)| (line, get_thought_color(thought_type)))
+        .collect()
+}
+
+/// Line in the current file that the selected thought points at, if any
+fn selected_line(app_data: &AppData) -> Option<usize> {
+    let thought = app_data.thoughts.get(app_data.selected_thought?)?;
+
+    if thought.file_path.is_some() && thought.file_path == app_data.current_file {
+        thought.line_number
+    } else {
+        None
+    }
+}
+
 fn render_thoughts_panel(frame: &mut Frame, app_data: &AppData, area: Rect) {
     let block = Block::default()
         .title(" AI Thoughts ")
@@ -141,6 +396,11 @@ fn render_thoughts_panel(frame: &mut Frame, app_data: &AppData, area: Rect) {
         frame.render_widget(placeholder, area);
     } else {
         let thoughts_widget = widgets::ThoughtsWidget::new(&app_data.thoughts)
+            .selected(app_data.selected_thought)
+            .grouping(&app_data.thought_grouping)
+            .current_file(app_data.current_file.as_deref())
+            .show_suggestions(app_data.config.include_suggestions)
+            .confidence_colors(widgets::ConfidenceColors::from_theme(&app_data.config.ui_theme))
             .block(block);
 
         frame.render_widget(thoughts_widget, area);
@@ -171,15 +431,26 @@ fn render_minimal_info(frame: &mut Frame, app_data: &AppData, area: Rect) {
 
     frame.render_widget(file_widget, layout[0]);
 
-    // Latest thought
-    if let Some(latest_thought) = app_data.thoughts.last() {
-        let thought_text = format!(
+    // Latest finding; the pass summary is pushed after it and carries no suggestions
+    let latest_thought = app_data
+        .thoughts
+        .iter()
+        .rev()
+        .find(|t| !matches!(t.thought_type, ThoughtType::Complete))
+        .or_else(|| app_data.thoughts.last());
+    if let Some(latest_thought) = latest_thought {
+        let mut thought_lines = vec![Line::from(format!(
             "{} {}",
             get_thought_icon(&latest_thought.thought_type),
             latest_thought.content
-        );
+        ))];
 
-        let thought_widget = Paragraph::new(thought_text)
+        // Keep the actionable part visible, but only the top two to stay compact
+        for (i, suggestion) in latest_thought.suggestions.iter().take(2).enumerate() {
+            thought_lines.push(widgets::suggestion_line(suggestion, i + 1));
+        }
+
+        let thought_widget = Paragraph::new(thought_lines)
             .block(
                 Block::default()
                     .title(" Latest Thought ")
@@ -227,11 +498,18 @@ fn render_status_bar(frame: &mut Frame, app_data: &AppData, area: Rect) {
 
     frame.render_widget(recording_widget, status_layout[0]);
 
-    // Center: Mode and keybindings
-    let mode_text = format!("Mode: {:?}", app_data.mode);
+    // Center: Mode, model and keybindings
+    let mut mode_text = format!("Mode: {:?} · Model: {}", app_data.mode, app_data.config.ai_model());
+    if app_data.config.manual_mode {
+        mode_text.push_str(" · Manual ([r] analyze)");
+    }
     let keybindings = " [q] Quit [v] Mode [c] Clear [f] File [h] Help ";
 
-    let center_text = format!("{} {}", mode_text, keybindings);
+    let center_text = match app_data.status_message {
+        Some(ref message) => message.clone(),
+        None if app_data.is_idle => format!("💤 Idle, waiting for changes · {} {}", mode_text, keybindings),
+        None => format!("{} {}", mode_text, keybindings),
+    };
     let center_widget = Paragraph::new(center_text)
         .block(Block::default().borders(Borders::ALL))
         .style(Style::default().fg(Color::Yellow))
@@ -239,11 +517,24 @@ fn render_status_bar(frame: &mut Frame, app_data: &AppData, area: Rect) {
 
     frame.render_widget(center_widget, status_layout[1]);
 
-    // Right: Thoughts count
-    let thoughts_count = format!("Thoughts: {}", app_data.thoughts.len());
+    // Right: Thoughts count and pending analyses
+    let mut thoughts_count = format!(
+        "Thoughts: {} · Queue: {}/{}",
+        app_data.thoughts.len(),
+        app_data.queue_depth,
+        app_data.queue_capacity
+    );
+    if app_data.dropped_fs_events > 0 {
+        thoughts_count.push_str(&format!(" · Dropped: {}", app_data.dropped_fs_events));
+    }
+    let queue_color = if app_data.queue_depth >= app_data.queue_capacity || app_data.dropped_fs_events > 0 {
+        Color::Red
+    } else {
+        Color::Cyan
+    };
     let thoughts_widget = Paragraph::new(thoughts_count)
         .block(Block::default().borders(Borders::ALL))
-        .style(Style::default().fg(Color::Cyan))
+        .style(Style::default().fg(queue_color))
         .alignment(Alignment::Right);
 
     frame.render_widget(thoughts_widget, status_layout[2]);
@@ -279,23 +570,18 @@ fn get_thought_color(thought_type: &ThoughtType) -> Color {
     }
 }
 
-pub fn render_help_overlay(frame: &mut Frame, area: Rect) {
+pub fn render_help_overlay(frame: &mut Frame, key_help: &[String], area: Rect) {
     let popup_area = centered_rect(80, 70, area);
 
     frame.render_widget(Clear, popup_area);
 
-    let help_text = vec![
+    let mut help_text = vec![
         Line::from("CoCo v2.0 - AI Pair Programmer"),
         Line::from(""),
         Line::from("Keybindings:"),
-        Line::from("  q, Esc, Ctrl+C - Quit"),
-        Line::from("  v - Toggle view mode"),
-        Line::from("  c - Clear thoughts"),
-        Line::from("  f - Select file"),
-        Line::from("  y - Accept suggestion"),
-        Line::from("  n - Reject suggestion"),
-        Line::from("  h, F1 - Show this help"),
-        Line::from("  F5 - Refresh"),
+    ];
+    help_text.extend(key_help.iter().map(|line| Line::from(format!("  {}", line))));
+    help_text.extend([
         Line::from(""),
         Line::from("View Modes:"),
         Line::from("  Side-by-Side - Code and thoughts side by side"),
@@ -304,7 +590,7 @@ pub fn render_help_overlay(frame: &mut Frame, area: Rect) {
         Line::from("  Thoughts Only - AI thoughts full screen"),
         Line::from(""),
         Line::from("Press any key to close this help"),
-    ];
+    ]);
 
     let help_widget = Paragraph::new(help_text)
         .block(
@@ -8,15 +8,21 @@ use ratatui::{
     },
 };
 
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
 use crate::app::{Thought, ThoughtType, Suggestion};
+use crate::config::UiTheme;
 
 pub struct CodeWidget<'a> {
     content: &'a str,
     block: Option<Block<'a>>,
     style: Style,
     line_numbers: bool,
-    highlight_lines: Vec<usize>,
+    highlight_lines: HashMap<usize, Color>,
+    selected_line: Option<usize>,
     syntax_highlighting: bool,
+    scroll_offset: usize,
 }
 
 impl<'a> CodeWidget<'a> {
@@ -26,8 +32,10 @@ impl<'a> CodeWidget<'a> {
             block: None,
             style: Style::default(),
             line_numbers: true,
-            highlight_lines: Vec::new(),
+            highlight_lines: HashMap::new(),
+            selected_line: None,
             syntax_highlighting: true,
+            scroll_offset: 0,
         }
     }
 
@@ -47,47 +55,73 @@ impl<'a> CodeWidget<'a> {
     }
 
     pub fn highlight_lines(mut self, lines: Vec<usize>) -> Self {
+        self.highlight_lines = lines.into_iter().map(|line| (line, Color::DarkGray)).collect();
+        self
+    }
+
+    /// Highlights lines (1-based) with a per-line marker color
+    pub fn highlight_lines_with_colors(mut self, lines: HashMap<usize, Color>) -> Self {
         self.highlight_lines = lines;
         self
     }
 
+    /// Emphasizes the line (1-based) of the currently selected thought
+    pub fn selected_line(mut self, line: Option<usize>) -> Self {
+        self.selected_line = line;
+        self
+    }
+
     pub fn syntax_highlighting(mut self, enable: bool) -> Self {
         self.syntax_highlighting = enable;
         self
     }
 
+    /// First source line (0-based) to render. Lines are skipped before wrapping,
+    /// so long wrapped lines never throw the offset off.
+    pub fn scroll(mut self, offset: usize) -> Self {
+        self.scroll_offset = offset;
+        self
+    }
+
     fn create_lines(&self) -> Vec<Line<'static>> {
         let lines: Vec<&str> = self.content.lines().collect();
         let mut result = Vec::new();
+        let offset = self.scroll_offset.min(lines.len().saturating_sub(1));
 
-        for (i, line) in lines.iter().enumerate() {
+        for (i, line) in lines.iter().enumerate().skip(offset) {
             let line_num = i + 1;
-            let is_highlighted = self.highlight_lines.contains(&line_num);
+            let highlight = self.highlight_lines.get(&line_num).copied();
 
             let mut spans = Vec::new();
 
             if self.line_numbers {
-                let line_num_str = format!("{:4} │ ", line_num);
+                let gutter_style = match highlight {
+                    Some(color) => Style::default().fg(color).add_modifier(Modifier::BOLD),
+                    None => Style::default().fg(Color::DarkGray),
+                };
+                let separator = if highlight.is_some() { "┃" } else { "│" };
                 spans.push(Span::styled(
-                    line_num_str,
-                    Style::default().fg(Color::DarkGray),
+                    format!("{:4} {} ", line_num, separator),
+                    gutter_style,
                 ));
+            } else if let Some(color) = highlight {
+                spans.push(Span::styled("▌", Style::default().fg(color)));
             }
 
             if self.syntax_highlighting {
                 spans.extend(self.highlight_syntax(line));
             } else {
-                spans.push(Span::styled(
-                    line.to_string(),
-                    if is_highlighted {
-                        Style::default().bg(Color::DarkGray)
-                    } else {
-                        self.style
-                    },
-                ));
+                spans.push(Span::styled(line.to_string(), self.style));
             }
 
-            result.push(Line::from(spans));
+            let mut line = Line::from(spans);
+            if self.selected_line == Some(line_num) {
+                line.patch_style(Style::default().bg(Color::Blue));
+            } else if highlight.is_some() {
+                line.patch_style(Style::default().bg(Color::DarkGray));
+            }
+
+            result.push(line);
         }
 
         result
@@ -165,7 +199,91 @@ pub struct ThoughtsWidget<'a> {
     style: Style,
     show_timestamps: bool,
     show_confidence: bool,
+    show_suggestions: bool,
     max_items: Option<usize>,
+    selected: Option<usize>,
+    grouping: Option<&'a ThoughtGrouping>,
+    current_file: Option<&'a str>,
+    confidence_colors: ConfidenceColors,
+}
+
+/// Colors a thought's confidence by how high it is, from `ui_theme`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConfidenceColors {
+    pub high: f32,
+    pub medium: f32,
+    pub high_color: Color,
+    pub medium_color: Color,
+    pub low_color: Color,
+}
+
+impl Default for ConfidenceColors {
+    fn default() -> Self {
+        Self {
+            high: 0.8,
+            medium: 0.6,
+            high_color: Color::Green,
+            medium_color: Color::Yellow,
+            low_color: Color::Red,
+        }
+    }
+}
+
+impl ConfidenceColors {
+    /// The theme's scale; colors that don't parse keep their default
+    pub fn from_theme(theme: &UiTheme) -> Self {
+        let defaults = Self::default();
+        let color = |name: &str, default: Color| name.parse().unwrap_or(default);
+        Self {
+            high: theme.confidence_high,
+            medium: theme.confidence_medium,
+            high_color: color(&theme.confidence_high_color, defaults.high_color),
+            medium_color: color(&theme.confidence_medium_color, defaults.medium_color),
+            low_color: color(&theme.confidence_low_color, defaults.low_color),
+        }
+    }
+
+    pub fn color_for(&self, confidence: f32) -> Color {
+        if confidence >= self.high {
+            self.high_color
+        } else if confidence >= self.medium {
+            self.medium_color
+        } else {
+            self.low_color
+        }
+    }
+}
+
+/// Section of the grouped thoughts panel holding thoughts without a file
+pub const GENERAL_GROUP: &str = "General";
+
+/// The file section a thought is listed under in the grouped panel
+pub fn thought_group(thought: &Thought) -> &str {
+    thought.file_path.as_deref().unwrap_or(GENERAL_GROUP)
+}
+
+/// How the thoughts panel arranges thoughts; flat and chronological by default
+#[derive(Debug, Clone, Default)]
+pub struct ThoughtGrouping {
+    /// Section thoughts under a header per file
+    pub by_file: bool,
+    /// Files whose section shows only its header
+    pub collapsed: HashSet<String>,
+    /// Merge runs of similar consecutive thoughts into one row with a count
+    pub compact: bool,
+}
+
+/// Share of characters two thoughts must have in common to be merged in compact mode
+const SIMILAR_THOUGHT_RATIO: f64 = 0.85;
+
+/// Characters of each thought compared in compact mode, bounding the cost per pair
+const SIMILARITY_MAX_CHARS: usize = 200;
+
+/// A line of the grouped thoughts panel
+#[derive(Debug, Clone, PartialEq)]
+enum GroupedRow<'a> {
+    Header { file: &'a str, count: usize, collapsed: bool },
+    Thought(usize),
 }
 
 impl<'a> ThoughtsWidget<'a> {
@@ -176,10 +294,20 @@ impl<'a> ThoughtsWidget<'a> {
             style: Style::default(),
             show_timestamps: true,
             show_confidence: true,
+            show_suggestions: true,
             max_items: None,
+            selected: None,
+            grouping: None,
+            current_file: None,
+            confidence_colors: ConfidenceColors::default(),
         }
     }
 
+    pub fn confidence_colors(mut self, colors: ConfidenceColors) -> Self {
+        self.confidence_colors = colors;
+        self
+    }
+
     pub fn block(mut self, block: Block<'a>) -> Self {
         self.block = Some(block);
         self
@@ -200,29 +328,88 @@ impl<'a> ThoughtsWidget<'a> {
         self
     }
 
+    /// Lists up to three suggestions under each thought
+    pub fn show_suggestions(mut self, show: bool) -> Self {
+        self.show_suggestions = show;
+        self
+    }
+
     pub fn max_items(mut self, max: usize) -> Self {
         self.max_items = Some(max);
         self
     }
 
+    /// Index into the full thought list of the thought to highlight
+    pub fn selected(mut self, index: Option<usize>) -> Self {
+        self.selected = index;
+        self
+    }
+
+    /// Sections thoughts by file when `grouping.by_file` is set
+    pub fn grouping(mut self, grouping: &'a ThoughtGrouping) -> Self {
+        self.grouping = Some(grouping);
+        self
+    }
+
+    /// File on screen; thoughts about other files are dimmed and marked ⏱ as stale
+    pub fn current_file(mut self, file: Option<&'a str>) -> Self {
+        self.current_file = file;
+        self
+    }
+
+    fn is_stale(&self, thought: &Thought) -> bool {
+        match (thought.file_path.as_deref(), self.current_file) {
+            (Some(file_path), Some(current)) => file_path != current,
+            _ => false,
+        }
+    }
+
     fn create_list_items(&self) -> Vec<ListItem> {
-        let thoughts = if let Some(max) = self.max_items {
-            if self.thoughts.len() > max {
-                &self.thoughts[self.thoughts.len() - max..]
-            } else {
-                self.thoughts
-            }
-        } else {
-            self.thoughts
+        let skipped = match self.max_items {
+            Some(max) => self.thoughts.len().saturating_sub(max),
+            None => 0,
         };
-
-        thoughts
-            .iter()
-            .map(|thought| self.create_thought_item(thought))
+        let shown = &self.thoughts[skipped.min(self.thoughts.len())..];
+        let highlight = Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD);
+
+        let grouped = self.grouping.filter(|grouping| grouping.by_file);
+        let compact = self.grouping.is_some_and(|grouping| grouping.compact);
+        let rows = match grouped {
+            Some(grouping) => grouped_rows(shown, grouping),
+            None => (0..shown.len()).map(GroupedRow::Thought).collect(),
+        };
+        let selected_group = self.selected.and_then(|i| self.thoughts.get(i)).map(thought_group);
+
+        compact_rows(rows, shown, compact)
+            .into_iter()
+            .map(|(row, run)| match row {
+                GroupedRow::Header { file, count, collapsed } => {
+                    let item = ListItem::new(Line::styled(
+                        format!("{} {} ({})", if collapsed { "▸" } else { "▾" }, file, count),
+                        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                    ));
+                    // A collapsed section stands in for the selected thought it hides
+                    if collapsed && selected_group == Some(file) {
+                        item.style(highlight)
+                    } else {
+                        item
+                    }
+                }
+                GroupedRow::Thought(offset) => {
+                    let item = self.create_thought_item(&self.thoughts[skipped + offset], grouped.is_none(), run.len());
+                    // A merged row stands in for every thought it holds
+                    if self.selected.is_some_and(|i| run.iter().any(|offset| skipped + offset == i)) {
+                        item.style(highlight)
+                    } else {
+                        item
+                    }
+                }
+            })
             .collect()
     }
 
-    fn create_thought_item(&self, thought: &Thought) -> ListItem {
+    /// A thought's row; `repeats` above 1 counts the similar thoughts merged into it
+    fn create_thought_item(&self, thought: &Thought, show_file: bool, repeats: usize) -> ListItem {
         let mut spans = Vec::new();
 
         // Thought type icon and color
@@ -234,6 +421,17 @@ impl<'a> ThoughtsWidget<'a> {
             Style::default().fg(color),
         ));
 
+        // About a file other than the one on screen
+        let stale = self.is_stale(thought);
+        if stale {
+            spans.push(Span::styled("⏱ ", Style::default().fg(Color::DarkGray)));
+        }
+
+        // About code changed in the latest save
+        if thought.in_recent_change {
+            spans.push(Span::styled("✎ ", Style::default().fg(Color::Yellow)));
+        }
+
         // Timestamp
         if self.show_timestamps {
             let time_str = thought.timestamp.format("%H:%M:%S").to_string();
@@ -246,32 +444,30 @@ impl<'a> ThoughtsWidget<'a> {
         // Confidence
         if self.show_confidence && thought.confidence > 0.0 {
             let confidence_str = format!("({:.0}%) ", thought.confidence * 100.0);
-            let confidence_color = if thought.confidence >= 0.8 {
-                Color::Green
-            } else if thought.confidence >= 0.6 {
-                Color::Yellow
-            } else {
-                Color::Red
-            };
             spans.push(Span::styled(
                 confidence_str,
-                Style::default().fg(confidence_color),
+                Style::default().fg(self.confidence_colors.color_for(thought.confidence)),
             ));
         }
 
         // Content
         spans.push(Span::styled(
             thought.content.clone(),
-            Style::default().fg(Color::White),
+            Style::default().fg(if stale { Color::DarkGray } else { Color::White }),
         ));
 
-        // File path and line number
-        if let Some(ref file_path) = thought.file_path {
-            let location = if let Some(line_num) = thought.line_number {
-                format!(" ({}:{})", file_path, line_num)
-            } else {
-                format!(" ({})", file_path)
-            };
+        if repeats > 1 {
+            spans.push(Span::styled(format!(" (×{})", repeats), Style::default().fg(Color::Cyan)));
+        }
+
+        // File path and line number; grouped thoughts already sit under their file
+        let location = match (&thought.file_path, thought.line_number) {
+            (Some(file_path), Some(line_num)) if show_file => Some(format!(" ({}:{})", file_path, line_num)),
+            (Some(file_path), None) if show_file => Some(format!(" ({})", file_path)),
+            (_, Some(line_num)) if !show_file => Some(format!(" (line {})", line_num)),
+            _ => None,
+        };
+        if let Some(location) = location {
             spans.push(Span::styled(
                 location,
                 Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
@@ -281,7 +477,8 @@ impl<'a> ThoughtsWidget<'a> {
         let mut lines = vec![Line::from(spans)];
 
         // Add suggestions if any
-        for (i, suggestion) in thought.suggestions.iter().enumerate() {
+        let suggestions = if self.show_suggestions { thought.suggestions.as_slice() } else { &[] };
+        for (i, suggestion) in suggestions.iter().enumerate() {
             if i < 3 {  // Limit to 3 suggestions per thought
                 let suggestion_line = self.create_suggestion_line(suggestion, i + 1);
                 lines.push(suggestion_line);
@@ -291,38 +488,92 @@ impl<'a> ThoughtsWidget<'a> {
         ListItem::new(lines)
     }
 
-    fn create_suggestion_line(&self, suggestion: &Suggestion, index: usize) -> Line {
-        let priority_icon = match suggestion.priority {
-            crate::app::Priority::Critical => "🔥",
-            crate::app::Priority::High => "⚡",
-            crate::app::Priority::Medium => "💡",
-            crate::app::Priority::Low => "💭",
-        };
+    fn create_suggestion_line(&self, suggestion: &Suggestion, index: usize) -> Line<'static> {
+        suggestion_line(suggestion, index)
+    }
+}
+
+/// Thoughts sectioned by file, files in path order with "General" last. Each thought
+/// keeps its chronological place within its file; collapsed files show only their header.
+/// Thought rows hold indices into `thoughts`.
+fn grouped_rows<'a>(thoughts: &'a [Thought], grouping: &ThoughtGrouping) -> Vec<GroupedRow<'a>> {
+    let mut files: Vec<&str> = Vec::new();
+    for thought in thoughts {
+        let file = thought_group(thought);
+        if !files.contains(&file) {
+            files.push(file);
+        }
+    }
+    files.sort_by_key(|file| (*file == GENERAL_GROUP, *file));
 
-        let action_icon = match suggestion.action_type {
-            crate::app::ActionType::Replace => "🔄",
-            crate::app::ActionType::Insert => "➕",
-            crate::app::ActionType::Delete => "❌",
-            crate::app::ActionType::Refactor => "🔧",
-            crate::app::ActionType::Optimize => "⚡",
-            crate::app::ActionType::Fix => "🩹",
+    let mut rows = Vec::new();
+    for file in files {
+        let members: Vec<usize> = (0..thoughts.len()).filter(|&i| thought_group(&thoughts[i]) == file).collect();
+        let collapsed = grouping.collapsed.contains(file);
+        rows.push(GroupedRow::Header { file, count: members.len(), collapsed });
+        if !collapsed {
+            rows.extend(members.into_iter().map(GroupedRow::Thought));
+        }
+    }
+    rows
+}
+
+/// Pairs each row with the thoughts it shows. With `compact`, a thought of the same type
+/// and nearly the same content as the one right above it joins that row, which then
+/// shows the newest of its run; headers always start a new run.
+fn compact_rows<'a>(rows: Vec<GroupedRow<'a>>, thoughts: &[Thought], compact: bool) -> Vec<(GroupedRow<'a>, Vec<usize>)> {
+    let mut compacted: Vec<(GroupedRow<'a>, Vec<usize>)> = Vec::new();
+    for row in rows {
+        let GroupedRow::Thought(offset) = row else {
+            compacted.push((row, Vec::new()));
+            continue;
         };
 
-        vec![
-            Span::styled(
-                format!("  {}. {} {} ", index, priority_icon, action_icon),
-                Style::default().fg(Color::Cyan),
-            ),
-            Span::styled(
-                suggestion.title.clone(),
-                Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
-            ),
-            Span::styled(
-                format!(" - {}", suggestion.description),
-                Style::default().fg(Color::Gray),
-            ),
-        ].into()
+        if let Some((GroupedRow::Thought(last), run)) = compacted.last_mut() {
+            if compact && similar_thoughts(&thoughts[*last], &thoughts[offset]) {
+                *last = offset;
+                run.push(offset);
+                continue;
+            }
+        }
+        compacted.push((row, vec![offset]));
+    }
+    compacted
+}
+
+fn similar_thoughts(a: &Thought, b: &Thought) -> bool {
+    a.thought_type == b.thought_type
+        && a.file_path == b.file_path
+        && content_similarity(&a.content, &b.content) >= SIMILAR_THOUGHT_RATIO
+}
+
+/// 1 minus the Levenshtein distance over the longer length, comparing at most
+/// `SIMILARITY_MAX_CHARS` of each
+fn content_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().take(SIMILARITY_MAX_CHARS).collect();
+    let b: Vec<char> = b.chars().take(SIMILARITY_MAX_CHARS).collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    // The length difference alone already rules out most unrelated pairs
+    let bound = 1.0 - a.len().abs_diff(b.len()) as f64 / longest as f64;
+    if bound < SIMILAR_THOUGHT_RATIO {
+        return bound;
     }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    1.0 - previous[b.len()] as f64 / longest as f64
 }
 
 impl<'a> Widget for ThoughtsWidget<'a> {
@@ -452,8 +703,42 @@ impl<'a> Widget for SuggestionWidget<'a> {
     }
 }
 
+/// A single indented suggestion line, styled by priority and action type
+pub fn suggestion_line(suggestion: &Suggestion, index: usize) -> Line<'static> {
+    let priority_icon = match suggestion.priority {
+        crate::app::Priority::Critical => "🔥",
+        crate::app::Priority::High => "⚡",
+        crate::app::Priority::Medium => "💡",
+        crate::app::Priority::Low => "💭",
+    };
+
+    let action_icon = match suggestion.action_type {
+        crate::app::ActionType::Replace => "🔄",
+        crate::app::ActionType::Insert => "➕",
+        crate::app::ActionType::Delete => "❌",
+        crate::app::ActionType::Refactor => "🔧",
+        crate::app::ActionType::Optimize => "⚡",
+        crate::app::ActionType::Fix => "🩹",
+    };
+
+    vec![
+        Span::styled(
+            format!("  {}. {} {} ", index, priority_icon, action_icon),
+            Style::default().fg(Color::Cyan),
+        ),
+        Span::styled(
+            suggestion.title.clone(),
+            Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!(" - {}", suggestion.description),
+            Style::default().fg(Color::Gray),
+        ),
+    ].into()
+}
+
 // Helper functions for thought styling
-fn get_thought_icon(thought_type: &ThoughtType) -> &'static str {
+pub fn get_thought_icon(thought_type: &ThoughtType) -> &'static str {
     match thought_type {
         ThoughtType::Analyzing => "🔍",
         ThoughtType::Suggesting => "💡",
@@ -483,12 +768,46 @@ fn get_thought_color(thought_type: &ThoughtType) -> Color {
     }
 }
 
+/// Files offered by the file picker and the highlighted entry
+#[derive(Debug, Clone, Default)]
+pub struct FilePicker {
+    pub title: &'static str,
+    pub files: Vec<PathBuf>,
+    pub selected: usize,
+}
+
+impl FilePicker {
+    pub fn new(files: Vec<PathBuf>) -> Self {
+        Self::with_title(files, "Open file")
+    }
+
+    pub fn with_title(files: Vec<PathBuf>, title: &'static str) -> Self {
+        Self { title, files, selected: 0 }
+    }
+
+    /// Moves the highlight by `delta` entries, stopping at either end
+    pub fn move_selection(&mut self, delta: isize) {
+        let last = self.files.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    pub fn selected_file(&self) -> Option<&PathBuf> {
+        self.files.get(self.selected)
+    }
+}
+
 // Stateful widgets for scrolling and selection
 pub struct ScrollableThoughts {
     pub scroll_state: usize,
     pub selected_index: Option<usize>,
 }
 
+impl Default for ScrollableThoughts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ScrollableThoughts {
     pub fn new() -> Self {
         Self {
@@ -542,4 +861,104 @@ impl ScrollableThoughts {
             }
         };
     }
-}
\ No newline at end of file
+
+    /// Keeps the selection on the same item after `count` items were dropped
+    /// from the front of the list, clearing it if the selected item was dropped
+    pub fn remove_front(&mut self, count: usize) {
+        self.selected_index = self.selected_index.and_then(|i| i.checked_sub(count));
+    }
+
+    /// Moves the selection onto the next item after the item at `index` was removed,
+    /// or the new last one if it was last; `remaining` is the list's new length
+    pub fn remove(&mut self, index: usize, remaining: usize) {
+        self.selected_index = match self.selected_index {
+            _ if remaining == 0 => None,
+            Some(i) if i > index => Some(i - 1),
+            Some(i) => Some(i.min(remaining - 1)),
+            None => None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thought(file_path: Option<&str>) -> Thought {
+        Thought::new(
+            ThoughtType::Analyzing,
+            String::new(),
+            file_path.map(str::to_string),
+            None,
+            0.8,
+        )
+    }
+
+    #[test]
+    fn test_confidence_colors() {
+        let defaults = ConfidenceColors::default();
+        assert_eq!(defaults.color_for(0.8), Color::Green);
+        assert_eq!(defaults.color_for(0.7), Color::Yellow);
+        assert_eq!(defaults.color_for(0.1), Color::Red);
+
+        let theme = UiTheme {
+            confidence_high: 0.9,
+            confidence_medium: 0.5,
+            confidence_high_color: "blue".to_string(),
+            confidence_medium_color: "#e69f00".to_string(),
+            confidence_low_color: "not-a-color".to_string(),
+            ..UiTheme::default()
+        };
+        let colors = ConfidenceColors::from_theme(&theme);
+        assert_eq!(colors.color_for(0.85), Color::Rgb(0xe6, 0x9f, 0x00));
+        assert_eq!(colors.color_for(0.95), Color::Blue);
+        assert_eq!(colors.color_for(0.4), Color::Red);
+    }
+
+    #[test]
+    fn test_grouped_rows() {
+        let thoughts = vec![thought(Some("src/b.rs")), thought(None), thought(Some("src/a.rs")), thought(Some("src/b.rs"))];
+        let mut grouping = ThoughtGrouping { by_file: true, ..Default::default() };
+
+        assert_eq!(grouped_rows(&thoughts, &grouping), vec![
+            GroupedRow::Header { file: "src/a.rs", count: 1, collapsed: false },
+            GroupedRow::Thought(2),
+            GroupedRow::Header { file: "src/b.rs", count: 2, collapsed: false },
+            GroupedRow::Thought(0),
+            GroupedRow::Thought(3),
+            GroupedRow::Header { file: GENERAL_GROUP, count: 1, collapsed: false },
+            GroupedRow::Thought(1),
+        ]);
+
+        grouping.collapsed.insert("src/b.rs".to_string());
+        let rows = grouped_rows(&thoughts, &grouping);
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[2], GroupedRow::Header { file: "src/b.rs", count: 2, collapsed: true });
+    }
+
+    #[test]
+    fn test_compact_rows() {
+        let mut thoughts = vec![thought(Some("src/a.rs")), thought(Some("src/a.rs")), thought(Some("src/a.rs")), thought(Some("src/a.rs"))];
+        thoughts[0].content = "Consider handling the error from parse()".to_string();
+        thoughts[1].content = "Consider handling the error from parse() here".to_string();
+        thoughts[2].content = "Function is getting long; split it up".to_string();
+        thoughts[3].content = "Consider handling the error from parse()".to_string();
+
+        let rows: Vec<GroupedRow> = (0..thoughts.len()).map(GroupedRow::Thought).collect();
+        assert_eq!(compact_rows(rows.clone(), &thoughts, true), vec![
+            (GroupedRow::Thought(1), vec![0, 1]),
+            (GroupedRow::Thought(2), vec![2]),
+            (GroupedRow::Thought(3), vec![3]),
+        ]);
+        assert_eq!(compact_rows(rows, &thoughts, false).len(), 4);
+
+        // Same content but a different type stays separate
+        thoughts[1].content = thoughts[0].content.clone();
+        thoughts[1].thought_type = ThoughtType::Warning;
+        let rows: Vec<GroupedRow> = (0..2).map(GroupedRow::Thought).collect();
+        assert_eq!(compact_rows(rows, &thoughts, true).len(), 2);
+
+        assert_eq!(content_similarity("", ""), 1.0);
+        assert!(content_similarity("kitten", "sitting") < SIMILAR_THOUGHT_RATIO);
+    }
+}
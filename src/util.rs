@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Files that mark the top of a project
+const PROJECT_MARKERS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+];
+
+/// Version control directories, which mark the top of a repository
+const VCS_MARKERS: &[&str] = &[".git", ".hg"];
+
+/// Finds the project containing `start`. The nearest repository root wins, so
+/// workspace members resolve to the repository rather than their own manifest;
+/// otherwise the nearest directory with a known manifest is used. The walk stops
+/// below the home directory, whose repository usually tracks dotfiles.
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+    find_project_root_below(start, dirs::home_dir().as_deref())
+}
+
+fn find_project_root_below(start: &Path, boundary: Option<&Path>) -> Option<PathBuf> {
+    let mut nearest_manifest = None;
+
+    for dir in start.ancestors() {
+        if dir != start && Some(dir) == boundary {
+            break;
+        }
+
+        if VCS_MARKERS.iter().any(|marker| dir.join(marker).exists()) {
+            return Some(dir.to_path_buf());
+        }
+
+        if nearest_manifest.is_none() && PROJECT_MARKERS.iter().any(|marker| dir.join(marker).exists()) {
+            nearest_manifest = Some(dir.to_path_buf());
+        }
+    }
+
+    nearest_manifest
+}
+
+/// Root of the project CoCo was started in, falling back to the working directory.
+/// Computed once per process.
+pub fn project_root() -> &'static Path {
+    static PROJECT_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+    PROJECT_ROOT.get_or_init(|| {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        find_project_root(&cwd).unwrap_or(cwd)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_project_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path();
+        let member = repo.join("crates").join("core");
+        let src = member.join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(member.join("Cargo.toml"), "").unwrap();
+
+        // Without version control, the nearest manifest is the root
+        assert_eq!(find_project_root(&src), Some(member.clone()));
+
+        std::fs::create_dir(repo.join(".git")).unwrap();
+        assert_eq!(find_project_root(&src), Some(repo.to_path_buf()));
+
+        // A repository in the home directory doesn't swallow the projects below it
+        assert_eq!(find_project_root_below(&src, Some(repo)), Some(member.clone()));
+        assert_eq!(find_project_root_below(repo, Some(repo)), Some(repo.to_path_buf()));
+    }
+}
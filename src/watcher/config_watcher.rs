@@ -0,0 +1,72 @@
+use crate::error::{CocoError, Result};
+use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, sleep};
+
+pub struct ConfigWatcher {
+    _watcher: RecommendedWatcher,
+    config_path: PathBuf,
+    notify_rx: mpsc::Receiver<Event>,
+    debounce_delay: Duration,
+}
+
+impl ConfigWatcher {
+    pub fn new(config_path: PathBuf) -> Result<Self> {
+        let (notify_tx, notify_rx) = mpsc::channel(10);
+
+        let mut watcher = RecommendedWatcher::new(
+            move |result: Result<Event, notify::Error>| {
+                if let Ok(event) = result {
+                    let _ = notify_tx.blocking_send(event);
+                }
+            },
+            Config::default(),
+        ).map_err(CocoError::WatcherSetup)?;
+
+        // Watch the parent directory: most editors save by writing a temp file
+        // and renaming it over the original, which a file-level watch would miss
+        let config_dir = config_path
+            .parent()
+            .ok_or_else(|| CocoError::InvalidConfig(format!("Config path {} has no parent directory", config_path.display())))?;
+
+        watcher
+            .watch(config_dir, RecursiveMode::NonRecursive)
+            .map_err(|source| CocoError::Watch { path: config_dir.to_path_buf(), source })?;
+
+        tracing::info!("Watching config file for changes: {}", config_path.display());
+
+        Ok(Self {
+            _watcher: watcher,
+            config_path,
+            notify_rx,
+            debounce_delay: Duration::from_millis(200),
+        })
+    }
+
+    /// Waits until the config file changes. Returns `None` once the watcher shuts down.
+    pub async fn changed(&mut self) -> Option<()> {
+        loop {
+            let event = self.notify_rx.recv().await?;
+
+            if !self.is_config_event(&event) {
+                continue;
+            }
+
+            // A single save usually produces a burst of events; let it settle
+            sleep(self.debounce_delay).await;
+            while self.notify_rx.try_recv().is_ok() {}
+
+            return Some(());
+        }
+    }
+
+    fn is_config_event(&self, event: &Event) -> bool {
+        if event.kind.is_access() {
+            return false;
+        }
+
+        let config_name = self.config_path.file_name();
+        event.paths.iter().any(|path| path.file_name() == config_name)
+    }
+}
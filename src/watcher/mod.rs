@@ -1,23 +1,46 @@
 pub mod monitor;
+pub mod config_watcher;
 
-use anyhow::Result;
+use ignore::gitignore::Gitignore;
 use tokio::sync::mpsc;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::app::FileEvent;
+use crate::config::FileSizeLimits;
+use crate::error::Result;
+
+pub use config_watcher::ConfigWatcher;
+
+/// File at the project root listing, in gitignore syntax, paths CoCo should leave alone
+pub const COCOIGNORE_FILE: &str = ".cocoignore";
 
 pub struct FileMonitor {
     inner: monitor::FileWatcher,
 }
 
 impl FileMonitor {
-    pub async fn new(tx: mpsc::Sender<FileEvent>) -> Result<Self> {
-        let watcher = monitor::FileWatcher::new(tx).await?;
+    pub async fn new(
+        tx: mpsc::Sender<FileEvent>,
+        debounce_delay: Duration,
+        size_limits: FileSizeLimits,
+        exclude_dirs: Vec<String>,
+        cocoignore: Gitignore,
+        notify_capacity: usize,
+        dropped_events: Arc<AtomicU64>,
+    ) -> Result<Self> {
+        let mut watcher = monitor::FileWatcher::new(tx, notify_capacity, dropped_events).await?;
+        watcher.set_debounce_delay(debounce_delay);
+        watcher.set_size_limits(size_limits);
+        watcher.set_exclude_dirs(exclude_dirs);
+        watcher.set_cocoignore(cocoignore);
         Ok(Self { inner: watcher })
     }
 
-    pub async fn watch(&mut self, path: &Path) -> Result<()> {
-        self.inner.watch(path).await
+    pub async fn watch(&mut self, path: &Path, recursive: bool) -> Result<()> {
+        self.inner.watch(path, recursive).await
     }
 
     pub async fn unwatch(&mut self, path: &Path) -> Result<()> {
@@ -33,3 +56,44 @@ impl FileMonitor {
     }
 }
 
+/// The `.cocoignore` in `root`, which matches nothing when there is none. Invalid
+/// lines are logged and left out.
+pub fn load_cocoignore(root: &Path) -> Gitignore {
+    let path = root.join(COCOIGNORE_FILE);
+    if !path.is_file() {
+        return Gitignore::empty();
+    }
+
+    let (cocoignore, error) = Gitignore::new(&path);
+    if let Some(e) = error {
+        tracing::warn!("Problem reading {}: {}", path.display(), e);
+    }
+    tracing::debug!("Loaded {} pattern(s) from {}", cocoignore.len(), path.display());
+    cocoignore
+}
+
+/// Whether `cocoignore` excludes `path`, a file given absolute or relative to the project root
+pub fn is_cocoignored(path: &Path, cocoignore: &Gitignore) -> bool {
+    monitor::FileWatcher::is_cocoignored(path, false, cocoignore)
+}
+
+/// Files under `root` that the watcher would analyze, for choosing one to open
+pub fn list_files(root: &Path, limit: usize, exclude_dirs: &[String], cocoignore: &Gitignore) -> Vec<PathBuf> {
+    monitor::FileWatcher::list_files(root, limit, exclude_dirs, cocoignore)
+}
+
+/// Counts files under `root` that `include` accepts, stopping once `limit` is reached
+pub fn count_files(
+    root: &Path,
+    limit: usize,
+    exclude_dirs: &[String],
+    cocoignore: &Gitignore,
+    include: impl Fn(&Path) -> bool,
+) -> usize {
+    monitor::FileWatcher::walk_files(root, limit, exclude_dirs, cocoignore, include).len()
+}
+
+/// Reads a file the way the watcher does: refusing files over `max_size` or that look binary
+pub async fn read_file(path: &Path, max_size: u64) -> Result<String> {
+    monitor::FileWatcher::read_file_content(path, max_size).await
+}
@@ -1,74 +1,196 @@
-use anyhow::{anyhow, Result};
-use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::HashSet;
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use tokio::io::AsyncReadExt;
 use tokio::sync::{mpsc, Mutex};
 use tokio::time::{Duration, Instant, sleep};
 use chrono::Utc;
+use ignore::gitignore::Gitignore;
 
 use crate::app::FileEvent;
+use crate::config::FileSizeLimits;
+use crate::error::{CocoError, Result};
+
+/// How much of a file is inspected when deciding whether it is binary
+const BINARY_SNIFF_BYTES: u64 = 4 * 1024;
+
+/// How often the event loop checks whether notify reported an error and the watcher
+/// needs to be recreated
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Build, cache and tooling directories whose contents are never analyzed
+const SKIP_DIRECTORIES: &[&str] = &[
+    "target",
+    "node_modules",
+    ".git",
+    "build",
+    "dist",
+    "out",
+    "__pycache__",
+    ".pytest_cache",
+    ".vscode",
+    ".idea",
+];
+
+/// What the event loop remembers about files between events, keyed by real path
+#[derive(Default)]
+struct EventHistory {
+    /// When each file last produced an event, for debouncing
+    last_events: HashMap<PathBuf, Instant>,
+    /// The path each file was first reported under, so symlinked copies of it are
+    /// skipped whichever events they arrive in
+    reported_paths: HashMap<PathBuf, PathBuf>,
+}
 
 pub struct FileWatcher {
-    watcher: RecommendedWatcher,
+    /// Shared with the event loop, which swaps in a new watcher when this one fails
+    watcher: Arc<std::sync::Mutex<RecommendedWatcher>>,
     event_tx: mpsc::Sender<FileEvent>,
-    watched_paths: Arc<Mutex<HashSet<PathBuf>>>,
+    /// Every watched path with its mode, for re-registering them on a new watcher
+    watched_paths: Arc<Mutex<HashMap<PathBuf, RecursiveMode>>>,
     debounce_delay: Duration,
-    last_events: Arc<Mutex<std::collections::HashMap<PathBuf, Instant>>>,
+    size_limits: FileSizeLimits,
+    exclude_dirs: Vec<String>,
+    /// Patterns from the project's `.cocoignore`
+    cocoignore: Gitignore,
+    history: Arc<Mutex<EventHistory>>,
     running: Arc<Mutex<bool>>,
+    notify_tx: mpsc::Sender<Event>,
+    /// Set by the notify callback when it reports an error
+    watcher_failed: Arc<AtomicBool>,
+    /// Filesystem events dropped because the notify channel was full
+    dropped_events: Arc<AtomicU64>,
     _notify_rx: mpsc::Receiver<Event>,
 }
 
 impl FileWatcher {
-    pub async fn new(event_tx: mpsc::Sender<FileEvent>) -> Result<Self> {
-        let (notify_tx, notify_rx) = mpsc::channel(10);
-        let watched_paths = Arc::new(Mutex::new(HashSet::new()));
-        let last_events = Arc::new(Mutex::new(std::collections::HashMap::new()));
+    /// `notify_capacity` bounds the events waiting between notify's thread and the
+    /// event loop; events arriving while it is full are counted in `dropped_events`
+    pub async fn new(event_tx: mpsc::Sender<FileEvent>, notify_capacity: usize, dropped_events: Arc<AtomicU64>) -> Result<Self> {
+        let (notify_tx, notify_rx) = mpsc::channel(notify_capacity.max(1));
+        let watched_paths = Arc::new(Mutex::new(HashMap::new()));
         let running = Arc::new(Mutex::new(false));
+        let watcher_failed = Arc::new(AtomicBool::new(false));
 
         // Create the file system watcher
-        let notify_tx_clone = notify_tx.clone();
-        let watcher = RecommendedWatcher::new(
-            move |result: Result<Event, notify::Error>| {
-                if let Ok(event) = result {
-                    let _ = notify_tx_clone.blocking_send(event);
-                }
-            },
-            Config::default(),
-        ).map_err(|e| anyhow!("Failed to create file watcher: {}", e))?;
+        let watcher = Self::create_watcher(notify_tx.clone(), watcher_failed.clone(), dropped_events.clone())?;
 
         Ok(Self {
-            watcher,
+            watcher: Arc::new(std::sync::Mutex::new(watcher)),
             event_tx,
             watched_paths,
             debounce_delay: Duration::from_millis(300),
-            last_events,
+            size_limits: FileSizeLimits {
+                default: 8 * 1024, // 8KB
+                by_extension: std::collections::HashMap::new(),
+            },
+            exclude_dirs: Vec::new(),
+            cocoignore: Gitignore::empty(),
+            history: Arc::default(),
             running,
+            notify_tx,
+            watcher_failed,
+            dropped_events,
             _notify_rx: notify_rx,
         })
     }
 
-    pub async fn watch(&mut self, path: &Path) -> Result<()> {
-        tracing::info!("Starting to watch path: {}", path.display());
+    /// A notify watcher forwarding its events to `notify_tx` and raising `failed` on errors.
+    /// Events that don't fit in the channel are dropped rather than blocking notify's thread.
+    fn create_watcher(
+        notify_tx: mpsc::Sender<Event>,
+        failed: Arc<AtomicBool>,
+        dropped: Arc<AtomicU64>,
+    ) -> Result<RecommendedWatcher> {
+        RecommendedWatcher::new(
+            move |result: Result<Event, notify::Error>| match result {
+                Ok(event) => Self::forward_event(&notify_tx, &dropped, event),
+                Err(e) => {
+                    tracing::warn!("File watcher error: {}", e);
+                    failed.store(true, Ordering::Relaxed);
+                }
+            },
+            Config::default(),
+        ).map_err(CocoError::WatcherSetup)
+    }
+
+    /// Passes `event` on without blocking, counting it in `dropped` if the channel is full
+    fn forward_event(notify_tx: &mpsc::Sender<Event>, dropped: &AtomicU64, event: Event) {
+        if let Err(mpsc::error::TrySendError::Full(event)) = notify_tx.try_send(event) {
+            let total = dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            // Bursts drop many at once; log the first and then every hundredth
+            if total == 1 || total.is_multiple_of(100) {
+                tracing::warn!("Notify channel full, dropped {} filesystem event(s) so far (latest: {:?})", total, event.paths);
+            }
+        }
+    }
+
+    /// Replaces a watcher that reported an error with a new one watching the same
+    /// paths, since notify may silently stop delivering events after one. Paths that
+    /// can no longer be watched, e.g. because they were deleted, are dropped.
+    async fn restart_watcher(
+        watcher: &std::sync::Mutex<RecommendedWatcher>,
+        notify_tx: &mpsc::Sender<Event>,
+        failed: &Arc<AtomicBool>,
+        dropped: &Arc<AtomicU64>,
+        watched_paths: &Mutex<HashMap<PathBuf, RecursiveMode>>,
+    ) -> Result<()> {
+        let mut paths = watched_paths.lock().await;
+        let mut replacement = Self::create_watcher(notify_tx.clone(), failed.clone(), dropped.clone())?;
+        paths.retain(|path, mode| match replacement.watch(path, *mode) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("No longer watching {}: {}", path.display(), e);
+                false
+            }
+        });
+
+        *watcher.lock().unwrap() = replacement;
+        tracing::warn!("Restarted the file watcher after an error, watching {} path(s) again", paths.len());
+        Ok(())
+    }
 
-        self.watcher
-            .watch(path, RecursiveMode::Recursive)
-            .map_err(|e| anyhow!("Failed to watch path {}: {}", path.display(), e))?;
+    /// Watches `path`, and everything below it when `recursive` is set. With excluded
+    /// directories, the tree is watched around them rather than with one recursive watch.
+    pub async fn watch(&mut self, path: &Path, recursive: bool) -> Result<()> {
+        tracing::info!("Starting to watch path: {}", path.display());
 
-        self.watched_paths.lock().await.insert(path.to_path_buf());
+        let targets = match (recursive, self.exclude_dirs.is_empty()) {
+            (true, false) => Self::watch_plan(path, &self.exclude_dirs),
+            (true, true) => vec![(path.to_path_buf(), RecursiveMode::Recursive)],
+            (false, _) => vec![(path.to_path_buf(), RecursiveMode::NonRecursive)],
+        };
+
+        let mut watched_paths = self.watched_paths.lock().await;
+        for (target, mode) in targets {
+            self.watcher
+                .lock()
+                .unwrap()
+                .watch(&target, mode)
+                .map_err(|source| CocoError::Watch { path: target.clone(), source })?;
+            watched_paths.insert(target, mode);
+        }
 
-        tracing::info!("Successfully watching path: {}", path.display());
+        tracing::info!("Successfully watching path: {} ({} watch(es) in total)", path.display(), watched_paths.len());
         Ok(())
     }
 
+    /// Stops watching `path` and any directories below it that were watched for it
     pub async fn unwatch(&mut self, path: &Path) -> Result<()> {
         tracing::info!("Stopping watch on path: {}", path.display());
 
-        self.watcher
-            .unwatch(path)
-            .map_err(|e| anyhow!("Failed to unwatch path {}: {}", path.display(), e))?;
-
-        self.watched_paths.lock().await.remove(path);
+        let mut watched_paths = self.watched_paths.lock().await;
+        let targets: Vec<PathBuf> = watched_paths.keys().filter(|watched| watched.starts_with(path)).cloned().collect();
+        for target in targets {
+            self.watcher
+                .lock()
+                .unwrap()
+                .unwatch(&target)
+                .map_err(|source| CocoError::Watch { path: target.clone(), source })?;
+            watched_paths.remove(&target);
+        }
 
         tracing::info!("Successfully unwatched path: {}", path.display());
         Ok(())
@@ -85,21 +207,36 @@ impl FileWatcher {
         });
 
         let event_tx = self.event_tx.clone();
-        let last_events = self.last_events.clone();
+        let history = self.history.clone();
         let debounce_delay = self.debounce_delay;
+        let size_limits = self.size_limits.clone();
+        let exclude_dirs = self.exclude_dirs.clone();
+        let cocoignore = self.cocoignore.clone();
         let running = self.running.clone();
+        let watcher = self.watcher.clone();
+        let notify_tx = self.notify_tx.clone();
+        let watcher_failed = self.watcher_failed.clone();
+        let dropped_events = self.dropped_events.clone();
+        let watched_paths = self.watched_paths.clone();
 
         // Spawn the event processing task
         let event_processor = tokio::spawn(async move {
+            let mut watchdog = tokio::time::interval(WATCHDOG_INTERVAL);
             while *running.lock().await {
                 tokio::select! {
                     event = notify_rx.recv() => {
                         if let Some(event) = event {
+                            if matches!(event.kind, EventKind::Create(_)) && !exclude_dirs.is_empty() {
+                                Self::watch_new_dirs(&event.paths, &watcher, &watched_paths, &exclude_dirs).await;
+                            }
                             if let Err(e) = Self::process_notify_event(
                                 event,
                                 &event_tx,
-                                &last_events,
-                                debounce_delay
+                                &history,
+                                debounce_delay,
+                                &size_limits,
+                                &exclude_dirs,
+                                &cocoignore,
                             ).await {
                                 tracing::error!("Error processing file event: {}", e);
                             }
@@ -108,6 +245,14 @@ impl FileWatcher {
                             break;
                         }
                     }
+                    _ = watchdog.tick() => {
+                        if watcher_failed.swap(false, Ordering::Relaxed) {
+                            if let Err(e) = Self::restart_watcher(&watcher, &notify_tx, &watcher_failed, &dropped_events, &watched_paths).await {
+                                tracing::error!("Failed to restart file watcher: {}", e);
+                                watcher_failed.store(true, Ordering::Relaxed);
+                            }
+                        }
+                    }
                     _ = sleep(Duration::from_millis(100)) => {
                         // Periodic check to keep the loop alive
                     }
@@ -116,7 +261,7 @@ impl FileWatcher {
         });
 
         // Wait for the processor to complete
-        event_processor.await.map_err(|e| anyhow!("Event processor task failed: {}", e))?;
+        event_processor.await?;
 
         tracing::info!("File watcher event loop stopped");
         Ok(())
@@ -127,7 +272,7 @@ impl FileWatcher {
         *self.running.lock().await = false;
 
         // Unwatch all paths
-        let paths: Vec<_> = self.watched_paths.lock().await.clone().into_iter().collect();
+        let paths: Vec<_> = self.watched_paths.lock().await.keys().cloned().collect();
         for path in paths {
             if let Err(e) = self.unwatch(&path).await {
                 tracing::warn!("Failed to unwatch path {}: {}", path.display(), e);
@@ -141,33 +286,65 @@ impl FileWatcher {
     async fn process_notify_event(
         event: Event,
         event_tx: &mpsc::Sender<FileEvent>,
-        last_events: &Arc<Mutex<std::collections::HashMap<PathBuf, Instant>>>,
+        history: &Mutex<EventHistory>,
         debounce_delay: Duration,
+        size_limits: &FileSizeLimits,
+        exclude_dirs: &[String],
+        cocoignore: &Gitignore,
     ) -> Result<()> {
         tracing::debug!("Processing notify event: {:?}", event);
 
         for path in &event.paths {
             // Check if we should process this file
-            if !Self::should_process_file(path) {
+            if !Self::should_process_file(path)
+                || Self::is_excluded(path, exclude_dirs)
+                || Self::is_cocoignored(path, false, cocoignore)
+            {
                 tracing::debug!("Skipping file: {}", path.display());
                 continue;
             }
 
-            // Debounce rapid file changes
-            let now = Instant::now();
+            // Resolving symlinks touches the filesystem once per ancestor, so keep it off the event loop
+            let resolved = {
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || {
+                    let real_path = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                    (Self::symlink_cycle(&path), real_path)
+                })
+                .await
+            };
+            let real_path = match resolved {
+                Ok((Some(link), _)) => {
+                    tracing::warn!("Symlink cycle at {}, skipping {}", link.display(), path.display());
+                    continue;
+                }
+                Ok((None, real_path)) => real_path,
+                Err(e) => {
+                    tracing::warn!("Failed to resolve {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+
             {
-                let mut last_events_map = last_events.lock().await;
-                if let Some(&last_time) = last_events_map.get(path) {
+                let mut history = history.lock().await;
+                if !Self::is_first_reported_path(&mut history.reported_paths, &real_path, path).await {
+                    tracing::debug!("Skipping symlinked duplicate: {}", path.display());
+                    continue;
+                }
+
+                // Debounce rapid file changes, keyed by real path so symlinked copies share a window
+                let now = Instant::now();
+                if let Some(&last_time) = history.last_events.get(&real_path) {
                     if now.duration_since(last_time) < debounce_delay {
                         tracing::debug!("Debouncing file event for: {}", path.display());
                         continue;
                     }
                 }
-                last_events_map.insert(path.clone(), now);
+                history.last_events.insert(real_path, now);
             }
 
             // Read file content
-            match Self::read_file_content(path).await {
+            match Self::read_file_content(path, size_limits.limit_for(path)).await {
                 Ok(content) => {
                     let file_event = FileEvent {
                         path: path.clone(),
@@ -191,7 +368,148 @@ impl FileWatcher {
         Ok(())
     }
 
-    fn should_process_file(path: &Path) -> bool {
+    /// Whether `path` is the path `real_path` is known by: the one it was first reported
+    /// under, or a replacement for that path once it no longer exists
+    async fn is_first_reported_path(reported_paths: &mut HashMap<PathBuf, PathBuf>, real_path: &Path, path: &Path) -> bool {
+        if let Some(first) = reported_paths.get(real_path) {
+            if first == path || tokio::fs::try_exists(first).await.unwrap_or(false) {
+                return first == path;
+            }
+        }
+
+        reported_paths.insert(real_path.to_path_buf(), path.to_path_buf());
+        true
+    }
+
+    /// The first directory above `path` that is a symlink back to one of its own
+    /// ancestors, which would make the tree below it repeat forever
+    pub(crate) fn symlink_cycle(path: &Path) -> Option<PathBuf> {
+        path.ancestors()
+            .skip(1)
+            .filter(|dir| dir.is_symlink())
+            .find(|link| {
+                let target = std::fs::canonicalize(link);
+                let parent = link.parent().map(std::fs::canonicalize);
+                matches!((target, parent), (Ok(target), Some(Ok(parent))) if parent.starts_with(&target))
+            })
+            .map(Path::to_path_buf)
+    }
+
+    /// Where to register watches so `root`'s tree is covered without watching inside
+    /// excluded, hidden or build directories: subtrees free of them get one recursive
+    /// watch, and each directory above one gets a non-recursive watch
+    pub(crate) fn watch_plan(root: &Path, exclude_dirs: &[String]) -> Vec<(PathBuf, RecursiveMode)> {
+        let (plan, _) = Self::plan_dir(root, exclude_dirs);
+        plan
+    }
+
+    /// The watches for `dir`, and whether anything below it was left out
+    fn plan_dir(dir: &Path, exclude_dirs: &[String]) -> (Vec<(PathBuf, RecursiveMode)>, bool) {
+        let mut children = Vec::new();
+        let mut pruned = false;
+
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::debug!("Cannot list {}: {}", dir.display(), e);
+                return (vec![(dir.to_path_buf(), RecursiveMode::Recursive)], false);
+            }
+        };
+        for entry in entries.flatten() {
+            if !entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                continue;
+            }
+
+            let path = entry.path();
+            if Self::is_skipped_dir(&path, exclude_dirs) {
+                pruned = true;
+                continue;
+            }
+
+            let (plan, child_pruned) = Self::plan_dir(&path, exclude_dirs);
+            pruned |= child_pruned;
+            children.extend(plan);
+        }
+
+        if !pruned {
+            return (vec![(dir.to_path_buf(), RecursiveMode::Recursive)], false);
+        }
+
+        let mut plan = vec![(dir.to_path_buf(), RecursiveMode::NonRecursive)];
+        plan.extend(children);
+        (plan, true)
+    }
+
+    /// Watches directories created under a non-recursive watch, which notify would
+    /// otherwise never report changes in
+    async fn watch_new_dirs(
+        paths: &[PathBuf],
+        watcher: &std::sync::Mutex<RecommendedWatcher>,
+        watched_paths: &Mutex<HashMap<PathBuf, RecursiveMode>>,
+        exclude_dirs: &[String],
+    ) {
+        let mut watched = watched_paths.lock().await;
+        for path in paths {
+            let covered = path.ancestors().any(|dir| watched.get(dir) == Some(&RecursiveMode::Recursive));
+            if covered || !path.is_dir() || Self::is_skipped_dir(path, exclude_dirs) {
+                continue;
+            }
+
+            for (target, mode) in Self::watch_plan(path, exclude_dirs) {
+                match watcher.lock().unwrap().watch(&target, mode) {
+                    Ok(()) => {
+                        tracing::debug!("Watching new directory {}", target.display());
+                        watched.insert(target, mode);
+                    }
+                    Err(e) => tracing::warn!("Failed to watch new directory {}: {}", target.display(), e),
+                }
+            }
+        }
+    }
+
+    /// Whether the watcher never looks inside `dir`: hidden, build and excluded directories
+    fn is_skipped_dir(dir: &Path, exclude_dirs: &[String]) -> bool {
+        let name = dir.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+        name.starts_with('.') || SKIP_DIRECTORIES.contains(&name.as_str()) || Self::is_excluded_dir(dir, exclude_dirs)
+    }
+
+    /// Whether the file at `path` lies in one of `exclude_dirs`
+    pub(crate) fn is_excluded(path: &Path, exclude_dirs: &[String]) -> bool {
+        path.parent().is_some_and(|dir| Self::is_excluded_dir(dir, exclude_dirs))
+    }
+
+    /// Whether `dir` is, or is inside, one of `exclude_dirs`: a bare name matches a
+    /// directory of that name at any depth, anything with a `/` the path from the project root
+    fn is_excluded_dir(dir: &Path, exclude_dirs: &[String]) -> bool {
+        let relative = dir.strip_prefix(crate::util::project_root()).unwrap_or(dir);
+        let dirs = relative.strip_prefix(".").unwrap_or(relative);
+
+        exclude_dirs.iter().any(|excluded| {
+            if excluded.contains('/') {
+                dirs.starts_with(excluded)
+            } else {
+                dirs.components().any(|dir| dir.as_os_str() == excluded.as_str())
+            }
+        })
+    }
+
+    /// Whether `cocoignore` ignores `path` or one of the directories it is in. Relative
+    /// paths are taken from the project root; paths outside it are never ignored.
+    pub(crate) fn is_cocoignored(path: &Path, is_dir: bool, cocoignore: &Gitignore) -> bool {
+        if cocoignore.is_empty() {
+            return false;
+        }
+
+        let relative = match path.strip_prefix(cocoignore.path()) {
+            Ok(relative) => relative,
+            // The matcher panics on paths it can't make relative to its root
+            Err(_) if path.has_root() => return false,
+            Err(_) => path.strip_prefix(".").unwrap_or(path),
+        };
+        cocoignore.matched_path_or_any_parents(relative, is_dir).is_ignore()
+    }
+
+    pub(crate) fn should_process_file(path: &Path) -> bool {
         // Skip hidden files and directories
         if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
             if name.starts_with('.') {
@@ -199,25 +517,12 @@ impl FileWatcher {
             }
         }
 
-        // Skip common build/cache directories
-        let path_str = path.to_string_lossy().to_lowercase();
-        let skip_patterns = [
-            "target/",
-            "node_modules/",
-            ".git/",
-            "build/",
-            "dist/",
-            "out/",
-            "__pycache__/",
-            ".pytest_cache/",
-            ".vscode/",
-            ".idea/",
-        ];
-
-        for pattern in &skip_patterns {
-            if path_str.contains(pattern) {
-                return false;
-            }
+        // Skip common build/cache directories inside the project, without tripping
+        // over a project that itself lives under e.g. ~/build/
+        let relative = path.strip_prefix(crate::util::project_root()).unwrap_or(path);
+        let path_str = relative.to_string_lossy().to_lowercase();
+        if SKIP_DIRECTORIES.iter().any(|dir| path_str.contains(&format!("{}/", dir))) {
+            return false;
         }
 
         // Skip temporary and backup files
@@ -261,29 +566,97 @@ impl FileWatcher {
         false
     }
 
-    async fn read_file_content(path: &Path) -> Result<String> {
+    /// Walks `root` for files the watcher would process, skipping hidden, build,
+    /// excluded and `.cocoignore`d paths, and returns at most `limit` of them sorted by path
+    pub(crate) fn list_files(root: &Path, limit: usize, exclude_dirs: &[String], cocoignore: &Gitignore) -> Vec<PathBuf> {
+        Self::walk_files(root, limit, exclude_dirs, cocoignore, Self::should_process_file)
+    }
+
+    /// Like `list_files`, but keeps the files `include` accepts instead
+    pub(crate) fn walk_files(
+        root: &Path,
+        limit: usize,
+        exclude_dirs: &[String],
+        cocoignore: &Gitignore,
+        include: impl Fn(&Path) -> bool,
+    ) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let mut pending = vec![root.to_path_buf()];
+
+        while let Some(dir) = pending.pop() {
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    tracing::debug!("Cannot list {}: {}", dir.display(), e);
+                    continue;
+                }
+            };
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+
+                let Ok(file_type) = entry.file_type() else {
+                    continue;
+                };
+
+                if file_type.is_dir() {
+                    if !Self::is_skipped_dir(&path, exclude_dirs) && !Self::is_cocoignored(&path, true, cocoignore) {
+                        pending.push(path);
+                    }
+                } else if file_type.is_file() && !Self::is_cocoignored(&path, false, cocoignore) && include(&path) {
+                    files.push(path);
+                }
+            }
+
+            if files.len() >= limit {
+                tracing::debug!("Stopped listing files at {}", limit);
+                break;
+            }
+        }
+
+        files.sort();
+        files.truncate(limit);
+        files
+    }
+
+    pub(crate) async fn read_file_content(path: &Path, max_size: u64) -> Result<String> {
         // Check file size first to avoid reading huge files
-        let metadata = tokio::fs::metadata(path).await
-            .map_err(|e| anyhow!("Failed to read file metadata: {}", e))?;
+        let metadata = tokio::fs::metadata(path).await?;
+
+        if metadata.len() > max_size {
+            return Err(CocoError::FileTooLarge { size: metadata.len(), limit: max_size });
+        }
+
+        let mut file = tokio::fs::File::open(path).await?;
+
+        // Sniff a prefix first so binary files are rejected without a full read
+        let mut bytes = Vec::with_capacity(metadata.len() as usize);
+        (&mut file).take(BINARY_SNIFF_BYTES).read_to_end(&mut bytes).await?;
 
-        const MAX_FILE_SIZE: u64 = 8 * 1024; // 8KB
-        if metadata.len() > MAX_FILE_SIZE {
-            return Err(anyhow!("File too large: {} bytes", metadata.len()));
+        if Self::looks_binary(&bytes) {
+            return Err(CocoError::BinaryFile);
         }
 
-        let content = tokio::fs::read_to_string(path).await
-            .map_err(|e| anyhow!("Failed to read file content: {}", e))?;
+        file.read_to_end(&mut bytes).await?;
 
-        // Basic validation that this is likely text content
-        if content.chars().take(1000).any(|c| c.is_control() && c != '\n' && c != '\r' && c != '\t') {
-            return Err(anyhow!("File appears to contain binary data"));
+        // Still analyze files with stray non-UTF-8 bytes (e.g. a Latin-1 comment)
+        match String::from_utf8(bytes) {
+            Ok(content) => Ok(content),
+            Err(e) => {
+                tracing::debug!("{} is not valid UTF-8 ({}), decoding lossily", path.display(), e.utf8_error());
+                Ok(String::from_utf8_lossy(e.as_bytes()).into_owned())
+            }
         }
+    }
 
-        Ok(content)
+    /// Same heuristic git uses: text files practically never contain NUL bytes,
+    /// while nearly every binary format does near the start
+    fn looks_binary(prefix: &[u8]) -> bool {
+        prefix.contains(&0)
     }
 
     pub async fn get_watched_paths(&self) -> Vec<PathBuf> {
-        self.watched_paths.lock().await.iter().cloned().collect()
+        self.watched_paths.lock().await.keys().cloned().collect()
     }
 
     pub async fn is_running(&self) -> bool {
@@ -293,6 +666,18 @@ impl FileWatcher {
     pub fn set_debounce_delay(&mut self, delay: Duration) {
         self.debounce_delay = delay;
     }
+
+    pub fn set_size_limits(&mut self, limits: FileSizeLimits) {
+        self.size_limits = limits;
+    }
+
+    pub fn set_exclude_dirs(&mut self, exclude_dirs: Vec<String>) {
+        self.exclude_dirs = exclude_dirs;
+    }
+
+    pub fn set_cocoignore(&mut self, cocoignore: Gitignore) {
+        self.cocoignore = cocoignore;
+    }
 }
 
 impl Drop for FileWatcher {
@@ -310,7 +695,7 @@ mod tests {
     #[tokio::test]
     async fn test_file_watcher_creation() {
         let (tx, _rx) = mpsc::channel(10);
-        let watcher = FileWatcher::new(tx).await;
+        let watcher = FileWatcher::new(tx, 10, Arc::default()).await;
         assert!(watcher.is_ok());
     }
 
@@ -323,13 +708,139 @@ mod tests {
         assert!(!FileWatcher::should_process_file(Path::new("file.tmp")));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_cycle() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        std::os::unix::fs::symlink(root, root.join("src/loop")).unwrap();
+        std::os::unix::fs::symlink(root.join("src"), root.join("alias")).unwrap();
+
+        let looped = root.join("src/loop/src/main.rs");
+        assert_eq!(FileWatcher::symlink_cycle(&looped), Some(root.join("src/loop")));
+        assert_eq!(FileWatcher::symlink_cycle(&root.join("src/main.rs")), None);
+        assert_eq!(FileWatcher::symlink_cycle(&root.join("alias/main.rs")), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinked_duplicates_skipped_across_events() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("real.rs"), "fn main() {}").unwrap();
+        std::os::unix::fs::symlink(root.join("real.rs"), root.join("alias.rs")).unwrap();
+
+        let (tx, mut rx) = mpsc::channel(10);
+        let history = Mutex::new(EventHistory::default());
+        let size_limits = FileSizeLimits { default: 8 * 1024, by_extension: HashMap::new() };
+
+        // Each path arrives in its own event, as notify reports them
+        for name in ["real.rs", "alias.rs", "real.rs"] {
+            let event = Event::new(EventKind::Any).add_path(root.join(name));
+            FileWatcher::process_notify_event(event, &tx, &history, Duration::ZERO, &size_limits, &[], &Gitignore::empty())
+                .await
+                .unwrap();
+        }
+        drop(tx);
+
+        let mut reported = Vec::new();
+        while let Some(event) = rx.recv().await {
+            reported.push(event.path);
+        }
+        assert_eq!(reported, vec![root.join("real.rs"), root.join("real.rs")]);
+    }
+
+    #[test]
+    fn test_list_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("node_modules").join("dep")).unwrap();
+        fs::write(root.join("src").join("main.rs"), "").unwrap();
+        fs::write(root.join("src").join("lib.rs"), "").unwrap();
+        fs::write(root.join("notes.txt"), "").unwrap();
+        fs::write(root.join("node_modules").join("dep").join("index.js"), "").unwrap();
+
+        let none = Gitignore::empty();
+        let files = FileWatcher::list_files(root, 100, &[], &none);
+        assert_eq!(files, vec![root.join("src").join("lib.rs"), root.join("src").join("main.rs")]);
+        assert_eq!(FileWatcher::list_files(root, 1, &[], &none).len(), 1);
+        assert!(FileWatcher::list_files(root, 100, &["src".to_string()], &none).is_empty());
+
+        let text_files = FileWatcher::walk_files(root, 100, &[], &none, |path| path.extension().is_some_and(|ext| ext == "txt"));
+        assert_eq!(text_files, vec![root.join("notes.txt")]);
+    }
+
+    #[test]
+    fn test_cocoignore() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src").join("generated")).unwrap();
+        fs::write(root.join("src").join("main.rs"), "").unwrap();
+        fs::write(root.join("src").join("generated").join("schema.rs"), "").unwrap();
+        fs::write(root.join("bench.rs"), "").unwrap();
+        fs::write(root.join(".cocoignore"), "# generated code\nsrc/generated/\n/bench.rs\n").unwrap();
+
+        let cocoignore = crate::watcher::load_cocoignore(root);
+        assert!(FileWatcher::is_cocoignored(&root.join("src/generated/schema.rs"), false, &cocoignore));
+        assert!(FileWatcher::is_cocoignored(Path::new("./bench.rs"), false, &cocoignore));
+        assert!(!FileWatcher::is_cocoignored(Path::new("src/bench.rs"), false, &cocoignore));
+        assert!(!FileWatcher::is_cocoignored(Path::new("/elsewhere/bench.rs"), false, &cocoignore));
+        assert_eq!(FileWatcher::list_files(root, 100, &[], &cocoignore), vec![root.join("src").join("main.rs")]);
+
+        // Negated patterns re-include what an earlier line ignored
+        fs::write(root.join(".cocoignore"), "src/generated/*.rs\n!keep.rs\n").unwrap();
+        let cocoignore = crate::watcher::load_cocoignore(root);
+        assert!(FileWatcher::is_cocoignored(&root.join("src/generated/schema.rs"), false, &cocoignore));
+        assert!(!FileWatcher::is_cocoignored(&root.join("src/generated/keep.rs"), false, &cocoignore));
+
+        assert!(crate::watcher::load_cocoignore(&root.join("src")).is_empty());
+    }
+
+    #[test]
+    fn test_watch_plan_skips_excluded_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for dir in ["src/ui", "vendor/dep", "crates/api/src", "crates/api/vendor", "crates/core/src", ".git/objects"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+
+        let mut plan = FileWatcher::watch_plan(root, &["vendor".to_string()]);
+        plan.sort();
+        assert_eq!(plan, vec![
+            (root.to_path_buf(), RecursiveMode::NonRecursive),
+            (root.join("crates"), RecursiveMode::NonRecursive),
+            (root.join("crates/api"), RecursiveMode::NonRecursive),
+            (root.join("crates/api/src"), RecursiveMode::Recursive),
+            (root.join("crates/core"), RecursiveMode::Recursive),
+            (root.join("src"), RecursiveMode::Recursive),
+        ]);
+
+        // Without exclusions below it, a directory is a single recursive watch
+        assert_eq!(FileWatcher::watch_plan(&root.join("src"), &["vendor".to_string()]), vec![(root.join("src"), RecursiveMode::Recursive)]);
+    }
+
+    #[test]
+    fn test_is_excluded() {
+        let exclude_dirs = vec!["vendor".to_string(), "src/generated".to_string()];
+
+        assert!(FileWatcher::is_excluded(Path::new("vendor/lib.rs"), &exclude_dirs));
+        assert!(FileWatcher::is_excluded(Path::new("./crates/api/vendor/dep/lib.rs"), &exclude_dirs));
+        assert!(FileWatcher::is_excluded(Path::new("src/generated/schema.rs"), &exclude_dirs));
+        assert!(!FileWatcher::is_excluded(Path::new("crates/src/generated/schema.rs"), &exclude_dirs));
+        assert!(!FileWatcher::is_excluded(Path::new("src/vendored.rs"), &exclude_dirs));
+        assert!(!FileWatcher::is_excluded(Path::new("vendor.rs"), &exclude_dirs));
+    }
+
     #[tokio::test]
     async fn test_watch_unwatch() {
         let temp_dir = TempDir::new().unwrap();
         let (tx, _rx) = mpsc::channel(10);
-        let mut watcher = FileWatcher::new(tx).await.unwrap();
+        let mut watcher = FileWatcher::new(tx, 10, Arc::default()).await.unwrap();
 
-        let result = watcher.watch(temp_dir.path()).await;
+        let result = watcher.watch(temp_dir.path(), true).await;
         assert!(result.is_ok());
 
         let paths = watcher.get_watched_paths().await;
@@ -342,6 +853,46 @@ mod tests {
         assert!(!paths.contains(&temp_dir.path().to_path_buf()));
     }
 
+    #[tokio::test]
+    async fn test_restart_watcher() {
+        let temp_dir = TempDir::new().unwrap();
+        let (tx, _rx) = mpsc::channel(10);
+        let mut watcher = FileWatcher::new(tx, 10, Arc::default()).await.unwrap();
+        watcher.watch(temp_dir.path(), true).await.unwrap();
+
+        FileWatcher::restart_watcher(&watcher.watcher, &watcher.notify_tx, &watcher.watcher_failed, &watcher.dropped_events, &watcher.watched_paths)
+            .await
+            .unwrap();
+
+        // The new watcher has the path registered, so unwatching it succeeds
+        assert!(watcher.unwatch(temp_dir.path()).await.is_ok());
+
+        // A path that vanished is dropped instead of failing the restart
+        watcher.watch(temp_dir.path(), true).await.unwrap();
+        drop(temp_dir);
+        FileWatcher::restart_watcher(&watcher.watcher, &watcher.notify_tx, &watcher.watcher_failed, &watcher.dropped_events, &watcher.watched_paths)
+            .await
+            .unwrap();
+        assert!(watcher.get_watched_paths().await.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_full_notify_channel_counts_dropped_events() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let dropped = AtomicU64::new(0);
+        let event = || Event::new(EventKind::Any).add_path(PathBuf::from("src/main.rs"));
+
+        // Nothing drains the channel, so everything past the first event is dropped
+        for _ in 0..3 {
+            FileWatcher::forward_event(&tx, &dropped, event());
+        }
+        assert_eq!(dropped.load(Ordering::Relaxed), 2);
+
+        rx.recv().await.unwrap();
+        FileWatcher::forward_event(&tx, &dropped, event());
+        assert_eq!(dropped.load(Ordering::Relaxed), 2);
+    }
+
     #[tokio::test]
     async fn test_read_file_content() {
         let temp_dir = TempDir::new().unwrap();
@@ -350,8 +901,34 @@ mod tests {
 
         fs::write(&file_path, content).unwrap();
 
-        let result = FileWatcher::read_file_content(&file_path).await;
+        let result = FileWatcher::read_file_content(&file_path, 8 * 1024).await;
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), content);
     }
+
+    #[tokio::test]
+    async fn test_read_file_content_binary_detection() {
+        let temp_dir = TempDir::new().unwrap();
+
+        // Control characters such as form feeds are legitimate in source files
+        let text_path = temp_dir.path().join("paged.c");
+        fs::write(&text_path, "int a;\n\x0c\nint b;\n").unwrap();
+        assert!(FileWatcher::read_file_content(&text_path, 8 * 1024).await.is_ok());
+
+        let binary_path = temp_dir.path().join("blob.c");
+        fs::write(&binary_path, b"\x7fELF\x02\x01\x01\x00\x00\x00").unwrap();
+        assert!(FileWatcher::read_file_content(&binary_path, 8 * 1024).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_file_content_lossy_utf8() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("latin1.py");
+
+        // "# caf\xe9" is Latin-1, not UTF-8
+        fs::write(&file_path, b"# caf\xe9\nprint(1)\n").unwrap();
+
+        let content = FileWatcher::read_file_content(&file_path, 8 * 1024).await.unwrap();
+        assert_eq!(content, "# caf\u{FFFD}\nprint(1)\n");
+    }
 }
\ No newline at end of file